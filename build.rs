@@ -0,0 +1,71 @@
+
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Embeds the build metadata reported by `fastleng --version-json`
+fn main() {
+    println!("cargo:rustc-env=FASTLENG_GIT_COMMIT={}", git_commit().unwrap_or_else(|| "unknown".to_string()));
+    println!("cargo:rustc-env=FASTLENG_BUILD_TIMESTAMP={}", format_timestamp(build_time()));
+    println!("cargo:rustc-env=FASTLENG_RUSTC_VERSION={}", rustc_version().unwrap_or_else(|| "unknown".to_string()));
+
+    //rebuild when the checked out commit changes, not on every source edit
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    for git_path in [".git/HEAD", ".git/refs/heads"] {
+        if Path::new(git_path).exists() {
+            println!("cargo:rerun-if-changed={git_path}");
+        }
+    }
+}
+
+/// Returns the output of a command if it ran successfully
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text: String = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    if text.is_empty() { None } else { Some(text) }
+}
+
+/// Returns the commit being built, or `None` outside of a git checkout (e.g. a crates.io package)
+fn git_commit() -> Option<String> {
+    command_output("git", &["rev-parse", "HEAD"])
+}
+
+/// Returns the version of the compiler doing the build, e.g. "rustc 1.80.0 (051478957 2024-07-21)"
+fn rustc_version() -> Option<String> {
+    let rustc: String = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    command_output(&rustc, &["--version"])
+}
+
+/// Returns the build time in seconds since the epoch, using `SOURCE_DATE_EPOCH` if it is set for a reproducible build
+fn build_time() -> u64 {
+    match std::env::var("SOURCE_DATE_EPOCH").ok().and_then(|epoch| epoch.parse().ok()) {
+        Some(epoch) => epoch,
+        None => SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0)
+    }
+}
+
+/// Formats seconds since the epoch as an RFC 3339 UTC timestamp, e.g. "2024-07-21T12:00:00Z"
+fn format_timestamp(epoch_seconds: u64) -> String {
+    let days: i64 = (epoch_seconds / 86400) as i64;
+    let seconds_of_day: u64 = epoch_seconds % 86400;
+
+    //convert the day count to a civil date (Howard Hinnant's days_from_civil, in reverse)
+    let shifted_days: i64 = days + 719468;
+    let era: i64 = shifted_days.div_euclid(146097);
+    let day_of_era: i64 = shifted_days - era * 146097;
+    let year_of_era: i64 = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year: i64 = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index: i64 = (5 * day_of_year + 2) / 153;
+    let day: i64 = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month: i64 = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year: i64 = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, seconds_of_day / 3600, seconds_of_day % 3600 / 60, seconds_of_day % 60
+    )
+}
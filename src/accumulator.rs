@@ -0,0 +1,151 @@
+
+use std::collections::BTreeMap;
+
+use crate::fastx_loader::stream_multifastx_lengths;
+use crate::length_stats::{compute_length_stats, compute_total_counts, LengthStats};
+use crate::loader::LoaderConfig;
+
+/// A running set of length counts that files and individual lengths can be added to over time, with the stats available at any point.
+/// This is the stateful form of the `gather_*_with_seed(...)` functions, for long-running callers such as a service processing files as they arrive.
+///
+/// # Thread safety
+/// The accumulator is `Send` and `Sync`, but adding to it requires `&mut self`, so it is shared between threads behind a lock such as `Mutex<LengthAccumulator>`.
+/// The `distinct_length_warning` of the config is checked as the counts grow, and is logged at most once.
+/// Loading a file can take a long time, so rather than holding the lock during `add_file(...)`, each file can be loaded into its own accumulator and then combined with `merge(...)`.
+/// # Examples
+/// ```
+/// use fastleng::accumulator::LengthAccumulator;
+/// let mut accumulator = LengthAccumulator::new();
+/// accumulator.add_file("./test_data/five_strings.fa").unwrap();
+/// accumulator.add_length(100);
+/// let summary_stats = accumulator.snapshot();
+/// assert_eq!(summary_stats.total_sequences, 6);
+/// assert_eq!(summary_stats.total_bases, 115);
+/// assert_eq!(accumulator.histogram().get(&100), Some(&1));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct LengthAccumulator {
+    /// The loading options used by `add_file(...)`
+    config: LoaderConfig,
+    /// The length counts added so far
+    length_counts: BTreeMap<usize, u64>
+}
+
+impl LengthAccumulator {
+    /// Creates an empty accumulator that loads files with the default `LoaderConfig`
+    pub fn new() -> LengthAccumulator {
+        Default::default()
+    }
+
+    /// Creates an empty accumulator that loads files with `config`.
+    /// The per-record options and the length filters are applied to each file, but `sampling` is not.
+    pub fn with_config(config: LoaderConfig) -> LengthAccumulator {
+        LengthAccumulator {
+            config,
+            length_counts: BTreeMap::new()
+        }
+    }
+
+    /// Creates an accumulator that starts from existing length counts, e.g. from a previous run
+    pub fn from_counts(length_counts: BTreeMap<usize, u64>) -> LengthAccumulator {
+        LengthAccumulator {
+            config: Default::default(),
+            length_counts
+        }
+    }
+
+    /// This will load the lengths of every record in a fastx or BAM/SAM file and add them to the counts.
+    /// The file is loaded completely before anything is added, so a file that fails part way through leaves the counts unchanged.
+    /// Returns the number of sequences added.
+    /// # Arguments
+    /// * `filename` - the file to load, which can be anything `loader::gather_stats(...)` supports
+    pub fn add_file(&mut self, filename: &str) -> Result<u64, Box<dyn std::error::Error>> {
+        let mut file_counts: BTreeMap<usize, u64> = BTreeMap::new();
+        stream_multifastx_lengths(&[filename], &self.config, |_seq_id, seq_len| {
+            if self.config.passes_length_filters(seq_len) {
+                self.config.count_length(&mut file_counts, seq_len);
+            }
+            Ok(())
+        })?;
+        let (_file_bases, file_seqs) = compute_total_counts(&file_counts);
+        self.merge(&file_counts);
+        Ok(file_seqs)
+    }
+
+    /// Adds a single sequence of length `seq_len`; no filters are applied
+    pub fn add_length(&mut self, seq_len: usize) {
+        self.config.count_length(&mut self.length_counts, seq_len);
+    }
+
+    /// Adds all of the counts in `other_counts`, e.g. the `histogram()` of another accumulator
+    pub fn merge(&mut self, other_counts: &BTreeMap<usize, u64>) {
+        self.config.merge_counts(&mut self.length_counts, other_counts);
+    }
+
+    /// Computes the summary statistics of everything added so far; this walks the counts, so it is not free for very large inputs
+    pub fn snapshot(&self) -> LengthStats {
+        compute_length_stats(&self.length_counts)
+    }
+
+    /// Returns the length counts added so far, with the sequence length as the key and the number of sequences with that length as the value
+    pub fn histogram(&self) -> &BTreeMap<usize, u64> {
+        &self.length_counts
+    }
+
+    /// Consumes the accumulator, returning the length counts
+    pub fn into_histogram(self) -> BTreeMap<usize, u64> {
+        self.length_counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::loader::gather_multi_stats;
+
+    #[test]
+    fn test_accumulator_files() {
+        //adding files one at a time matches loading them all at once
+        let filenames = ["./test_data/five_strings.fa", "./test_data/five_strings.sam", "./test_data/long_strings.fa"];
+        let mut accumulator = LengthAccumulator::new();
+        for filename in filenames.iter() {
+            assert_eq!(accumulator.add_file(filename).unwrap(), gather_multi_stats(&[filename], &LoaderConfig::new()).unwrap().values().sum::<u64>());
+        }
+        let expected: BTreeMap<usize, u64> = gather_multi_stats(&filenames, &LoaderConfig::new()).unwrap();
+        assert_eq!(accumulator.histogram(), &expected);
+        assert_eq!(accumulator.snapshot(), compute_length_stats(&expected));
+
+        //a failed file adds nothing
+        assert!(accumulator.add_file("./test_data/panic_file.fa").is_err());
+        assert!(accumulator.add_file("./test_data/does_not_exist.fa").is_err());
+        assert_eq!(accumulator.into_histogram(), expected);
+    }
+
+    #[test]
+    fn test_accumulator_lengths() {
+        let mut accumulator = LengthAccumulator::from_counts([(5, 1)].iter().cloned().collect());
+        accumulator.add_length(5);
+        accumulator.add_length(10);
+        let mut other_accumulator = LengthAccumulator::new();
+        other_accumulator.add_length(10);
+        accumulator.merge(other_accumulator.histogram());
+        let expected: BTreeMap<usize, u64> = [(5, 2), (10, 2)].iter().cloned().collect();
+        assert_eq!(accumulator.histogram(), &expected);
+
+        //the filters from the config apply to files only
+        let mut filtered_accumulator = LengthAccumulator::with_config(LoaderConfig::new().min_length(3));
+        assert_eq!(filtered_accumulator.add_file("./test_data/five_strings.fa").unwrap(), 3);
+        filtered_accumulator.add_length(1);
+        assert_eq!(filtered_accumulator.snapshot().total_sequences, 4);
+
+        //empty accumulators still have (empty) stats
+        assert_eq!(LengthAccumulator::new().snapshot().total_sequences, 0);
+    }
+
+    #[test]
+    fn test_accumulator_thread_safety() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<LengthAccumulator>();
+    }
+}
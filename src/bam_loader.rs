@@ -1,7 +1,13 @@
 
 use log::{info, warn};
-use rust_htslib::{bam, bam::Read};
-use std::collections::BTreeMap;
+use rust_htslib::bam;
+use rust_htslib::bam::record::{Aux, Cigar};
+use std::collections::{BTreeMap, HashSet};
+use url::Url;
+
+use crate::accumulator::LengthAccumulator;
+use crate::format::{is_remote_url, is_stream_input, resolve_format};
+use crate::loader::{KeyedLengthCounts, LoaderConfig};
 
 /// This is the main function for gathering all sequence lengths for a fastx file into a BTreeMap.
 /// # Arguments
@@ -17,6 +23,40 @@ pub fn gather_bam_stats(filename: &str) -> Result<BTreeMap<usize, u64>, Box<dyn
     gather_bam_stats_with_seed(filename, None)
 }
 
+/// Options controlling how each BAM/SAM record is converted into a sequence length.
+#[derive(Clone, Debug, Default)]
+pub struct BamLoadOptions {
+    /// If true, hard-clipped bases (`H` CIGAR operations) are added back to the SEQ length to recover the original read length.
+    /// Soft-clipped bases are always counted since they are retained in the SEQ field.
+    pub include_hardclips: bool,
+    /// If true, only the primary record of each read name is counted so every template contributes exactly one length.
+    /// Memory cost: every unique read name is retained in a `HashSet` for the duration of the file.
+    pub unique_reads: bool,
+    /// If set, only the records overlapping this samtools-style region (e.g. `chr1:1000-2000` or `chr1`) are counted.
+    /// This requires a coordinate-sorted BAM/CRAM with an index (e.g. from `samtools index`).
+    pub region: Option<String>
+}
+
+/// This will gather the sequence lengths of only the records overlapping a region of an indexed BAM/CRAM file.
+/// The index (`.bai`, `.csi`, or `.crai`) is found next to the file, and the load fails if there is not one.
+/// # Arguments
+/// * `filename` - the indexed alignment file to read sequences from
+/// * `region` - a samtools-style region, e.g. `chr1:1000-2000` (1-based and inclusive) or just `chr1`
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::bam_loader::gather_bam_stats_region;
+/// let counts: BTreeMap<usize, u64> = gather_bam_stats_region("./test_data/aligned_strings.bam", "chr1:1000-2000").unwrap();
+/// assert_eq!(counts, [(20, 1), (30, 1)].iter().cloned().collect());
+/// ```
+pub fn gather_bam_stats_region(filename: &str, region: &str) -> Result<BTreeMap<usize, u64>, Box<dyn std::error::Error>> {
+    let options = BamLoadOptions {
+        region: Some(region.to_string()),
+        ..Default::default()
+    };
+    gather_bam_stats_with_options(filename, None, &options)
+}
+
 /// This will gather sequence lengths from a filename and add them to a provided BTreeMap (`initial_counts`).
 /// # Arguments
 /// * `filename` - the filename to read sequences from
@@ -30,41 +70,247 @@ pub fn gather_bam_stats(filename: &str) -> Result<BTreeMap<usize, u64>, Box<dyn
 /// let counts: BTreeMap<usize, u64> = gather_bam_stats_with_seed(&filename, Some(initial_counts)).unwrap();
 /// ```
 pub fn gather_bam_stats_with_seed(filename: &str, initial_counts: Option<BTreeMap<usize, u64>>) -> Result<BTreeMap<usize, u64>, Box<dyn std::error::Error>> {
-    //create an empty stats file (or use initial counts) and ready the reader
-    let mut hash_stats: BTreeMap<usize, u64> = match initial_counts {
-        Some(ic) => ic,
-        None => BTreeMap::new()
+    gather_bam_stats_with_options(filename, initial_counts, &BamLoadOptions::default())
+}
+
+/// This will gather sequence lengths from a filename using the provided `options` and add them to a provided BTreeMap (`initial_counts`).
+/// # Arguments
+/// * `filename` - the filename to read sequences from
+/// * `initial_counts` - if provided, this will use that BTreeMap as the inital counts, otherwise it will create an empty one
+/// * `options` - controls how each record length is calculated
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::bam_loader::{gather_bam_stats_with_options, BamLoadOptions};
+/// let filename = "./test_data/hardclip_strings.sam";
+/// let options = BamLoadOptions { include_hardclips: true, ..Default::default() };
+/// let counts: BTreeMap<usize, u64> = gather_bam_stats_with_options(&filename, None, &options).unwrap();
+/// assert_eq!(counts.get(&8), Some(&2));
+/// ```
+pub fn gather_bam_stats_with_options(filename: &str, initial_counts: Option<BTreeMap<usize, u64>>, options: &BamLoadOptions) -> Result<BTreeMap<usize, u64>, Box<dyn std::error::Error>> {
+    //create an empty accumulator (or use initial counts) and ready the reader
+    let mut accumulator = LengthAccumulator::from_counts(initial_counts.unwrap_or_default());
+    let config = LoaderConfig {
+        bam_options: options.clone(),
+        ..Default::default()
     };
-    let mut reader = bam::Reader::from_path(filename)?;
+    stream_bam_lengths(filename, &config, |_qname, seq_len| {
+        accumulator.add_length(seq_len);
+        Ok(())
+    })?;
+
+    //return the full count list now
+    Ok(accumulator.into_histogram())
+}
+
+/// This will stream through a BAM/SAM file and call `callback` with the read name and length of each counted record.
+/// The file may also be a remote URL, see `format::is_remote_url(...)`, and `bam_options.region` restricts the records to a region of an indexed file.
+/// Gzip (or BGZF) compressed SAM files such as `reads.sam.gz` are decompressed by htslib itself, so they need no special handling here.
+/// Nothing is retained between records (unless `bam_options.unique_reads` is set), so memory usage stays flat regardless of file size.
+/// Returns the number of records passed to `callback`.
+/// # Arguments
+/// * `filename` - the filename to read sequences from
+/// * `config` - the per-file options to apply; `bam_options` controls how each record length is calculated, and `head` and `max_records` limit the number of records
+/// * `callback` - called with the read name and sequence length for each record
+/// # Examples
+/// ```
+/// use fastleng::bam_loader::stream_bam_lengths;
+/// use fastleng::loader::LoaderConfig;
+/// let filename = "./test_data/five_strings.sam";
+/// let mut lengths: Vec<usize> = vec![];
+/// let count = stream_bam_lengths(&filename, &LoaderConfig::new(), |_qname, seq_len| {
+///     lengths.push(seq_len);
+///     Ok(())
+/// }).unwrap();
+/// assert_eq!(count, 5);
+/// assert_eq!(lengths, vec![1, 2, 3, 4, 5]);
+/// ```
+pub fn stream_bam_lengths<F>(filename: &str, config: &LoaderConfig, mut callback: F) -> Result<usize, Box<dyn std::error::Error>>
+where
+    F: FnMut(&[u8], usize) -> Result<(), Box<dyn std::error::Error>>
+{
+    stream_bam_file(filename, config, |record, seq_len| callback(record.qname(), seq_len))
+}
+
+/// The read group that records are placed in when they do not have an `RG` tag
+pub const NO_READ_GROUP: &str = "no_read_group";
+
+/// Returns the read group of a record, which is the value of its `RG` aux tag or `NO_READ_GROUP` if it does not have one.
+/// # Arguments
+/// * `record` - the BAM record to inspect
+pub fn read_group_name(record: &bam::Record) -> String {
+    match record.aux(b"RG") {
+        Ok(Aux::String(read_group)) => read_group.to_string(),
+        _ => NO_READ_GROUP.to_string()
+    }
+}
+
+/// This will load the lengths of the records in one or more BAM/SAM files, separated by the read group in each record's `RG` tag.
+/// Records without an `RG` tag are counted in `NO_READ_GROUP`, and the same read group in different files is counted together.
+/// The length filters and sampling in `config` are applied, and the per-group counts can be combined with `merge_length_counts(...)` to get the overall counts.
+/// # Arguments
+/// * `filenames` - the BAM/SAM/CRAM filenames to read records from; any other format is an error
+/// * `config` - the loading options and filters to apply
+/// # Examples
+/// ```
+/// use fastleng::bam_loader::gather_read_group_stats;
+/// use fastleng::loader::LoaderConfig;
+/// let read_group_counts = gather_read_group_stats(&["./test_data/read_groups.sam"], &LoaderConfig::new()).unwrap();
+/// assert_eq!(read_group_counts["run1"].len(), 2);
+/// assert_eq!(read_group_counts["run2"][&2], 1);
+/// assert_eq!(read_group_counts["no_read_group"][&3], 1);
+/// ```
+pub fn gather_read_group_stats<T: AsRef<str> + std::fmt::Debug>(filenames: &[T], config: &LoaderConfig) -> Result<KeyedLengthCounts<String>, Box<dyn std::error::Error>> {
+    let mut rng = config.sampling.as_ref().map(|sampling| sampling.build_rng());
+    let mut total_count: usize = 0;
+    let mut kept_count: u64 = 0;
+    let mut read_group_stats: KeyedLengthCounts<String> = BTreeMap::new();
+    let mut distinct_lengths: usize = 0;
+    for filename in filenames.iter() {
+        if is_stream_input(filename.as_ref()) || !resolve_format(filename.as_ref(), config.format_detection)?.is_alignment() {
+            return Err(format!("Read groups can only be loaded from BAM/SAM/CRAM files: {:?}", filename).into());
+        }
+        total_count += stream_bam_file(filename.as_ref(), config, |record, seq_len| {
+            //the sampling draw happens for every record so the sample does not depend on the filters
+            let sampled: bool = match (rng.as_mut(), config.sampling.as_ref()) {
+                (Some(rng), Some(sampling)) => rng.next_f64() < sampling.fraction,
+                _ => true
+            };
+            if sampled && config.passes_length_filters(seq_len) {
+                let hash_stats: &mut BTreeMap<usize, u64> = read_group_stats.entry(read_group_name(record)).or_default();
+                config.count_keyed_length(hash_stats, seq_len, &mut distinct_lengths);
+                kept_count += 1;
+            }
+            Ok(())
+        }).map_err(|e| format!("Error while parsing BAM file {:?}: {}", filename, e))?;
+    }
+    if config.sampling.is_some() || config.min_length.is_some() || config.max_length.is_some() {
+        info!("Kept {} of {} sequences.", kept_count, total_count);
+    }
+    info!("Found {} read groups.", read_group_stats.len());
+    Ok(read_group_stats)
+}
+
+/// Opens a BAM/SAM file (or the region of one) and streams its records, see `stream_bam_lengths(...)`
+fn stream_bam_file<F>(filename: &str, config: &LoaderConfig, callback: F) -> Result<usize, Box<dyn std::error::Error>>
+where
+    F: FnMut(&bam::Record, usize) -> Result<(), Box<dyn std::error::Error>>
+{
+    //URLs go straight to htslib, which streams just the data it needs instead of downloading the whole file
+    if let Some(region) = config.bam_options.region.as_ref() {
+        let indexed_result = if is_remote_url(filename) {
+            bam::IndexedReader::from_url(&Url::parse(filename)?)
+        } else {
+            bam::IndexedReader::from_path(filename)
+        };
+        let mut reader = indexed_result.map_err(|e| format!(
+            "Failed to open an index for {:?}, a region can only be read from a coordinate-sorted BAM/CRAM with an index ({})",
+            filename, e
+        ))?;
+        reader.fetch(region.as_str()).map_err(|e| format!("Invalid region {:?} for {:?} ({})", region, filename, e))?;
+        info!("Restricting to region {:?}", region);
+        stream_bam_records(&mut reader, filename, config, callback)
+    } else {
+        let mut reader = if is_remote_url(filename) {
+            bam::Reader::from_url(&Url::parse(filename)?)?
+        } else {
+            bam::Reader::from_path(filename)?
+        };
+        stream_bam_records(&mut reader, filename, config, callback)
+    }
+}
+
+/// Streams the records of an already opened (and possibly fetched) reader, see `stream_bam_lengths(...)`
+fn stream_bam_records<R, F>(reader: &mut R, filename: &str, config: &LoaderConfig, mut callback: F) -> Result<usize, Box<dyn std::error::Error>>
+where
+    R: bam::Read,
+    F: FnMut(&bam::Record, usize) -> Result<(), Box<dyn std::error::Error>>
+{
+    let options: &BamLoadOptions = &config.bam_options;
 
     //go through all the records
     let mut warning_triggered = false;
+    let mut missing_seq_count: usize = 0;
+    let mut seen_names: HashSet<Vec<u8>> = HashSet::new();
     let mut count: usize = 0;
+    let mut skipped: usize = 0;
     info!("Loading file \"{}\"...", filename);
     for read_entry in reader.records() {
+        if config.head == Some(count) {
+            warn!("Stopped reading after the first {} records: {}", count, filename);
+            break;
+        }
+
         //all we care about is the sequence length
         let record = read_entry?;
-        let seq_len: usize = record.seq_len();
 
-        if !warning_triggered && !record.is_unmapped() {
-            // user gave us an aligned file, spit out a one-time warning
-            warn!("Detected aligned reads, this is not properly handled: {filename}");
-            warning_triggered = true;
+        if record.seq_len() == 0 {
+            //SEQ is "*" (e.g. secondary alignments or some unmapped mates), so there is no length to count
+            if missing_seq_count == 0 {
+                warn!("Detected records without a sequence (SEQ is \"*\"), these will be skipped: {filename}");
+            }
+            missing_seq_count += 1;
+            continue;
+        }
+
+        if options.unique_reads {
+            //secondary/supplementary records are never the primary length; otherwise only the first record for a name counts
+            if record.is_secondary() || record.is_supplementary() || !seen_names.insert(record.qname().to_vec()) {
+                skipped += 1;
+                continue;
+            }
+        }
+        if config.max_records == Some(count) {
+            return Err(format!("File contains more than the maximum of {} records: {:?}", count, filename).into());
+        }
+
+        let mut seq_len: usize = record.seq_len();
+
+        if !record.is_unmapped() {
+            if !warning_triggered {
+                // user gave us an aligned file, spit out a one-time warning
+                match (options.include_hardclips, options.unique_reads) {
+                    (true, true) => warn!("Detected aligned reads, hard clips will be restored and only the primary alignment of each read is counted: {filename}"),
+                    (true, false) => warn!("Detected aligned reads, hard clips will be restored but secondary alignments are still counted: {filename}"),
+                    (false, true) => warn!("Detected aligned reads, only the primary alignment of each read is counted but hard clips are not restored (see --include-hardclips): {filename}"),
+                    (false, false) => warn!("Detected aligned reads, this is not properly handled (see --include-hardclips): {filename}")
+                }
+                warning_triggered = true;
+            }
+
+            if options.include_hardclips {
+                seq_len += compute_hardclip_length(&record);
+            }
         }
         
-        //insert 0 if absent; then increment
-        let len_count: &mut u64 = hash_stats.entry(seq_len).or_insert(0);
-        *len_count += 1;
+        callback(&record, seq_len)?;
         
         count += 1;
-        if count % 1000000 == 0 {
+        if config.is_progress_point(count) {
             info!("Processed {} sequences", count);
         }
     }
+    if options.unique_reads {
+        info!("Skipped {} non-primary or repeated records.", skipped);
+    }
+    if missing_seq_count > 0 {
+        info!("Skipped {} records without a sequence.", missing_seq_count);
+    }
     info!("Finished loading file with {} sequences.", count);
+    Ok(count)
+}
 
-    //return the full count list now
-    Ok(hash_stats)
+/// Returns the total number of hard-clipped bases in a record's CIGAR string.
+/// These bases are removed from the SEQ field, so they must be added back to get the original read length.
+/// # Arguments
+/// * `record` - the BAM record to inspect
+fn compute_hardclip_length(record: &bam::Record) -> usize {
+    record.cigar().iter()
+        .map(|op| match op {
+            Cigar::HardClip(l) => *l as usize,
+            _ => 0
+        })
+        .sum()
 }
 
 #[cfg(test)]
@@ -73,6 +319,7 @@ mod tests {
 
     // allows us to test a bunch at once
     use crate::fastx_loader::gather_multifastx_stats;
+    use crate::length_stats::merge_length_counts;
     
     /// This one is a single sequence "A"
     fn stats_basic_bam() -> BTreeMap<usize, u64> {
@@ -119,7 +366,7 @@ mod tests {
         let expected = stats_basic_bam();
 
         //now do it for real
-        let hash_stats = gather_bam_stats(&filename).unwrap();
+        let hash_stats = gather_bam_stats(filename).unwrap();
         assert_eq!(hash_stats, expected);
     }
 
@@ -132,7 +379,20 @@ mod tests {
         let expected = stats_basic_bam2();
 
         //now do it for real
-        let hash_stats = gather_bam_stats(&filename).unwrap();
+        let hash_stats = gather_bam_stats(filename).unwrap();
+        assert_eq!(hash_stats, expected);
+    }
+
+    #[test]
+    fn test_basic_cram2() {
+        //same records as five_strings.sam, just stored as CRAM
+        let filename = "./test_data/five_strings.cram";
+
+        //get the expected outputs
+        let expected = stats_basic_bam2();
+
+        //now do it for real
+        let hash_stats = gather_bam_stats(filename).unwrap();
         assert_eq!(hash_stats, expected);
     }
 
@@ -145,7 +405,7 @@ mod tests {
         let expected = stats_basic_bam3();
 
         //now do it for real
-        let hash_stats = gather_bam_stats(&filename).unwrap();
+        let hash_stats = gather_bam_stats(filename).unwrap();
         assert_eq!(hash_stats, expected);
     }
 
@@ -158,7 +418,7 @@ mod tests {
         let expected = stats_basic_bam4();
 
         //now do it for real
-        let hash_stats = gather_bam_stats(&filename).unwrap();
+        let hash_stats = gather_bam_stats(filename).unwrap();
         assert_eq!(hash_stats, expected);
     }
 
@@ -171,15 +431,141 @@ mod tests {
         let expected = stats_basic_bam4();
 
         //now do it for real
-        let hash_stats = gather_bam_stats(&filename).unwrap();
+        let hash_stats = gather_bam_stats(filename).unwrap();
         assert_eq!(hash_stats, expected);
     }
 
+    /// aligned reads with hard clips, counted as SEQ length only
+    fn stats_hardclip_sam() -> BTreeMap<usize, u64> {
+        let mut results: BTreeMap<usize, u64> = BTreeMap::new();
+        results.insert(3, 1);
+        results.insert(5, 2);
+        results.insert(6, 1);
+        results
+    }
+
+    /// aligned reads with hard clips restored
+    fn stats_hardclip_sam_restored() -> BTreeMap<usize, u64> {
+        let mut results: BTreeMap<usize, u64> = BTreeMap::new();
+        results.insert(3, 1);
+        results.insert(5, 1);
+        results.insert(8, 2);
+        results
+    }
+
+    #[test]
+    fn test_hardclip_sam() {
+        let filename = "./test_data/hardclip_strings.sam";
+
+        //default behavior ignores the hard clips
+        let hash_stats = gather_bam_stats(filename).unwrap();
+        assert_eq!(hash_stats, stats_hardclip_sam());
+
+        //now restore them
+        let options = BamLoadOptions { include_hardclips: true, ..Default::default() };
+        let hash_stats = gather_bam_stats_with_options(filename, None, &options).unwrap();
+        assert_eq!(hash_stats, stats_hardclip_sam_restored());
+    }
+
+    /// multiple records per read name, including secondary, supplementary, and paired records
+    fn stats_duplicate_names_sam() -> BTreeMap<usize, u64> {
+        let mut results: BTreeMap<usize, u64> = BTreeMap::new();
+        results.insert(3, 1);
+        results.insert(4, 1);
+        results.insert(5, 1);
+        results.insert(6, 2);
+        results.insert(8, 1);
+        results.insert(10, 1);
+        results
+    }
+
+    /// same as above, but one length per read name
+    fn stats_duplicate_names_sam_unique() -> BTreeMap<usize, u64> {
+        let mut results: BTreeMap<usize, u64> = BTreeMap::new();
+        results.insert(3, 1);
+        results.insert(4, 1);
+        results.insert(8, 1);
+        results.insert(10, 1);
+        results
+    }
+
+    #[test]
+    fn test_unique_reads_sam() {
+        let filename = "./test_data/duplicate_names.sam";
+
+        //default behavior counts every record
+        let hash_stats = gather_bam_stats(filename).unwrap();
+        assert_eq!(hash_stats, stats_duplicate_names_sam());
+
+        //now only the primary of each name
+        let options = BamLoadOptions { unique_reads: true, ..Default::default() };
+        let hash_stats = gather_bam_stats_with_options(filename, None, &options).unwrap();
+        assert_eq!(hash_stats, stats_duplicate_names_sam_unique());
+    }
+
+    #[test]
+    fn test_missing_seq() {
+        //a secondary alignment and an unmapped read both have SEQ "*", which should not show up as length 0
+        let expected: BTreeMap<usize, u64> = [(3, 1), (5, 1), (8, 1)].iter().cloned().collect();
+        assert_eq!(gather_bam_stats("./test_data/missing_seq.bam").unwrap(), expected);
+        assert_eq!(gather_bam_stats("./test_data/missing_seq.sam").unwrap(), expected);
+
+        //they are not records for the purposes of head either
+        let config = LoaderConfig::new().head(2);
+        let mut lengths: Vec<usize> = vec![];
+        stream_bam_lengths("./test_data/missing_seq.bam", &config, |_qname, seq_len| {
+            lengths.push(seq_len);
+            Ok(())
+        }).unwrap();
+        assert_eq!(lengths, vec![5, 8]);
+    }
+
+    #[test]
+    fn test_region() {
+        let filename = "./test_data/aligned_strings.bam";
+        let expected: BTreeMap<usize, u64> = [(20, 1), (30, 1)].iter().cloned().collect();
+        assert_eq!(gather_bam_stats_region(filename, "chr1:1000-2000").unwrap(), expected);
+
+        //a whole contig, and a region touching a single read at its first base
+        let expected: BTreeMap<usize, u64> = [(50, 1)].iter().cloned().collect();
+        assert_eq!(gather_bam_stats_region(filename, "chr2").unwrap(), expected);
+        let expected: BTreeMap<usize, u64> = [(10, 1)].iter().cloned().collect();
+        assert_eq!(gather_bam_stats_region(filename, "chr1:1-100").unwrap(), expected);
+
+        //the region must exist in the header
+        assert!(gather_bam_stats_region(filename, "chr3:1-100").is_err());
+
+        //without a region every record is counted, including the unmapped one
+        let all_counts = gather_bam_stats(filename).unwrap();
+        assert_eq!(all_counts.values().sum::<u64>(), 6);
+        assert_eq!(all_counts, gather_bam_stats("./test_data/aligned_strings.sam").unwrap());
+
+        //the loader config applies the region to every file, and FASTX files cannot be restricted
+        let config = LoaderConfig::new().region("chr1:1000-2000");
+        let expected: BTreeMap<usize, u64> = [(20, 2), (30, 2)].iter().cloned().collect();
+        assert_eq!(crate::loader::gather_multi_stats(&[filename, filename], &config).unwrap(), expected);
+        assert!(crate::loader::gather_multi_stats(&[filename, "./test_data/five_strings.fa"], &config).is_err());
+    }
+
+    #[test]
+    fn test_region_without_index() {
+        //the same file but with no index next to it
+        let unindexed_bam = std::env::temp_dir().join("fastleng_test_unindexed.bam");
+        std::fs::copy("./test_data/aligned_strings.bam", &unindexed_bam).unwrap();
+        let region_result = gather_bam_stats_region(unindexed_bam.to_str().unwrap(), "chr1");
+        std::fs::remove_file(&unindexed_bam).unwrap();
+        let error_message: String = region_result.unwrap_err().to_string();
+        assert!(error_message.contains("index"), "{error_message}");
+
+        //text SAM files cannot be indexed at all
+        assert!(gather_bam_stats_region("./test_data/aligned_strings.sam", "chr1").is_err());
+    }
+
     #[test]
     #[should_panic]
     fn test_error_handling() {
         let filename = "./test_data/panic_file.fa";
-        let _hash_stats = gather_bam_stats(&filename).unwrap();
+        let _hash_stats = gather_bam_stats(filename).unwrap();
     }
 
     #[test]
@@ -213,6 +599,37 @@ mod tests {
         assert_eq!(hash_stats, expected);
     }
 
+    #[test]
+    fn test_multiformat() {
+        //every supported format in one run, each file is routed to the right loader
+        let filenames = [
+            "./test_data/five_strings.sam",
+            "./test_data/five_strings.cram",
+            "./test_data/five_strings.fq",
+            "./test_data/long_strings.bam",
+            "./test_data/long_strings.fa.gz"
+        ];
+
+        //get the expected outputs
+        let expected_list = [
+            stats_basic_bam2(),
+            stats_basic_bam2(),
+            stats_basic_bam2(),
+            stats_basic_bam4(),
+            stats_basic_bam4()
+        ];
+
+        //sum the expected outputs
+        let mut expected: BTreeMap<usize, u64> = BTreeMap::new();
+        for results in expected_list.iter() {
+            merge_length_counts(&mut expected, results);
+        }
+
+        //now do it for real
+        let hash_stats = gather_multifastx_stats(&filenames).unwrap();
+        assert_eq!(hash_stats, expected);
+    }
+
     #[test]
     #[should_panic]
     fn test_multifastx_error_handling() {
@@ -222,4 +639,46 @@ mod tests {
         ];
         let _hash_stats = gather_multifastx_stats(&filenames).unwrap();
     }
+
+    #[test]
+    fn test_read_group_stats() {
+        let filenames = ["./test_data/read_groups.sam"];
+        let read_group_counts = gather_read_group_stats(&filenames, &LoaderConfig::new()).unwrap();
+        let mut expected: KeyedLengthCounts<String> = BTreeMap::new();
+        expected.insert("run1".to_string(), [(4, 1), (6, 1)].iter().cloned().collect());
+        expected.insert("run2".to_string(), [(2, 1)].iter().cloned().collect());
+        expected.insert(NO_READ_GROUP.to_string(), [(3, 1)].iter().cloned().collect());
+        assert_eq!(read_group_counts, expected);
+
+        //the groups together are the normal counts
+        let mut merged: BTreeMap<usize, u64> = BTreeMap::new();
+        for counts in read_group_counts.values() {
+            merge_length_counts(&mut merged, counts);
+        }
+        assert_eq!(merged, gather_multifastx_stats(&filenames).unwrap());
+
+        //files without any RG tags are all in the default group, and filters remove emptied groups
+        let untagged_counts = gather_read_group_stats(&["./test_data/five_strings.sam"], &LoaderConfig::new().min_length(3)).unwrap();
+        assert_eq!(untagged_counts.keys().collect::<Vec<&String>>(), vec![NO_READ_GROUP]);
+        assert_eq!(untagged_counts[NO_READ_GROUP].len(), 3);
+        let filtered_counts = gather_read_group_stats(&filenames, &LoaderConfig::new().min_length(4)).unwrap();
+        assert_eq!(filtered_counts.keys().collect::<Vec<&String>>(), vec!["run1"]);
+        assert!(gather_read_group_stats(&["./test_data/does_not_exist.sam"], &LoaderConfig::new()).is_err());
+        assert!(gather_read_group_stats(&["./test_data/five_strings.fa"], &LoaderConfig::new()).is_err());
+    }
+
+    #[test]
+    fn test_gzipped_sam() {
+        //htslib detects the gzip compression on its own
+        let filename = "./test_data/five_strings.sam.gz";
+        assert_eq!(gather_bam_stats(filename).unwrap(), gather_bam_stats("./test_data/five_strings.sam").unwrap());
+        assert_eq!(resolve_format(filename, Default::default()).unwrap(), crate::format::InputFormat::Sam);
+        let mut names: Vec<Vec<u8>> = vec![];
+        stream_bam_lengths(filename, &LoaderConfig::new().head(2), |qname, _seq_len| {
+            names.push(qname.to_vec());
+            Ok(())
+        }).unwrap();
+        assert_eq!(names, vec![b"m64109_200805_204709/1/ccs".to_vec(), b"m64109_200805_204709/2/ccs".to_vec()]);
+        assert_eq!(gather_multifastx_stats(&[filename, "./test_data/five_strings.fa"]).unwrap().values().sum::<u64>(), 10);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,270 @@
+
+use flate2::read::MultiGzDecoder;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::thread::JoinHandle;
+
+/// The default number of BGZF blocks decompressed together by one thread, about 4 MB of decompressed data
+pub const DEFAULT_BLOCKS_PER_BATCH: usize = 64;
+
+/// The length of the fixed part of a gzip member header, up to and including `XLEN`
+const GZIP_HEADER_LENGTH: usize = 12;
+
+/// This will check whether a file is BGZF compressed, i.e. a gzip file whose first member records its own compressed size in a `BC` extra field.
+/// Plain gzip files (and uncompressed files) return false, since they can only be decompressed sequentially.
+/// # Arguments
+/// * `filename` - the file to check
+/// # Examples
+/// ```
+/// use fastleng::bgzf::is_bgzf_file;
+/// assert!(is_bgzf_file("./test_data/bgzf_strings.fq.gz").unwrap());
+/// assert!(!is_bgzf_file("./test_data/long_strings.fa.gz").unwrap());
+/// assert!(!is_bgzf_file("./test_data/long_strings.fa").unwrap());
+/// ```
+pub fn is_bgzf_file(filename: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut reader = BufReader::new(File::open(filename)?);
+    Ok(matches!(read_block_header(&mut reader), Ok(Some(_))))
+}
+
+/// Reads the header of the next BGZF block, returning the header bytes and the total size of the block, or `None` at the end of the data
+fn read_block_header<R: Read>(reader: &mut R) -> std::io::Result<Option<(Vec<u8>, usize)>> {
+    let mut header: Vec<u8> = vec![0; GZIP_HEADER_LENGTH];
+    let bytes_read: usize = read_fully(reader, &mut header)?;
+    if bytes_read == 0 {
+        return Ok(None);
+    }
+    if bytes_read < GZIP_HEADER_LENGTH || header[0..4] != [0x1f, 0x8b, 0x08, 0x04] {
+        return Err(invalid_data("Expected a BGZF block header"));
+    }
+
+    //the block size is in the "BC" subfield of the extra field
+    let extra_length: usize = u16::from_le_bytes([header[10], header[11]]) as usize;
+    header.resize(GZIP_HEADER_LENGTH + extra_length, 0);
+    if read_fully(reader, &mut header[GZIP_HEADER_LENGTH..])? < extra_length {
+        return Err(invalid_data("Truncated BGZF block header"));
+    }
+    let mut subfield_start: usize = GZIP_HEADER_LENGTH;
+    while subfield_start + 4 <= header.len() {
+        let subfield_length: usize = u16::from_le_bytes([header[subfield_start + 2], header[subfield_start + 3]]) as usize;
+        if header[subfield_start..subfield_start + 2] == *b"BC" && subfield_length == 2 && subfield_start + 6 <= header.len() {
+            let block_size: usize = u16::from_le_bytes([header[subfield_start + 4], header[subfield_start + 5]]) as usize + 1;
+            if block_size < header.len() {
+                return Err(invalid_data("BGZF block is smaller than its header"));
+            }
+            return Ok(Some((header, block_size)));
+        }
+        subfield_start += 4 + subfield_length;
+    }
+    Err(invalid_data("Gzip member is missing the BGZF block size"))
+}
+
+/// Fills as much of `buffer` as possible, returning fewer bytes than requested only at the end of the data
+fn read_fully<R: Read>(reader: &mut R, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut bytes_read: usize = 0;
+    while bytes_read < buffer.len() {
+        match reader.read(&mut buffer[bytes_read..]) {
+            Ok(0) => break,
+            Ok(count) => bytes_read += count,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {},
+            Err(e) => return Err(e)
+        }
+    }
+    Ok(bytes_read)
+}
+
+/// Creates an `InvalidData` error with the given message
+fn invalid_data(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+/// A reader over the decompressed contents of a BGZF stream that decompresses batches of blocks on several threads at once.
+/// The compressed blocks are read in order on the calling thread and the decompressed batches are returned in the same order, so the output is byte-for-byte identical to sequential decompression.
+/// Record parsing stays with the consumer of the reader, which avoids guessing where FASTQ records start in the middle of a block.
+/// At most `threads` batches are decompressing at any time, with up to `threads` more read ahead and waiting as compressed data.
+/// # Examples
+/// ```
+/// use std::fs::File;
+/// use std::io::Read;
+/// use fastleng::bgzf::{ParallelBgzfReader, DEFAULT_BLOCKS_PER_BATCH};
+/// let file = File::open("./test_data/bgzf_strings.fq.gz").unwrap();
+/// let mut reader = ParallelBgzfReader::new(file, 4, DEFAULT_BLOCKS_PER_BATCH);
+/// let mut contents: String = String::new();
+/// reader.read_to_string(&mut contents).unwrap();
+/// assert!(contents.starts_with("@read1\n"));
+/// ```
+pub struct ParallelBgzfReader<R: Read> {
+    /// The compressed data
+    compressed: R,
+    /// The number of batches decompressed at once
+    threads: usize,
+    /// The number of blocks in each batch
+    blocks_per_batch: usize,
+    /// The batches that have been read but not returned yet, in file order
+    pending: VecDeque<PendingBatch>,
+    /// The decompressed batch currently being returned
+    current: Vec<u8>,
+    /// The position of the next unreturned byte in `current`
+    position: usize,
+    /// True once all of the compressed data has been read
+    compressed_finished: bool
+}
+
+/// A batch of compressed blocks that has been read ahead of the consumer
+enum PendingBatch {
+    /// Waiting for a free thread
+    Compressed(Vec<u8>),
+    /// Being decompressed on its own thread
+    Decompressing(JoinHandle<std::io::Result<Vec<u8>>>)
+}
+
+impl PendingBatch {
+    /// Waits for the decompressed contents of the batch, decompressing it on the calling thread if it never got a thread of its own
+    fn finish(self) -> std::io::Result<Vec<u8>> {
+        match self {
+            PendingBatch::Compressed(batch) => decompress_batch(&batch),
+            PendingBatch::Decompressing(handle) => handle.join().map_err(|_| std::io::Error::other("BGZF decompression thread panicked"))?
+        }
+    }
+}
+
+/// Decompresses a batch of complete BGZF blocks
+fn decompress_batch(batch: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut decompressed: Vec<u8> = vec![];
+    MultiGzDecoder::new(batch).read_to_end(&mut decompressed)?;
+    Ok(decompressed)
+}
+
+impl<R: Read> ParallelBgzfReader<R> {
+    /// Creates a reader that decompresses `compressed` using up to `threads` threads, each handling `blocks_per_batch` blocks at a time
+    pub fn new(compressed: R, threads: usize, blocks_per_batch: usize) -> ParallelBgzfReader<R> {
+        assert!(threads > 0 && blocks_per_batch > 0);
+        ParallelBgzfReader {
+            compressed,
+            threads,
+            blocks_per_batch,
+            pending: VecDeque::new(),
+            current: vec![],
+            position: 0,
+            compressed_finished: false
+        }
+    }
+
+    /// Reads the next batch of compressed blocks, returning an empty batch at the end of the data
+    fn read_batch(&mut self) -> std::io::Result<Vec<u8>> {
+        let mut batch: Vec<u8> = vec![];
+        for _block in 0..self.blocks_per_batch {
+            let (header, block_size) = match read_block_header(&mut self.compressed)? {
+                Some(block_header) => block_header,
+                None => break
+            };
+            let block_start: usize = batch.len();
+            batch.extend_from_slice(&header);
+            batch.resize(block_start + block_size, 0);
+            if read_fully(&mut self.compressed, &mut batch[block_start + header.len()..])? < block_size - header.len() {
+                return Err(invalid_data("Truncated BGZF block"));
+            }
+        }
+        Ok(batch)
+    }
+
+    /// Reads ahead until `2 * threads` batches are pending or the compressed data runs out, then starts decompressing the oldest `threads` of them
+    fn fill_pending(&mut self) -> std::io::Result<()> {
+        while !self.compressed_finished && self.pending.len() < 2 * self.threads {
+            let batch: Vec<u8> = self.read_batch()?;
+            if batch.is_empty() {
+                self.compressed_finished = true;
+            } else {
+                self.pending.push_back(PendingBatch::Compressed(batch));
+            }
+        }
+
+        //batches are returned from the front, so the running ones are always the oldest and never more than `threads`
+        for pending_batch in self.pending.iter_mut().take(self.threads) {
+            if let PendingBatch::Compressed(batch) = pending_batch {
+                let batch: Vec<u8> = std::mem::take(batch);
+                *pending_batch = PendingBatch::Decompressing(std::thread::spawn(move || decompress_batch(&batch)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for ParallelBgzfReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        //the empty EOF block (and any other empty block) decompresses to nothing, so keep going until we have data or run out
+        while self.position == self.current.len() {
+            self.fill_pending()?;
+            let next_batch = match self.pending.pop_front() {
+                Some(handle) => handle,
+                None => return Ok(0)
+            };
+            self.current = next_batch.finish()?;
+            self.position = 0;
+        }
+        let count: usize = buf.len().min(self.current.len() - self.position);
+        buf[..count].copy_from_slice(&self.current[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decompresses a whole file sequentially for comparison
+    fn sequential_contents(filename: &str) -> Vec<u8> {
+        let mut contents: Vec<u8> = vec![];
+        MultiGzDecoder::new(File::open(filename).unwrap()).read_to_end(&mut contents).unwrap();
+        contents
+    }
+
+    #[test]
+    fn test_parallel_bgzf_matches_sequential() {
+        let filename = "./test_data/bgzf_strings.fq.gz";
+        let expected: Vec<u8> = sequential_contents(filename);
+
+        //vary the batching so that batches end in the middle of records and some threads have nothing to do
+        for (threads, blocks_per_batch) in [(1, 1), (2, 1), (3, 7), (8, 2), (4, DEFAULT_BLOCKS_PER_BATCH)] {
+            let mut reader = ParallelBgzfReader::new(File::open(filename).unwrap(), threads, blocks_per_batch);
+            let mut contents: Vec<u8> = vec![];
+            reader.read_to_end(&mut contents).unwrap();
+            assert_eq!(contents, expected, "threads {threads}, blocks {blocks_per_batch}");
+        }
+    }
+
+    #[test]
+    fn test_parallel_bgzf_thread_limit() {
+        let filename = "./test_data/bgzf_strings.fq.gz";
+        let expected: Vec<u8> = sequential_contents(filename);
+
+        //read a byte at a time so the read-ahead is checked after every refill
+        let threads: usize = 2;
+        let mut reader = ParallelBgzfReader::new(File::open(filename).unwrap(), threads, 1);
+        let mut contents: Vec<u8> = vec![];
+        let mut byte: [u8; 1] = [0];
+        while reader.read(&mut byte).unwrap() == 1 {
+            contents.push(byte[0]);
+            let running: usize = reader.pending.iter().filter(|pending_batch| matches!(pending_batch, PendingBatch::Decompressing(_))).count();
+            assert!(running <= threads);
+            assert!(reader.pending.len() <= 2 * threads);
+        }
+        assert_eq!(contents, expected);
+    }
+
+    #[test]
+    fn test_parallel_bgzf_errors() {
+        //plain gzip is not BGZF
+        let mut plain_reader = ParallelBgzfReader::new(File::open("./test_data/long_strings.fa.gz").unwrap(), 2, 1);
+        let mut contents: Vec<u8> = vec![];
+        assert!(plain_reader.read_to_end(&mut contents).is_err());
+
+        //a truncated block is an error rather than silently short data
+        let mut compressed: Vec<u8> = std::fs::read("./test_data/bgzf_strings.fq.gz").unwrap();
+        compressed.truncate(compressed.len() / 2);
+        let mut truncated_reader = ParallelBgzfReader::new(&compressed[..], 2, 4);
+        assert!(truncated_reader.read_to_end(&mut contents).is_err());
+        assert!(is_bgzf_file("./test_data/does_not_exist.fq.gz").is_err());
+    }
+}
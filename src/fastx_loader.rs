@@ -1,9 +1,24 @@
 
-use log::{error, info};
-use needletail::parse_fastx_file;
+use log::{error, info, warn};
+use needletail::{parse_fastx_file, parse_fastx_reader, FastxReader};
+use needletail::errors::ParseErrorKind;
+use needletail::parser::SequenceRecord;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
-use crate::bam_loader::gather_bam_stats_with_seed;
+use crate::accumulator::LengthAccumulator;
+use crate::bam_loader::{stream_bam_lengths, BamLoadOptions};
+#[cfg(feature = "parallel-bgzf")]
+use crate::bgzf::{is_bgzf_file, ParallelBgzfReader, DEFAULT_BLOCKS_PER_BATCH};
+#[cfg(feature = "parallel-bgzf")]
+use crate::format::sniff_reader;
+use crate::format::{is_stream_input, open_sniffed_stream, resolve_format, FormatDetection, InputFormat, SniffedStream};
+use crate::length_stats::merge_length_counts;
+use crate::loader::{LoaderConfig, DEFAULT_DISTINCT_LENGTH_WARNING};
+use crate::sampling::SamplingConfig;
 
 /// This is the main function for gathering all sequence lengths for a fastx file into a BTreeMap.
 /// # Arguments
@@ -20,6 +35,7 @@ pub fn gather_fastx_stats(filename: &str) -> Result<BTreeMap<usize, u64>, Box<dy
 }
 
 /// This will gather sequence lengths from a filename and add them to a provided BTreeMap (`initial_counts`).
+/// The default `distinct_length_warning` is checked; use `LengthAccumulator::with_config(...)` to load with another threshold.
 /// # Arguments
 /// * `filename` - the filename to read sequences from
 /// * `initial_counts` - if provided, this will use that BTreeMap as the inital counts, otherwise it will create an empty one
@@ -32,36 +48,683 @@ pub fn gather_fastx_stats(filename: &str) -> Result<BTreeMap<usize, u64>, Box<dy
 /// let counts: BTreeMap<usize, u64> = gather_fastx_stats_with_seed(&filename, Some(initial_counts)).unwrap();
 /// ```
 pub fn gather_fastx_stats_with_seed(filename: &str, initial_counts: Option<BTreeMap<usize, u64>>) -> Result<BTreeMap<usize, u64>, Box<dyn std::error::Error>> {
-    //create an empty stats file (or use initial counts) and ready the reader
-    let mut hash_stats: BTreeMap<usize, u64> = match initial_counts {
-        Some(ic) => ic,
-        None => BTreeMap::new()
-    };
-    let mut reader = parse_fastx_file(filename)?;
+    //create an empty accumulator (or use initial counts) and ready the reader
+    let mut accumulator = LengthAccumulator::from_counts(initial_counts.unwrap_or_default());
+    stream_fastx_lengths(filename, |_seq_id, seq_len| {
+        accumulator.add_length(seq_len);
+        Ok(())
+    })?;
+
+    //return the full count list now
+    Ok(accumulator.into_histogram())
+}
+
+/// This will stream through a fastx file and call `callback` with the identifier and length of each record.
+/// Nothing is retained between records, so memory usage stays flat regardless of file size.
+/// Returns the number of records processed.
+/// # Arguments
+/// * `filename` - the filename to read sequences from
+/// * `callback` - called with the record identifier (the full header line) and sequence length for each record
+/// # Examples
+/// ```
+/// use fastleng::fastx_loader::stream_fastx_lengths;
+/// let filename = "./test_data/five_strings.fa";
+/// let mut lengths: Vec<usize> = vec![];
+/// let count = stream_fastx_lengths(&filename, |_seq_id, seq_len| {
+///     lengths.push(seq_len);
+///     Ok(())
+/// }).unwrap();
+/// assert_eq!(count, 5);
+/// assert_eq!(lengths, vec![1, 2, 3, 4, 5]);
+/// ```
+pub fn stream_fastx_lengths<F>(filename: &str, mut callback: F) -> Result<usize, Box<dyn std::error::Error>>
+where
+    F: FnMut(&[u8], usize) -> Result<(), Box<dyn std::error::Error>>
+{
+    stream_fastx_records(filename, &LoaderConfig::default(), &mut 0, |seq_rec| {
+        //all we care about is the sequence length
+        callback(seq_rec.id(), seq_rec.num_bases())
+    })
+}
+
+/// This is the core record loop for fastx files, calling `callback` with each parsed record.
+/// Returns the number of records processed.
+/// # Arguments
+/// * `filename` - the filename to read sequences from
+/// * `config` - the per-file options to apply; only `head`, `max_records`, and `validate_qual_length` are used here
+/// * `qual_length_mismatches` - incremented for each record with mismatched sequence and quality lengths when validating
+/// * `callback` - called with each record in the file
+fn stream_fastx_records<F>(filename: &str, config: &LoaderConfig, qual_length_mismatches: &mut u64, callback: F) -> Result<usize, Box<dyn std::error::Error>>
+where
+    F: FnMut(&SequenceRecord) -> Result<(), Box<dyn std::error::Error>>
+{
+    let mismatches: Option<Arc<AtomicU64>> = config.validate_qual_length.then(|| Arc::new(AtomicU64::new(0)));
+    let reader = open_fastx_file(filename, config, mismatches.as_ref())?;
+    let result = stream_fastx_parser(reader, filename, config, callback);
+    if let Some(mismatches) = mismatches {
+        *qual_length_mismatches += mismatches.load(Ordering::Relaxed);
+    }
+    result
+}
+
+/// Opens a FASTX parser on a regular file, skipping a UTF-8 byte order mark at the start (e.g. from a Windows editor) that would otherwise break the first record.
+/// Files without one are handed to the parser directly, so its own decompression is used as normal.
+/// With the `parallel-bgzf` feature and more than one `config.decompression_threads`, BGZF files are instead decompressed in parallel ahead of the parser.
+/// If `mismatches` is given, FASTQ records with mismatched sequence and quality lengths are dropped before the parser and counted there.
+fn open_fastx_file(filename: &str, config: &LoaderConfig, mismatches: Option<&Arc<AtomicU64>>) -> Result<Box<dyn FastxReader>, Box<dyn std::error::Error>> {
+    #[cfg(feature = "parallel-bgzf")]
+    if config.decompression_threads > 1 && is_bgzf_file(filename)? {
+        info!("Decompressing BGZF file with {} threads: {:?}", config.decompression_threads, filename);
+        let parallel_reader = ParallelBgzfReader::new(std::fs::File::open(filename)?, config.decompression_threads, DEFAULT_BLOCKS_PER_BATCH);
+        let sniffed = sniff_reader(parallel_reader)?;
+        if sniffed.byte_order_mark {
+            warn!("Skipping the UTF-8 byte order mark at the start of the file: {:?}", filename);
+        }
+        return Ok(parse_fastx_reader(validated_contents(sniffed, filename, mismatches))?);
+    }
+    #[cfg(not(feature = "parallel-bgzf"))]
+    let _ = config;
+
+    let sniffed = open_sniffed_stream(filename)?;
+    if sniffed.byte_order_mark {
+        warn!("Skipping the UTF-8 byte order mark at the start of the file: {:?}", filename);
+    }
+    if sniffed.byte_order_mark || (mismatches.is_some() && sniffed.input_format == InputFormat::Fastq) {
+        return Ok(parse_fastx_reader(validated_contents(sniffed, filename, mismatches))?);
+    }
+    Ok(parse_fastx_file(filename)?)
+}
+
+/// Returns the plain contents of `sniffed`, first dropping any FASTQ records with mismatched sequence and quality lengths if `mismatches` is given
+fn validated_contents(sniffed: SniffedStream, label: &str, mismatches: Option<&Arc<AtomicU64>>) -> Box<dyn Read + Send> {
+    match mismatches {
+        Some(mismatches) if sniffed.input_format == InputFormat::Fastq => {
+            Box::new(QualLengthValidator::new(BufReader::new(sniffed.reader), label, Arc::clone(mismatches)))
+        },
+        _ => sniffed.reader
+    }
+}
+
+/// A reader over plain FASTQ data that drops each four-line record whose sequence and quality lines have different lengths, counting them in `mismatches`.
+/// The FASTQ parser cannot resume after such a record, so checking them here lets the rest of the file still be loaded.
+/// Anything that does not look like a FASTQ record is passed through unchanged for the parser to report.
+struct QualLengthValidator<R: BufRead> {
+    /// The plain FASTQ data
+    reader: R,
+    /// The name of the data, for log messages
+    label: String,
+    /// Incremented for each dropped record
+    mismatches: Arc<AtomicU64>,
+    /// The number of records checked so far
+    record_count: usize,
+    /// The raw lines of the record being passed through
+    record: Vec<u8>,
+    /// How much of `record` has been passed through
+    position: usize
+}
 
+impl<R: BufRead> QualLengthValidator<R> {
+    fn new(reader: R, label: &str, mismatches: Arc<AtomicU64>) -> QualLengthValidator<R> {
+        QualLengthValidator {
+            reader,
+            label: label.to_string(),
+            mismatches,
+            record_count: 0,
+            record: vec![],
+            position: 0
+        }
+    }
+
+    /// Loads the next record with matching lengths into `record`, which is left empty at the end of the data
+    fn load_next_record(&mut self) -> std::io::Result<()> {
+        loop {
+            self.record.clear();
+            self.position = 0;
+            let mut line_ends: Vec<usize> = Vec::with_capacity(4);
+            while line_ends.len() < 4 && self.reader.read_until(b'\n', &mut self.record)? > 0 {
+                line_ends.push(self.record.len());
+            }
+            if line_ends.len() < 4 {
+                //an incomplete record is left for the parser to report
+                return Ok(());
+            }
+
+            let header: &[u8] = &self.record[..line_ends[0]];
+            let seq: &[u8] = trim_line_ending(&self.record[line_ends[0]..line_ends[1]]);
+            let plus: &[u8] = &self.record[line_ends[1]..line_ends[2]];
+            let qual: &[u8] = trim_line_ending(&self.record[line_ends[2]..line_ends[3]]);
+            self.record_count += 1;
+            if !header.starts_with(b"@") || !plus.starts_with(b"+") || seq.len() == qual.len() {
+                return Ok(());
+            }
+            if self.mismatches.fetch_add(1, Ordering::Relaxed) == 0 {
+                warn!(
+                    "Skipping FASTQ record {} with {} bases but {} qualities, along with any later mismatched records: {:?}",
+                    self.record_count, seq.len(), qual.len(), self.label
+                );
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Read for QualLengthValidator<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.position == self.record.len() {
+            self.load_next_record()?;
+        }
+        let remaining: &[u8] = &self.record[self.position..];
+        let copy_length: usize = remaining.len().min(buf.len());
+        buf[..copy_length].copy_from_slice(&remaining[..copy_length]);
+        self.position += copy_length;
+        Ok(copy_length)
+    }
+}
+
+/// Removes a trailing `\n` or `\r\n` from a line
+fn trim_line_ending(line: &[u8]) -> &[u8] {
+    let line: &[u8] = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// This will stream through fastx data from any reader (e.g. a member of an archive) and call `callback` with the identifier and length of each record.
+/// Compression is detected automatically, and the `head`, `max_records`, and `min_mean_qual` options of `config` are applied.
+/// Returns the total number of records processed, including any that failed `min_mean_qual`.
+/// # Arguments
+/// * `reader` - the source of the (optionally gzip compressed) fastx data
+/// * `label` - a name for the data used in log and error messages, e.g. the original filename
+/// * `config` - the loading options to apply
+/// * `callback` - called with the record identifier and sequence length for each record
+/// # Examples
+/// ```
+/// use fastleng::fastx_loader::stream_fastx_reader_lengths;
+/// use fastleng::loader::LoaderConfig;
+/// let fasta: &[u8] = b">seq1\nACGT\n>seq2\nAC\n";
+/// let mut lengths: Vec<usize> = vec![];
+/// let count = stream_fastx_reader_lengths(fasta, "in-memory", &LoaderConfig::new(), |_seq_id, seq_len| {
+///     lengths.push(seq_len);
+///     Ok(())
+/// }).unwrap();
+/// assert_eq!(count, 2);
+/// assert_eq!(lengths, vec![4, 2]);
+/// ```
+pub fn stream_fastx_reader_lengths<R, F>(reader: R, label: &str, config: &LoaderConfig, mut callback: F) -> Result<usize, Box<dyn std::error::Error>>
+where
+    R: std::io::Read + Send,
+    F: FnMut(&[u8], usize) -> Result<(), Box<dyn std::error::Error>>
+{
+    let fastx_reader = parse_fastx_reader(reader)?;
+    stream_fastx_parser(fastx_reader, label, config, |seq_rec| {
+        filter_fastx_record(seq_rec, label, config, &mut callback)
+    })
+}
+
+/// Passes a record to `callback` if it passes the `min_mean_qual` filter of `config`, failing if a quality filter was requested for a record without qualities.
+/// # Arguments
+/// * `seq_rec` - the parsed record
+/// * `label` - the name of the data the record came from, for error messages
+/// * `config` - the loading options to apply
+/// * `callback` - called with the record identifier and sequence length if the record passes
+fn filter_fastx_record<F>(seq_rec: &SequenceRecord, label: &str, config: &LoaderConfig, callback: &mut F) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: FnMut(&[u8], usize) -> Result<(), Box<dyn std::error::Error>>
+{
+    if let Some(min_mean_qual) = config.min_mean_qual {
+        let qual: &[u8] = match seq_rec.qual() {
+            Some(qual) => qual,
+            None => return Err(format!("Mean quality filtering requires FASTQ input, but found a record without qualities: {:?}", label).into())
+        };
+        if compute_mean_quality(qual) < min_mean_qual {
+            return Ok(());
+        }
+    }
+    callback(seq_rec.id(), seq_rec.num_bases())
+}
+
+/// This is the core record loop for parsed fastx data, calling `callback` with each record.
+/// Returns the number of records processed.
+/// # Arguments
+/// * `reader` - the fastx parser to pull records from
+/// * `filename` - the name of the data being parsed, for log and error messages
+/// * `config` - the per-file options to apply; only `head` and `max_records` are used here
+/// * `callback` - called with each record
+fn stream_fastx_parser<F>(mut reader: Box<dyn FastxReader + '_>, filename: &str, config: &LoaderConfig, mut callback: F) -> Result<usize, Box<dyn std::error::Error>>
+where
+    F: FnMut(&SequenceRecord) -> Result<(), Box<dyn std::error::Error>>
+{
     //go through all the records
     let mut count: usize = 0;
     info!("Loading file \"{}\"...", filename);
     while let Some(record) = reader.next() {
-        //all we care about is the sequence length
-        let seq_rec = record?;
-        let seq_len: usize = seq_rec.num_bases();
-        
-        //insert 0 if absent; then increment
-        let len_count: &mut u64 = hash_stats.entry(seq_len).or_insert(0);
-        *len_count += 1;
+        if config.head == Some(count) {
+            warn!("Stopped reading after the first {} records: {}", count, filename);
+            break;
+        }
+        if config.max_records == Some(count) {
+            return Err(format!("File contains more than the maximum of {} records: {:?}", count, filename).into());
+        }
+
+        let seq_rec = record.map_err(|e| describe_parse_error(e, count, filename))?;
+        callback(&seq_rec)?;
         
         count += 1;
-        if count % 1000000 == 0 {
+        if config.is_progress_point(count) {
             info!("Processed {} sequences", count);
         }
     }
     info!("Finished loading file with {} sequences.", count);
+    Ok(count)
+}
 
-    //return the full count list now
+/// Converts a parse error into the error reported to the user, making it clear when a file was probably truncated.
+/// Every error names the failing record (counting from 1) so that it can be found in a large file.
+/// # Arguments
+/// * `e` - the error from the parser
+/// * `count` - the number of records successfully read before the error
+/// * `filename` - the name of the data being parsed
+fn describe_parse_error(e: needletail::errors::ParseError, count: usize, filename: &str) -> Box<dyn std::error::Error> {
+    if matches!(e.kind, ParseErrorKind::Io | ParseErrorKind::UnexpectedEnd) {
+        //the raw decompression errors are cryptic, so make it clear what probably happened
+        format!(
+            "File appears to be truncated or corrupt, {} records were read successfully before the error: {:?} ({})",
+            count, filename, e.msg
+        ).into()
+    } else if e.kind == ParseErrorKind::UnequalLengths {
+        format!(
+            "FASTQ record {} has different sequence and quality lengths, use --validate-qual-length to count these instead: {:?} ({})",
+            count + 1, filename, e.msg
+        ).into()
+    } else {
+        format!("Failed at record {} of {:?}: {}", count + 1, filename, e).into()
+    }
+}
+
+/// Opens a FASTX parser on a file, standard input (`-`), or a pipe, failing for alignment formats which cannot be read in lockstep
+fn open_fastx_parser(filename: &str, config: &LoaderConfig) -> Result<Box<dyn FastxReader>, Box<dyn std::error::Error>> {
+    if is_stream_input(filename) {
+        let sniffed = open_sniffed_stream(filename)?;
+        if sniffed.input_format.is_alignment() {
+            return Err(format!("Paired mode only supports FASTX inputs: {:?}", filename).into());
+        }
+        return Ok(parse_fastx_reader(sniffed.reader)?);
+    }
+    if detect_logged_format(filename, config.format_detection)?.is_alignment() {
+        return Err(format!("Paired mode only supports FASTX inputs: {:?}", filename).into());
+    }
+    open_fastx_file(filename, config, None)
+}
+
+/// This will read a pair of R1/R2 files in lockstep and gather the combined length of the i-th record of each file into a BTreeMap.
+/// Returns an error if the files do not contain the same number of records; with `config.head`, only the first `head` pairs are read.
+/// Only the `head`, `format_detection`, and `progress_interval` options of `config` are used, and only FASTX inputs are supported.
+/// # Arguments
+/// * `r1_filename` - the file with the first read of each pair
+/// * `r2_filename` - the file with the second read of each pair
+/// * `config` - the loading options to apply
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::fastx_loader::gather_paired_stats;
+/// use fastleng::loader::LoaderConfig;
+/// let counts: BTreeMap<usize, u64> = gather_paired_stats("./test_data/five_strings.fa", "./test_data/five_strings.fq", &LoaderConfig::new()).unwrap();
+/// assert_eq!(counts, [(2, 1), (4, 1), (6, 1), (8, 1), (10, 1)].iter().cloned().collect());
+/// ```
+pub fn gather_paired_stats(r1_filename: &str, r2_filename: &str, config: &LoaderConfig) -> Result<BTreeMap<usize, u64>, Box<dyn std::error::Error>> {
+    let mut r1_reader = open_fastx_parser(r1_filename, config)?;
+    let mut r2_reader = open_fastx_parser(r2_filename, config)?;
+    let mut hash_stats: BTreeMap<usize, u64> = BTreeMap::new();
+
+    //advance both readers together, one pair at a time
+    let mut count: usize = 0;
+    info!("Loading paired files \"{}\" and \"{}\"...", r1_filename, r2_filename);
+    loop {
+        if config.head == Some(count) {
+            warn!("Stopped reading after the first {} pairs: {} {}", count, r1_filename, r2_filename);
+            break;
+        }
+        let r1_len: Option<usize> = match r1_reader.next() {
+            Some(record) => Some(record.map_err(|e| describe_parse_error(e, count, r1_filename))?.num_bases()),
+            None => None
+        };
+        let r2_len: Option<usize> = match r2_reader.next() {
+            Some(record) => Some(record.map_err(|e| describe_parse_error(e, count, r2_filename))?.num_bases()),
+            None => None
+        };
+        let pair_len: usize = match (r1_len, r2_len) {
+            (Some(r1_len), Some(r2_len)) => r1_len + r2_len,
+            (None, None) => break,
+            (Some(_), None) | (None, Some(_)) => {
+                let shorter_filename: &str = if r1_len.is_none() { r1_filename } else { r2_filename };
+                error!("Paired files have different numbers of records, {:?} ended after {} records", shorter_filename, count);
+                return Err(format!("Paired files have different numbers of records, {:?} ended after {} records", shorter_filename, count).into());
+            }
+        };
+
+        config.count_length(&mut hash_stats, pair_len);
+        count += 1;
+        if config.is_progress_point(count) {
+            info!("Processed {} pairs", count);
+        }
+    }
+    info!("Finished loading paired files with {} pairs.", count);
     Ok(hash_stats)
 }
 
+/// Sequence length counts both with and without the N (gap) bases
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GappedLengthCounts {
+    /// A BTreeMap with the full sequence length as the key, and the value the total number of sequences with that length
+    pub length_counts: BTreeMap<usize, u64>,
+    /// A BTreeMap with the ungapped sequence length (i.e. excluding N bases) as the key, and the value the total number of sequences with that length
+    pub ungapped_counts: BTreeMap<usize, u64>,
+    /// The ungapped length counts of the sequences with each full length, so the ungapped counts can be filtered by the same full lengths as `length_counts`
+    pub ungapped_by_length: BTreeMap<usize, BTreeMap<usize, u64>>
+}
+
+impl GappedLengthCounts {
+    /// Returns only the counts for the sequences whose full length passes `keep`, so that both maps still describe the same sequences.
+    /// # Arguments
+    /// * `keep` - returns true for each full sequence length to keep
+    /// # Examples
+    /// ```
+    /// use fastleng::fastx_loader::{gather_fastx_n_stats_with_seed, GappedLengthCounts};
+    /// let counts: GappedLengthCounts = gather_fastx_n_stats_with_seed("./test_data/gapped_strings.fa", None).unwrap();
+    /// let short_counts: GappedLengthCounts = counts.retain_lengths(|seq_len| seq_len <= 10);
+    /// assert_eq!(short_counts.length_counts.get(&12), None);
+    /// assert_eq!(short_counts.ungapped_counts.get(&10), Some(&1));
+    /// ```
+    pub fn retain_lengths<F: Fn(usize) -> bool>(&self, keep: F) -> GappedLengthCounts {
+        let mut retained: GappedLengthCounts = GappedLengthCounts::default();
+        for (seq_len, ungapped_lengths) in self.ungapped_by_length.iter().filter(|(seq_len, _ungapped_lengths)| keep(**seq_len)) {
+            if let Some(seq_count) = self.length_counts.get(seq_len) {
+                retained.length_counts.insert(*seq_len, *seq_count);
+            }
+            merge_length_counts(&mut retained.ungapped_counts, ungapped_lengths);
+            retained.ungapped_by_length.insert(*seq_len, ungapped_lengths.clone());
+        }
+        retained
+    }
+
+    /// Returns the total number of N bases across all of the sequences
+    /// # Examples
+    /// ```
+    /// use fastleng::fastx_loader::{gather_fastx_n_stats_with_seed, GappedLengthCounts};
+    /// let counts: GappedLengthCounts = gather_fastx_n_stats_with_seed("./test_data/gapped_strings.fa", None).unwrap();
+    /// assert_eq!(counts.total_n_bases(), 8);
+    /// ```
+    pub fn total_n_bases(&self) -> u64 {
+        self.ungapped_by_length.iter()
+            .flat_map(|(seq_len, ungapped_lengths)| ungapped_lengths.iter().map(move |(ungapped_len, seq_count)| ((seq_len - ungapped_len) as u64).saturating_mul(*seq_count)))
+            .fold(0, u64::saturating_add)
+    }
+}
+
+/// Returns the number of N bases (either case) in a sequence.
+/// # Arguments
+/// * `seq` - the sequence bytes to scan; line breaks are ignored so a raw multi-line FASTA sequence is fine
+/// # Examples
+/// ```
+/// use fastleng::fastx_loader::count_n_bases;
+/// assert_eq!(count_n_bases(b"ACNNGTn"), 3);
+/// ```
+pub fn count_n_bases(seq: &[u8]) -> usize {
+    seq.iter().filter(|&&b| b == b'N' || b == b'n').count()
+}
+
+/// Returns the mean Phred score of a FASTQ quality string, assuming the standard offset of 33.
+/// This is the arithmetic mean of the scores themselves (not of the error probabilities); an empty quality string is 0.0.
+/// # Arguments
+/// * `qual` - the raw quality bytes of a record
+/// # Examples
+/// ```
+/// use fastleng::fastx_loader::compute_mean_quality;
+/// assert_eq!(compute_mean_quality(b"+I"), 25.0);
+/// assert_eq!(compute_mean_quality(b""), 0.0);
+/// ```
+pub fn compute_mean_quality(qual: &[u8]) -> f64 {
+    if qual.is_empty() {
+        return 0.0;
+    }
+    let total_qual: u64 = qual.iter().map(|&q| q.saturating_sub(33) as u64).sum();
+    total_qual as f64 / qual.len() as f64
+}
+
+/// This will gather both the full and the ungapped (N bases removed) sequence lengths for a fastx file.
+/// This requires scanning every base, so it is slower than `gather_fastx_stats_with_seed(...)`.
+/// # Arguments
+/// * `filename` - the filename to read sequences from
+/// * `initial_counts` - if provided, this will use these as the inital counts, otherwise it will create empty ones
+/// # Examples
+/// ```
+/// use fastleng::fastx_loader::{gather_fastx_n_stats_with_seed, GappedLengthCounts};
+/// let filename = "./test_data/gapped_strings.fa";
+/// let counts: GappedLengthCounts = gather_fastx_n_stats_with_seed(&filename, None).unwrap();
+/// assert_eq!(counts.length_counts.get(&10), Some(&3));
+/// ```
+pub fn gather_fastx_n_stats_with_seed(filename: &str, initial_counts: Option<GappedLengthCounts>) -> Result<GappedLengthCounts, Box<dyn std::error::Error>> {
+    gather_fastx_n_stats_with_warning(filename, initial_counts, DEFAULT_DISTINCT_LENGTH_WARNING)
+}
+
+/// This is `gather_fastx_n_stats_with_seed(...)`, but with the threshold for the distinct length memory warning
+fn gather_fastx_n_stats_with_warning(filename: &str, initial_counts: Option<GappedLengthCounts>, distinct_length_warning: usize) -> Result<GappedLengthCounts, Box<dyn std::error::Error>> {
+    let mut gapped_counts: GappedLengthCounts = initial_counts.unwrap_or_default();
+    let record_config = LoaderConfig {
+        distinct_length_warning,
+        ..Default::default()
+    };
+    stream_fastx_records(filename, &record_config, &mut 0, |seq_rec| {
+        let seq_len: usize = seq_rec.num_bases();
+        let n_count: usize = count_n_bases(seq_rec.raw_seq());
+
+        record_config.count_length(&mut gapped_counts.length_counts, seq_len);
+        //insert 0 if absent; then increment
+        let len_count: &mut u64 = gapped_counts.ungapped_counts.entry(seq_len - n_count).or_insert(0);
+        *len_count += 1;
+        let by_length_count: &mut u64 = gapped_counts.ungapped_by_length.entry(seq_len).or_default().entry(seq_len - n_count).or_insert(0);
+        *by_length_count += 1;
+        Ok(())
+    })?;
+    Ok(gapped_counts)
+}
+
+/// This will iterate through multiple fastx files and gather the full and ungapped lengths into single BTreeMaps.
+/// BAM/SAM inputs are not supported and will return an error.
+/// # Arguments
+/// * `filenames` - the filenames to read sequences from
+/// * `distinct_length_warning` - the number of distinct lengths that triggers the memory warning, or 0 to disable it, see `LoaderConfig::distinct_length_warning(...)`
+/// # Examples
+/// ```
+/// use fastleng::fastx_loader::{gather_multifastx_n_stats, GappedLengthCounts};
+/// use fastleng::loader::DEFAULT_DISTINCT_LENGTH_WARNING;
+/// let filenames = [
+///     "./test_data/gapped_strings.fa",
+///     "./test_data/five_strings.fa"
+/// ];
+/// let counts: GappedLengthCounts = gather_multifastx_n_stats(&filenames, DEFAULT_DISTINCT_LENGTH_WARNING).unwrap();
+/// ```
+pub fn gather_multifastx_n_stats<T: AsRef<str> + std::fmt::Debug>(filenames: &[T], distinct_length_warning: usize) -> Result<GappedLengthCounts, Box<dyn std::error::Error>> {
+    let mut gapped_counts: GappedLengthCounts = GappedLengthCounts::default();
+    for filename in filenames.iter() {
+        if detect_logged_format(filename.as_ref(), FormatDetection::default())?.is_alignment() {
+            error!("N base counting is only supported for FASTX files: {:?}", filename);
+            return Err(format!("N base counting is not supported for BAM/SAM file: {:?}", filename).into());
+        }
+        gapped_counts = match gather_fastx_n_stats_with_warning(filename.as_ref(), Some(gapped_counts), distinct_length_warning) {
+            Ok(result) => result,
+            Err(e) => {
+                error!("Error while parsing FASTX file: {:?}", filename);
+                error!("Error: {:?}", e);
+                return Err(e);
+            }
+        };
+    }
+    Ok(gapped_counts)
+}
+
+/// The IUPAC ambiguity codes tallied by `count_ambiguous_bases(...)`, in upper case
+pub const AMBIGUOUS_BASES: [char; 11] = ['B', 'D', 'H', 'K', 'M', 'N', 'R', 'S', 'V', 'W', 'Y'];
+
+/// This will add the number of each IUPAC ambiguity code (see `AMBIGUOUS_BASES`, either case) in a sequence to `ambiguous_counts`.
+/// Codes that do not appear in the sequence are not added to the map.
+/// # Arguments
+/// * `seq` - the sequence bytes to scan; line breaks are ignored so a raw multi-line FASTA sequence is fine
+/// * `ambiguous_counts` - the running count of each code
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::fastx_loader::count_ambiguous_bases;
+/// let mut ambiguous_counts: BTreeMap<char, u64> = BTreeMap::new();
+/// count_ambiguous_bases(b"ACGTRryN", &mut ambiguous_counts);
+/// assert_eq!(ambiguous_counts[&'R'], 2);
+/// assert_eq!(ambiguous_counts[&'Y'], 1);
+/// assert_eq!(ambiguous_counts[&'N'], 1);
+/// assert_eq!(ambiguous_counts.get(&'A'), None);
+/// ```
+pub fn count_ambiguous_bases(seq: &[u8], ambiguous_counts: &mut BTreeMap<char, u64>) {
+    //tally every byte first, it is much cheaper than a map lookup per base
+    let mut byte_counts: [u64; 256] = [0; 256];
+    for &base in seq.iter() {
+        byte_counts[base as usize] += 1;
+    }
+    for &code in AMBIGUOUS_BASES.iter() {
+        let code_count: u64 = byte_counts[code as usize] + byte_counts[code.to_ascii_lowercase() as usize];
+        if code_count > 0 {
+            *ambiguous_counts.entry(code).or_insert(0) += code_count;
+        }
+    }
+}
+
+/// Sequence length counts along with the total number of each IUPAC ambiguity code in those sequences
+#[derive(Clone, Debug, PartialEq)]
+pub struct AmbiguousLengthCounts {
+    /// A BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+    pub length_counts: BTreeMap<usize, u64>,
+    /// A BTreeMap with each code in `AMBIGUOUS_BASES` as the key, and the value the number of those bases across all of the sequences
+    pub ambiguous_counts: BTreeMap<char, u64>
+}
+
+impl Default for AmbiguousLengthCounts {
+    fn default() -> AmbiguousLengthCounts {
+        AmbiguousLengthCounts {
+            length_counts: BTreeMap::new(),
+            //every code is reported, even when it never appears
+            ambiguous_counts: AMBIGUOUS_BASES.iter().map(|&code| (code, 0)).collect()
+        }
+    }
+}
+
+/// This will iterate through multiple fastx files and gather the lengths along with the number of each IUPAC ambiguity code in the sequences.
+/// The `head`, `max_records`, and `min_mean_qual` options and the length filters of `config` are applied, and only the kept sequences are counted; BAM/SAM inputs return an error.
+/// # Arguments
+/// * `filenames` - the filenames to read sequences from
+/// * `config` - the loading options and filters to apply
+/// # Examples
+/// ```
+/// use fastleng::fastx_loader::{gather_multifastx_ambiguous_stats, AmbiguousLengthCounts};
+/// use fastleng::loader::LoaderConfig;
+/// let counts: AmbiguousLengthCounts = gather_multifastx_ambiguous_stats(&["./test_data/ambiguous_bases.fa"], &LoaderConfig::new()).unwrap();
+/// assert_eq!(counts.ambiguous_counts[&'N'], 4);
+/// assert_eq!(counts.ambiguous_counts[&'R'], 1);
+/// ```
+pub fn gather_multifastx_ambiguous_stats<T: AsRef<str> + std::fmt::Debug>(filenames: &[T], config: &LoaderConfig) -> Result<AmbiguousLengthCounts, Box<dyn std::error::Error>> {
+    let mut ambiguous_length_counts: AmbiguousLengthCounts = AmbiguousLengthCounts::default();
+    for filename in filenames.iter() {
+        if detect_logged_format(filename.as_ref(), config.format_detection)?.is_alignment() {
+            error!("Ambiguous base counting is only supported for FASTX files: {:?}", filename);
+            return Err(format!("Ambiguous base counting is not supported for BAM/SAM file: {:?}", filename).into());
+        }
+        let fastx_result = stream_fastx_records(filename.as_ref(), config, &mut 0, |seq_rec| {
+            //the quality filter decides whether the record is passed on
+            let mut passed_quality: bool = false;
+            filter_fastx_record(seq_rec, filename.as_ref(), config, &mut |_seq_id, _seq_len| {
+                passed_quality = true;
+                Ok(())
+            })?;
+            let seq_len: usize = seq_rec.num_bases();
+            if !passed_quality || !config.passes_length_filters(seq_len) {
+                return Ok(());
+            }
+
+            config.count_length(&mut ambiguous_length_counts.length_counts, seq_len);
+            count_ambiguous_bases(seq_rec.raw_seq(), &mut ambiguous_length_counts.ambiguous_counts);
+            Ok(())
+        });
+        if let Err(e) = fastx_result {
+            error!("Error while parsing FASTX file: {:?}", filename);
+            error!("Error: {:?}", e);
+            return Err(e);
+        }
+    }
+    Ok(ambiguous_length_counts)
+}
+
+/// Sequence length counts along with the total quality of the bases at each length, for reporting quality by read length
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LengthQualityCounts {
+    /// A BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+    pub length_counts: BTreeMap<usize, u64>,
+    /// A BTreeMap with the sequence length as the key, and the value the sum of the Phred scores of every base in the sequences with that length
+    pub quality_sums: BTreeMap<usize, u64>
+}
+
+impl LengthQualityCounts {
+    /// Returns the mean Phred score of the reads at each length, which is 0.0 for zero-length reads.
+    /// Every read of a length has the same number of bases, so this is also the mean of the per-read mean qualities.
+    pub fn mean_quality_by_length(&self) -> BTreeMap<usize, f64> {
+        self.length_counts.iter()
+            .map(|(&seq_len, &seq_count)| {
+                let total_bases: u64 = seq_len as u64 * seq_count;
+                let quality_sum: u64 = self.quality_sums.get(&seq_len).copied().unwrap_or(0);
+                (seq_len, if total_bases == 0 { 0.0 } else { quality_sum as f64 / total_bases as f64 })
+            })
+            .collect()
+    }
+}
+
+/// This will iterate through multiple FASTQ files and gather the lengths along with the total base quality at each length.
+/// The `head`, `max_records`, and `min_mean_qual` options and the length filters of `config` are applied; BAM/SAM inputs and records without qualities return an error.
+/// # Arguments
+/// * `filenames` - the FASTQ filenames to read sequences from
+/// * `config` - the loading options and filters to apply
+/// # Examples
+/// ```
+/// use fastleng::fastx_loader::{gather_multifastx_quality_stats, LengthQualityCounts};
+/// use fastleng::loader::LoaderConfig;
+/// let quality_counts: LengthQualityCounts = gather_multifastx_quality_stats(&["./test_data/quality_by_length.fq"], &LoaderConfig::new()).unwrap();
+/// assert_eq!(quality_counts.length_counts.get(&4), Some(&2));
+/// assert_eq!(quality_counts.mean_quality_by_length()[&4], 30.0);
+/// ```
+pub fn gather_multifastx_quality_stats<T: AsRef<str> + std::fmt::Debug>(filenames: &[T], config: &LoaderConfig) -> Result<LengthQualityCounts, Box<dyn std::error::Error>> {
+    let mut quality_counts: LengthQualityCounts = LengthQualityCounts::default();
+    for filename in filenames.iter() {
+        if detect_logged_format(filename.as_ref(), config.format_detection)?.is_alignment() {
+            error!("Quality by length is only supported for FASTQ files: {:?}", filename);
+            return Err(format!("Quality by length is not supported for BAM/SAM file: {:?}", filename).into());
+        }
+        let fastx_result = stream_fastx_records(filename.as_ref(), config, &mut 0, |seq_rec| {
+            let qual: &[u8] = match seq_rec.qual() {
+                Some(qual) => qual,
+                None => return Err(format!("Quality by length requires FASTQ input, but found a record without qualities: {:?}", filename).into())
+            };
+            let seq_len: usize = seq_rec.num_bases();
+            if config.min_mean_qual.is_some_and(|min_mean_qual| compute_mean_quality(qual) < min_mean_qual) || !config.passes_length_filters(seq_len) {
+                return Ok(());
+            }
+
+            config.count_length(&mut quality_counts.length_counts, seq_len);
+            //insert 0 if absent; then increment
+            let quality_sum: &mut u64 = quality_counts.quality_sums.entry(seq_len).or_insert(0);
+            *quality_sum += qual.iter().map(|&q| q.saturating_sub(33) as u64).sum::<u64>();
+            Ok(())
+        });
+        if let Err(e) = fastx_result {
+            error!("Error while parsing FASTX file: {:?}", filename);
+            error!("Error: {:?}", e);
+            return Err(e);
+        }
+    }
+    Ok(quality_counts)
+}
+
 /// This will iterate through multiple fastx files and gather the lengths into a single BTreeMap.
 /// # Arguments
 /// * `filenames` - the filenames to read sequences from
@@ -76,15 +739,417 @@ pub fn gather_fastx_stats_with_seed(filename: &str, initial_counts: Option<BTree
 /// let counts: BTreeMap<usize, u64> = gather_multifastx_stats(&filenames).unwrap();
 /// ```
 pub fn gather_multifastx_stats<T: AsRef<str> + std::fmt::Debug>(filenames: &[T]) -> Result<BTreeMap<usize, u64>, Box<dyn std::error::Error>> {
+    gather_multifastx_stats_with_options(filenames, &BamLoadOptions::default())
+}
+
+/// This will iterate through multiple fastx files and gather the lengths into a single BTreeMap.
+/// Any BAM/SAM files in the list are loaded using the provided `bam_options`.
+/// # Arguments
+/// * `filenames` - the filenames to read sequences from
+/// * `bam_options` - controls how BAM/SAM record lengths are calculated
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::bam_loader::BamLoadOptions;
+/// use fastleng::fastx_loader::gather_multifastx_stats_with_options;
+/// let filenames = [
+///     "./test_data/single_string.fa",
+///     "./test_data/hardclip_strings.sam"
+/// ];
+/// let bam_options = BamLoadOptions { include_hardclips: true, ..Default::default() };
+/// let counts: BTreeMap<usize, u64> = gather_multifastx_stats_with_options(&filenames, &bam_options).unwrap();
+/// ```
+pub fn gather_multifastx_stats_with_options<T: AsRef<str> + std::fmt::Debug>(filenames: &[T], bam_options: &BamLoadOptions) -> Result<BTreeMap<usize, u64>, Box<dyn std::error::Error>> {
     /*
     Notes on the T here: we need to be able to reference as a &str and run the debug formatting for output.
     The above allows us to pass lists/vecs of Strings/&strs without having to do a bunch of work.
     Derived from: https://stackoverflow.com/questions/32723794/how-do-i-write-a-function-that-takes-both-owned-and-non-owned-string-collections/32724666#32724666
     */
+    gather_multifastx_stats_head(filenames, bam_options, None)
+}
+
+/// This will iterate through multiple fastx or BAM/SAM files and gather the lengths of only the first `head` records of each file.
+/// This is useful for quickly spot-checking the start of very large files; with `head` of `None`, every record is loaded.
+/// # Arguments
+/// * `filenames` - the filenames to read sequences from
+/// * `bam_options` - controls how BAM/SAM record lengths are calculated
+/// * `head` - if set, stop reading each file after this many records
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::bam_loader::BamLoadOptions;
+/// use fastleng::fastx_loader::gather_multifastx_stats_head;
+/// let filenames = [
+///     "./test_data/five_strings.fa",
+///     "./test_data/five_strings.sam"
+/// ];
+/// let counts: BTreeMap<usize, u64> = gather_multifastx_stats_head(&filenames, &BamLoadOptions::default(), Some(2)).unwrap();
+/// assert_eq!(counts, [(1, 2), (2, 2)].iter().cloned().collect());
+/// ```
+pub fn gather_multifastx_stats_head<T: AsRef<str> + std::fmt::Debug>(filenames: &[T], bam_options: &BamLoadOptions, head: Option<usize>) -> Result<BTreeMap<usize, u64>, Box<dyn std::error::Error>> {
+    let mut hash_stats: BTreeMap<usize, u64> = BTreeMap::new();
+    let config = LoaderConfig {
+        bam_options: bam_options.clone(),
+        head,
+        ..Default::default()
+    };
+    stream_multifastx_lengths(filenames, &config, |_seq_id, seq_len| {
+        config.count_length(&mut hash_stats, seq_len);
+        Ok(())
+    })?;
+    Ok(hash_stats)
+}
+
+/// A file that could not be loaded when failures are tolerated
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FailedFile {
+    /// The file that failed to load
+    pub filename: String,
+    /// A description of the error that occurred
+    pub error: String
+}
+
+/// This will iterate through multiple fastx or BAM/SAM files and gather the lengths into a single BTreeMap, skipping any files that fail to load.
+/// Each file is loaded into its own BTreeMap first, so a file that fails part way through contributes no lengths at all.
+/// Returns the combined length counts of the successful files and the list of files that failed.
+/// # Arguments
+/// * `filenames` - the filenames to read sequences from
+/// * `bam_options` - controls how BAM/SAM record lengths are calculated
+/// * `distinct_length_warning` - the number of distinct lengths that triggers the memory warning, or 0 to disable it, see `LoaderConfig::distinct_length_warning(...)`
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::bam_loader::BamLoadOptions;
+/// use fastleng::fastx_loader::gather_multifastx_stats_keep_going;
+/// use fastleng::loader::DEFAULT_DISTINCT_LENGTH_WARNING;
+/// let filenames = [
+///     "./test_data/single_string.fa",
+///     "./test_data/panic_file.fa"
+/// ];
+/// let (counts, failed_files) = gather_multifastx_stats_keep_going(&filenames, &BamLoadOptions::default(), DEFAULT_DISTINCT_LENGTH_WARNING);
+/// assert_eq!(counts.len(), 1);
+/// assert_eq!(failed_files.len(), 1);
+/// ```
+pub fn gather_multifastx_stats_keep_going<T: AsRef<str> + std::fmt::Debug>(filenames: &[T], bam_options: &BamLoadOptions, distinct_length_warning: usize) -> (BTreeMap<usize, u64>, Vec<FailedFile>) {
+    let file_config = LoaderConfig {
+        bam_options: bam_options.clone(),
+        distinct_length_warning,
+        ..Default::default()
+    };
     let mut hash_stats: BTreeMap<usize, u64> = BTreeMap::new();
+    let mut failed_files: Vec<FailedFile> = vec![];
     for filename in filenames.iter() {
-        if filename.as_ref().ends_with(".bam") || filename.as_ref().ends_with(".sam") {
-            hash_stats = match gather_bam_stats_with_seed(filename.as_ref(), Some(hash_stats)) {
+        let mut file_stats: BTreeMap<usize, u64> = BTreeMap::new();
+        let file_result = stream_multifastx_lengths(&[filename], &file_config, |_seq_id, seq_len| {
+            file_config.count_length(&mut file_stats, seq_len);
+            Ok(())
+        });
+        match file_result {
+            Ok(_count) => {
+                file_config.merge_counts(&mut hash_stats, &file_stats);
+            },
+            Err(e) => {
+                warn!("Skipping file that failed to load: {:?}", filename);
+                failed_files.push(FailedFile {
+                    filename: filename.as_ref().to_string(),
+                    error: e.to_string()
+                });
+            }
+        };
+    }
+    (hash_stats, failed_files)
+}
+
+/// This will gather sequence lengths from a fastx file, keeping each record with the probability given by `sampling`.
+/// The resulting counts are an approximation of the full file, but are reproducible for a given seed.
+/// # Arguments
+/// * `filename` - the filename to read sequences from
+/// * `sampling` - the fraction of records to keep and the seed for the random number generator
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::fastx_loader::gather_fastx_stats_sampled;
+/// use fastleng::sampling::SamplingConfig;
+/// let filename = "./test_data/long_strings.fa";
+/// let sampling = SamplingConfig { fraction: 0.5, seed: 0 };
+/// let counts: BTreeMap<usize, u64> = gather_fastx_stats_sampled(&filename, &sampling).unwrap();
+/// ```
+pub fn gather_fastx_stats_sampled(filename: &str, sampling: &SamplingConfig) -> Result<BTreeMap<usize, u64>, Box<dyn std::error::Error>> {
+    gather_multifastx_stats_sampled(&[filename], &BamLoadOptions::default(), sampling)
+}
+
+/// This will iterate through multiple fastx or BAM/SAM files, keeping each record with the probability given by `sampling`.
+/// A single random number generator is used across all files, so the sample depends on the order of `filenames`.
+/// # Arguments
+/// * `filenames` - the filenames to read sequences from
+/// * `bam_options` - controls how BAM/SAM record lengths are calculated
+/// * `sampling` - the fraction of records to keep and the seed for the random number generator
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::bam_loader::BamLoadOptions;
+/// use fastleng::fastx_loader::gather_multifastx_stats_sampled;
+/// use fastleng::sampling::SamplingConfig;
+/// let filenames = [
+///     "./test_data/single_string.fa",
+///     "./test_data/five_strings.sam"
+/// ];
+/// let sampling = SamplingConfig { fraction: 0.5, seed: 0 };
+/// let counts: BTreeMap<usize, u64> = gather_multifastx_stats_sampled(&filenames, &BamLoadOptions::default(), &sampling).unwrap();
+/// ```
+pub fn gather_multifastx_stats_sampled<T: AsRef<str> + std::fmt::Debug>(filenames: &[T], bam_options: &BamLoadOptions, sampling: &SamplingConfig) -> Result<BTreeMap<usize, u64>, Box<dyn std::error::Error>> {
+    let mut rng = sampling.build_rng();
+    let mut sampled_count: u64 = 0;
+    let mut hash_stats: BTreeMap<usize, u64> = BTreeMap::new();
+    let config = LoaderConfig {
+        bam_options: bam_options.clone(),
+        ..Default::default()
+    };
+    let total_count = stream_multifastx_lengths(filenames, &config, |_seq_id, seq_len| {
+        if rng.next_f64() < sampling.fraction {
+            config.count_length(&mut hash_stats, seq_len);
+            sampled_count += 1;
+        }
+        Ok(())
+    })?;
+    info!("Sampled {} of {} sequences.", sampled_count, total_count);
+    Ok(hash_stats)
+}
+
+/// This will gather the combined length of each read pair from an interleaved file, where records 1 & 2 are a pair, records 3 & 4 are a pair, etc.
+/// Returns an error if the file contains an odd number of records.
+/// # Arguments
+/// * `filename` - the filename to read sequences from
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::fastx_loader::gather_fastx_interleaved_stats;
+/// let filename = "./test_data/small_strings.fa";
+/// let counts: BTreeMap<usize, u64> = gather_fastx_interleaved_stats(&filename).unwrap();
+/// assert_eq!(counts.values().sum::<u64>(), 4);
+/// ```
+pub fn gather_fastx_interleaved_stats(filename: &str) -> Result<BTreeMap<usize, u64>, Box<dyn std::error::Error>> {
+    gather_multifastx_interleaved_stats(&[filename], &BamLoadOptions::default(), DEFAULT_DISTINCT_LENGTH_WARNING)
+}
+
+/// This will iterate through multiple interleaved files and gather the combined length of each read pair into a single BTreeMap.
+/// Pairs never span files, so each file must contain an even number of records.
+/// # Arguments
+/// * `filenames` - the filenames to read sequences from
+/// * `bam_options` - controls how BAM/SAM record lengths are calculated
+/// * `distinct_length_warning` - the number of distinct lengths that triggers the memory warning, or 0 to disable it, see `LoaderConfig::distinct_length_warning(...)`
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::bam_loader::BamLoadOptions;
+/// use fastleng::fastx_loader::gather_multifastx_interleaved_stats;
+/// use fastleng::loader::DEFAULT_DISTINCT_LENGTH_WARNING;
+/// let filenames = [
+///     "./test_data/small_strings.fa",
+///     "./test_data/small_strings.sam"
+/// ];
+/// let counts: BTreeMap<usize, u64> = gather_multifastx_interleaved_stats(&filenames, &BamLoadOptions::default(), DEFAULT_DISTINCT_LENGTH_WARNING).unwrap();
+/// assert_eq!(counts.values().sum::<u64>(), 8);
+/// ```
+pub fn gather_multifastx_interleaved_stats<T: AsRef<str> + std::fmt::Debug>(filenames: &[T], bam_options: &BamLoadOptions, distinct_length_warning: usize) -> Result<BTreeMap<usize, u64>, Box<dyn std::error::Error>> {
+    let config = LoaderConfig {
+        bam_options: bam_options.clone(),
+        distinct_length_warning,
+        ..Default::default()
+    };
+    let mut hash_stats: BTreeMap<usize, u64> = BTreeMap::new();
+    for filename in filenames.iter() {
+        //holds the length of the first read in a pair until the second one arrives
+        let mut pending_len: Option<usize> = None;
+        let count = stream_multifastx_lengths(&[filename], &config, |_seq_id, seq_len| {
+            match pending_len.take() {
+                Some(first_len) => {
+                    config.count_length(&mut hash_stats, first_len + seq_len);
+                },
+                None => {
+                    pending_len = Some(seq_len);
+                }
+            };
+            Ok(())
+        })?;
+
+        if pending_len.is_some() {
+            error!("Interleaved file has an odd number of records ({}): {:?}", count, filename);
+            return Err(format!("Interleaved file has an odd number of records ({}): {:?}", count, filename).into());
+        }
+    }
+    Ok(hash_stats)
+}
+
+/// Determines the format of an input file, logging any error in the same way as a parsing failure.
+/// # Arguments
+/// * `filename` - the file to inspect
+/// * `detection` - how the format should be determined
+fn detect_logged_format(filename: &str, detection: FormatDetection) -> Result<InputFormat, Box<dyn std::error::Error>> {
+    match resolve_format(filename, detection) {
+        Ok(input_format) => Ok(input_format),
+        Err(e) => {
+            error!("Error while detecting format of file: {:?}", filename);
+            error!("Error: {:?}", e);
+            Err(e)
+        }
+    }
+}
+
+/// Streams the records of standard input or a pipe in a single pass, detecting the format from the first bytes without losing them.
+/// Only FASTX data is supported this way, since the alignment loader must open the inputs itself.
+/// Returns the number of records processed along with the detected format.
+/// # Arguments
+/// * `filename` - the pipe to read, or `-` for standard input
+/// * `config` - the loading options to apply
+/// * `qual_length_mismatches` - incremented for each record with mismatched sequence and quality lengths when validating
+/// * `callback` - called with the record identifier and sequence length for each record
+fn stream_piped_lengths<F>(filename: &str, config: &LoaderConfig, qual_length_mismatches: &mut u64, callback: &mut F) -> Result<(usize, InputFormat), Box<dyn std::error::Error>>
+where
+    F: FnMut(&[u8], usize) -> Result<(), Box<dyn std::error::Error>>
+{
+    let sniffed = match open_sniffed_stream(filename) {
+        Ok(sniffed) => sniffed,
+        Err(e) => {
+            error!("Error while opening input stream: {:?}", filename);
+            error!("Error: {:?}", e);
+            return Err(e);
+        }
+    };
+    if sniffed.input_format.is_alignment() {
+        error!("BAM/SAM/CRAM data is only supported from files: {:?}", filename);
+        return Err(format!("Alignment data cannot be read from a pipe or stdin: {:?}", filename).into());
+    }
+    let input_format: InputFormat = sniffed.input_format;
+    let mismatches: Option<Arc<AtomicU64>> = config.validate_qual_length.then(|| Arc::new(AtomicU64::new(0)));
+    let reader = validated_contents(sniffed, filename, mismatches.as_ref());
+    match stream_fastx_reader_lengths(reader, filename, config, callback) {
+        Ok(result) => {
+            if let Some(mismatches) = mismatches {
+                *qual_length_mismatches += mismatches.load(Ordering::Relaxed);
+            }
+            Ok((result, input_format))
+        },
+        Err(e) => {
+            error!("Error while parsing FASTX file: {:?}", filename);
+            error!("Error: {:?}", e);
+            Err(e)
+        }
+    }
+}
+
+/// This will stream through multiple fastx or BAM/SAM files and call `callback` with the identifier and length of each record.
+/// The format of each file is determined according to `config.format_detection`.
+/// Standard input (`-`), named pipes, and process substitution are read in a single pass with their format always detected from the contents; only FASTX data is supported from these.
+/// Only the per-record options of `config` (`bam_options`, `head`, `max_records`, `format_detection`, and `min_mean_qual`) are used here; any sampling or length filters are left to the caller.
+/// Records failing `min_mean_qual` are not passed to `callback`, and asking for it on a file without quality scores is an error.
+/// Returns the total number of records processed, including any that failed `min_mean_qual`.
+/// # Arguments
+/// * `filenames` - the filenames to read sequences from
+/// * `config` - the loading options to apply to each file
+/// * `callback` - called with the record identifier and sequence length for each record
+/// # Examples
+/// ```
+/// use fastleng::fastx_loader::stream_multifastx_lengths;
+/// use fastleng::loader::LoaderConfig;
+/// let filenames = [
+///     "./test_data/single_string.fa",
+///     "./test_data/five_strings.sam"
+/// ];
+/// let mut total_length: usize = 0;
+/// let count = stream_multifastx_lengths(&filenames, &LoaderConfig::new(), |_seq_id, seq_len| {
+///     total_length += seq_len;
+///     Ok(())
+/// }).unwrap();
+/// assert_eq!(count, 6);
+/// assert_eq!(total_length, 16);
+/// ```
+pub fn stream_multifastx_lengths<T, F>(filenames: &[T], config: &LoaderConfig, callback: F) -> Result<usize, Box<dyn std::error::Error>>
+where
+    T: AsRef<str> + std::fmt::Debug,
+    F: FnMut(&[u8], usize) -> Result<(), Box<dyn std::error::Error>>
+{
+    stream_multifastx_lengths_with_formats(filenames, config, callback).map(|(total_count, _input_formats)| total_count)
+}
+
+/// This is identical to `stream_multifastx_lengths(...)`, but also returns the format that each file was loaded as, in the same order as `filenames`.
+/// # Arguments
+/// * `filenames` - the filenames to read sequences from
+/// * `config` - the loading options to apply to each file
+/// * `callback` - called with the record identifier and sequence length for each record
+/// # Examples
+/// ```
+/// use fastleng::fastx_loader::stream_multifastx_lengths_with_formats;
+/// use fastleng::format::InputFormat;
+/// use fastleng::loader::LoaderConfig;
+/// let filenames = [
+///     "./test_data/single_string.fa",
+///     "./test_data/five_strings.sam"
+/// ];
+/// let (count, input_formats) = stream_multifastx_lengths_with_formats(&filenames, &LoaderConfig::new(), |_seq_id, _seq_len| Ok(())).unwrap();
+/// assert_eq!(count, 6);
+/// assert_eq!(input_formats, vec![InputFormat::Fasta, InputFormat::Sam]);
+/// ```
+pub fn stream_multifastx_lengths_with_formats<T, F>(filenames: &[T], config: &LoaderConfig, callback: F) -> Result<(usize, Vec<InputFormat>), Box<dyn std::error::Error>>
+where
+    T: AsRef<str> + std::fmt::Debug,
+    F: FnMut(&[u8], usize) -> Result<(), Box<dyn std::error::Error>>
+{
+    stream_multifastx_lengths_with_summary(filenames, config, callback).map(|stream_summary| (stream_summary.total_records, stream_summary.input_formats))
+}
+
+/// The totals from streaming through multiple files with `stream_multifastx_lengths_with_summary(...)`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StreamSummary {
+    /// The total number of records processed, including any that failed `min_mean_qual`
+    pub total_records: usize,
+    /// The format each file was loaded as, in the order of the filenames
+    pub input_formats: Vec<InputFormat>,
+    /// If `validate_qual_length` is set, the number of FASTQ records skipped for mismatched sequence and quality lengths; otherwise 0
+    pub qual_length_mismatches: u64
+}
+
+/// This is identical to `stream_multifastx_lengths_with_formats(...)`, but returns a `StreamSummary` that also has the number of malformed FASTQ records found with `config.validate_qual_length`.
+/// # Arguments
+/// * `filenames` - the filenames to read sequences from
+/// * `config` - the loading options to apply to each file
+/// * `callback` - called with the record identifier and sequence length for each record
+/// # Examples
+/// ```
+/// use fastleng::fastx_loader::stream_multifastx_lengths_with_summary;
+/// use fastleng::loader::LoaderConfig;
+/// let config = LoaderConfig::new().validate_qual_length(true);
+/// let stream_summary = stream_multifastx_lengths_with_summary(&["./test_data/qual_length_mismatch.fq"], &config, |_seq_id, _seq_len| Ok(())).unwrap();
+/// assert_eq!(stream_summary.total_records, 3);
+/// assert_eq!(stream_summary.qual_length_mismatches, 1);
+/// ```
+pub fn stream_multifastx_lengths_with_summary<T, F>(filenames: &[T], config: &LoaderConfig, mut callback: F) -> Result<StreamSummary, Box<dyn std::error::Error>>
+where
+    T: AsRef<str> + std::fmt::Debug,
+    F: FnMut(&[u8], usize) -> Result<(), Box<dyn std::error::Error>>
+{
+    let mut total_count: usize = 0;
+    let mut qual_length_mismatches: u64 = 0;
+    let mut input_formats: Vec<InputFormat> = Vec::with_capacity(filenames.len());
+    for filename in filenames.iter() {
+        if config.bam_options.region.is_some() && is_stream_input(filename.as_ref()) {
+            return Err(format!("A region can only be read from an indexed BAM/CRAM file, not a stream: {:?}", filename).into());
+        }
+        if is_stream_input(filename.as_ref()) {
+            let (piped_count, input_format) = stream_piped_lengths(filename.as_ref(), config, &mut qual_length_mismatches, &mut callback)?;
+            total_count += piped_count;
+            input_formats.push(input_format);
+            continue;
+        }
+
+        let input_format: InputFormat = detect_logged_format(filename.as_ref(), config.format_detection)?;
+        input_formats.push(input_format);
+        if input_format.is_alignment() {
+            if config.min_mean_qual.is_some() {
+                error!("Mean quality filtering is only supported for FASTQ files: {:?}", filename);
+                return Err(format!("Mean quality filtering is not supported for alignment file: {:?}", filename).into());
+            }
+            total_count += match stream_bam_lengths(filename.as_ref(), config, &mut callback) {
                 Ok(result) => result,
                 Err(e) => {
                     error!("Error while parsing BAM file: {:?}", filename);
@@ -94,7 +1159,13 @@ pub fn gather_multifastx_stats<T: AsRef<str> + std::fmt::Debug>(filenames: &[T])
             };
         }
         else {
-            hash_stats = match gather_fastx_stats_with_seed(filename.as_ref(), Some(hash_stats)) {
+            if config.bam_options.region.is_some() {
+                return Err(format!("A region can only be read from an indexed BAM/CRAM file, not FASTX: {:?}", filename).into());
+            }
+            let fastx_result = stream_fastx_records(filename.as_ref(), config, &mut qual_length_mismatches, |seq_rec| {
+                filter_fastx_record(seq_rec, filename.as_ref(), config, &mut callback)
+            });
+            total_count += match fastx_result {
                 Ok(result) => result,
                 Err(e) => {
                     error!("Error while parsing FASTX file: {:?}", filename);
@@ -104,12 +1175,18 @@ pub fn gather_multifastx_stats<T: AsRef<str> + std::fmt::Debug>(filenames: &[T])
             };
         }
     }
-    Ok(hash_stats)
+    Ok(StreamSummary {
+        total_records: total_count,
+        input_formats,
+        qual_length_mismatches
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::length_stats::compute_total_counts;
+    use crate::loader::gather_multi_stats;
     
     /// This one is a single sequence "A"
     fn stats_basic_fasta() -> BTreeMap<usize, u64> {
@@ -156,7 +1233,7 @@ mod tests {
         let expected = stats_basic_fasta();
 
         //now do it for real
-        let hash_stats = gather_fastx_stats(&filename).unwrap();
+        let hash_stats = gather_fastx_stats(filename).unwrap();
         assert_eq!(hash_stats, expected);
     }
 
@@ -169,7 +1246,7 @@ mod tests {
         let expected = stats_basic_fasta2();
 
         //now do it for real
-        let hash_stats = gather_fastx_stats(&filename).unwrap();
+        let hash_stats = gather_fastx_stats(filename).unwrap();
         assert_eq!(hash_stats, expected);
     }
 
@@ -182,7 +1259,7 @@ mod tests {
         let expected = stats_basic_fasta3();
 
         //now do it for real
-        let hash_stats = gather_fastx_stats(&filename).unwrap();
+        let hash_stats = gather_fastx_stats(filename).unwrap();
         assert_eq!(hash_stats, expected);
     }
 
@@ -195,15 +1272,110 @@ mod tests {
         let expected = stats_basic_fasta4();
 
         //now do it for real
-        let hash_stats = gather_fastx_stats(&filename).unwrap();
+        let hash_stats = gather_fastx_stats(filename).unwrap();
         assert_eq!(hash_stats, expected);
     }
 
+    #[test]
+    fn test_multiline_fasta() {
+        //sequences wrapped at 60 and 80 columns, with the true length in each header
+        let filename = "./test_data/multiline.fa";
+        let mut streamed: Vec<(String, usize)> = vec![];
+        stream_fastx_lengths(filename, |seq_id, seq_len| {
+            streamed.push((String::from_utf8_lossy(seq_id).to_string(), seq_len));
+            Ok(())
+        }).unwrap();
+        assert_eq!(streamed.len(), 6);
+        for (seq_id, seq_len) in streamed.iter() {
+            let header_length: usize = seq_id.rsplit_once("length=").unwrap().1.parse().unwrap();
+            assert_eq!(*seq_len, header_length, "{seq_id}");
+        }
+
+        //independently count the bases by joining the wrapped lines
+        let contents: String = std::fs::read_to_string(filename).unwrap();
+        let joined_lengths: Vec<usize> = contents.split('>')
+            .skip(1)
+            .map(|record| record.lines().skip(1).map(|line| line.trim_end().len()).sum())
+            .collect();
+        let streamed_lengths: Vec<usize> = streamed.iter().map(|(_seq_id, seq_len)| *seq_len).collect();
+        assert_eq!(streamed_lengths, joined_lengths);
+        assert_eq!(streamed_lengths, vec![120, 137, 1000, 79, 1, 241]);
+    }
+
+    #[test]
+    fn test_empty_sequence_fasta() {
+        //a header with a blank line and a header directly followed by another header both load as length 0
+        let expected: BTreeMap<usize, u64> = [(0, 2), (2, 1), (4, 1), (8, 1)].iter().cloned().collect();
+        assert_eq!(gather_fastx_stats("./test_data/empty_sequence.fa").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_byte_order_mark() {
+        //a BOM in front of the first header, plain or compressed, is skipped
+        let expected = gather_fastx_stats("./test_data/single_string.fa").unwrap();
+        assert_eq!(gather_fastx_stats("./test_data/bom_single_string.fa").unwrap(), expected);
+        assert_eq!(gather_fastx_stats("./test_data/bom_single_string.fa.gz").unwrap(), expected);
+
+        //the BOM does not hide the format from detection either
+        let config = LoaderConfig::new().format_detection(FormatDetection::Content);
+        let (_count, input_formats) = stream_multifastx_lengths_with_formats(&["./test_data/bom_single_string.fa"], &config, |_seq_id, _seq_len| Ok(())).unwrap();
+        assert_eq!(input_formats, vec![InputFormat::Fasta]);
+    }
+
+    #[test]
+    fn test_qual_length_mismatch() {
+        //the third record has 8 bases but only 4 qualities, which fails by default and names the record
+        let filenames = ["./test_data/qual_length_mismatch.fq"];
+        let error = stream_multifastx_lengths(&filenames, &LoaderConfig::new(), |_seq_id, _seq_len| Ok(())).unwrap_err();
+        assert!(error.to_string().contains("FASTQ record 3"), "{}", error);
+
+        //when validating, the mismatch is counted and skipped, and the records after it are still loaded
+        let mut lengths: Vec<usize> = vec![];
+        let config = LoaderConfig::new().validate_qual_length(true);
+        let stream_summary = stream_multifastx_lengths_with_summary(&filenames, &config, |_seq_id, seq_len| {
+            lengths.push(seq_len);
+            Ok(())
+        }).unwrap();
+        assert_eq!(lengths, vec![4, 6, 2]);
+        assert_eq!(stream_summary, StreamSummary { total_records: 3, input_formats: vec![InputFormat::Fastq], qual_length_mismatches: 1 });
+
+        //later files are still loaded, and well-formed files have no mismatches
+        let stream_summary = stream_multifastx_lengths_with_summary(&["./test_data/qual_length_mismatch.fq", "./test_data/five_strings.fq"], &config, |_seq_id, _seq_len| Ok(())).unwrap();
+        assert_eq!(stream_summary.total_records, 8);
+        assert_eq!(stream_summary.qual_length_mismatches, 1);
+        let stream_summary = stream_multifastx_lengths_with_summary(&["./test_data/five_strings.fq"], &config, |_seq_id, _seq_len| Ok(())).unwrap();
+        assert_eq!(stream_summary.qual_length_mismatches, 0);
+    }
+
+    #[test]
+    fn test_qual_length_validator() {
+        //every mismatched record is dropped, including with Windows line endings
+        let fastq: &[u8] = b"@r1\nACGT\n+\nIIII\n@r2\nACGTAC\n+\nIII\n@r3\r\nACG\r\n+\r\nIII\r\n@r4\nAC\n+\nI\n@r5\nA\n+\nI\n";
+        let mismatches = Arc::new(AtomicU64::new(0));
+        let validator = QualLengthValidator::new(fastq, "in-memory", Arc::clone(&mismatches));
+        let mut records: Vec<(Vec<u8>, usize)> = vec![];
+        stream_fastx_reader_lengths(validator, "in-memory", &LoaderConfig::new(), |seq_id, seq_len| {
+            records.push((seq_id.to_vec(), seq_len));
+            Ok(())
+        }).unwrap();
+        assert_eq!(records, vec![(b"r1".to_vec(), 4), (b"r3".to_vec(), 3), (b"r5".to_vec(), 1)]);
+        assert_eq!(mismatches.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_gzip_fasta() {
+        //the gzipped copy should be indistinguishable from the plain file
+        let plain_stats = gather_fastx_stats("./test_data/long_strings.fa").unwrap();
+        let gzip_stats = gather_fastx_stats("./test_data/long_strings.fa.gz").unwrap();
+        assert_eq!(gzip_stats, plain_stats);
+        assert_eq!(gzip_stats, stats_basic_fasta4());
+    }
+
     #[test]
     #[should_panic]
     fn test_error_handling() {
         let filename = "./test_data/panic_file.fa";
-        let _hash_stats = gather_fastx_stats(&filename).unwrap();
+        let _hash_stats = gather_fastx_stats(filename).unwrap();
     }
 
     #[test]
@@ -277,4 +1449,300 @@ mod tests {
         ];
         let _hash_stats = gather_multifastx_stats(&filenames).unwrap();
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_stream_fastx_lengths() {
+        let filename = "./test_data/small_strings.fa";
+
+        //collect every record and make sure it matches the histogram
+        let mut records: Vec<(String, usize)> = vec![];
+        let count = stream_fastx_lengths(filename, |seq_id, seq_len| {
+            records.push((String::from_utf8(seq_id.to_vec()).unwrap(), seq_len));
+            Ok(())
+        }).unwrap();
+        assert_eq!(count, records.len());
+
+        let mut hash_stats: BTreeMap<usize, u64> = BTreeMap::new();
+        for (_seq_id, seq_len) in records.iter() {
+            *hash_stats.entry(*seq_len).or_insert(0) += 1;
+        }
+        assert_eq!(hash_stats, stats_basic_fasta3());
+    }
+
+    #[test]
+    fn test_sampled_fasta() {
+        let filename = "./test_data/small_strings.fa";
+
+        //keeping everything or nothing is exact
+        let sampling = SamplingConfig { fraction: 1.0, seed: 0 };
+        let hash_stats = gather_fastx_stats_sampled(filename, &sampling).unwrap();
+        assert_eq!(hash_stats, stats_basic_fasta3());
+
+        let sampling = SamplingConfig { fraction: 0.0, seed: 0 };
+        let hash_stats = gather_fastx_stats_sampled(filename, &sampling).unwrap();
+        assert!(hash_stats.is_empty());
+
+        //partial samples are reproducible with the same seed
+        let sampling = SamplingConfig { fraction: 0.5, seed: 5 };
+        let hash_stats = gather_fastx_stats_sampled(filename, &sampling).unwrap();
+        let hash_stats2 = gather_fastx_stats_sampled(filename, &sampling).unwrap();
+        assert_eq!(hash_stats, hash_stats2);
+        let sampled_total: u64 = hash_stats.values().sum();
+        assert!(sampled_total <= 8);
+    }
+
+    #[test]
+    fn test_gapped_fasta() {
+        let filename = "./test_data/gapped_strings.fa";
+        let gapped_counts = gather_fastx_n_stats_with_seed(filename, None).unwrap();
+
+        //the full lengths match the normal loader
+        let hash_stats = gather_fastx_stats(filename).unwrap();
+        assert_eq!(gapped_counts.length_counts, hash_stats);
+
+        //no gaps, NNNN, nn (lowercase), and a line-wrapped record with NN across the line break
+        let expected_full: BTreeMap<usize, u64> = [(10, 3), (12, 1)].iter().cloned().collect();
+        let expected_ungapped: BTreeMap<usize, u64> = [(6, 1), (8, 1), (10, 2)].iter().cloned().collect();
+        assert_eq!(gapped_counts.length_counts, expected_full);
+        assert_eq!(gapped_counts.ungapped_counts, expected_ungapped);
+        assert_eq!(gapped_counts.total_n_bases(), 8);
+
+        //filtering by full length must drop the same records from both maps; the 12bp record has 10 ungapped bases like the 10bp one without gaps
+        let short_counts = gapped_counts.retain_lengths(|seq_len| seq_len <= 10);
+        let expected_short_ungapped: BTreeMap<usize, u64> = [(6, 1), (8, 1), (10, 1)].iter().cloned().collect();
+        assert_eq!(short_counts.length_counts, [(10, 3)].iter().cloned().collect());
+        assert_eq!(short_counts.ungapped_counts, expected_short_ungapped);
+        assert_eq!(short_counts.total_n_bases(), 6);
+        let (full_bases, _full_seqs) = compute_total_counts(&short_counts.length_counts);
+        let (ungapped_bases, _ungapped_seqs) = compute_total_counts(&short_counts.ungapped_counts);
+        assert_eq!(full_bases - ungapped_bases, short_counts.total_n_bases());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_gapped_bam_error() {
+        let filenames = [
+            "./test_data/gapped_strings.fa",
+            "./test_data/five_strings.sam"
+        ];
+        let _gapped_counts = gather_multifastx_n_stats(&filenames, DEFAULT_DISTINCT_LENGTH_WARNING).unwrap();
+    }
+
+    #[test]
+    fn test_interleaved_fasta() {
+        //small_strings.fa has lengths 4, 2, 1, 2, 1, 3, 4, 1 in that order
+        let filename = "./test_data/small_strings.fa";
+        let hash_stats = gather_fastx_interleaved_stats(filename).unwrap();
+        let expected: BTreeMap<usize, u64> = [(3, 1), (4, 1), (5, 1), (6, 1)].iter().cloned().collect();
+        assert_eq!(hash_stats, expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_interleaved_odd_error() {
+        let filename = "./test_data/five_strings.fa";
+        let _hash_stats = gather_fastx_interleaved_stats(filename).unwrap();
+    }
+
+    #[test]
+    fn test_multifastx_keep_going() {
+        let filenames = [
+            "./test_data/single_string.fa",
+            "./test_data/panic_file.fa",
+            "./test_data/five_strings.fa",
+            "./test_data/does_not_exist.fa"
+        ];
+        let (hash_stats, failed_files) = gather_multifastx_stats_keep_going(&filenames, &BamLoadOptions::default(), DEFAULT_DISTINCT_LENGTH_WARNING);
+
+        //the good files should be combined
+        let mut expected = stats_basic_fasta();
+        merge_length_counts(&mut expected, &stats_basic_fasta2());
+        assert_eq!(hash_stats, expected);
+
+        //the bad files should be reported in order
+        let failed_names: Vec<&str> = failed_files.iter().map(|f| f.filename.as_str()).collect();
+        assert_eq!(failed_names, vec!["./test_data/panic_file.fa", "./test_data/does_not_exist.fa"]);
+    }
+
+    #[test]
+    fn test_multifastx_head() {
+        //long_strings has 7 records, so this is truly truncated; single_string has fewer than the limit
+        let filenames = [
+            "./test_data/long_strings.fa",
+            "./test_data/single_string.fa",
+            "./test_data/long_strings.sam"
+        ];
+        let hash_stats = gather_multifastx_stats_head(&filenames, &BamLoadOptions::default(), Some(3)).unwrap();
+        let total_seqs: u64 = hash_stats.values().sum();
+        assert_eq!(total_seqs, 7);
+
+        //no limit is the same as the regular loader
+        let full_stats = gather_multifastx_stats_head(&filenames, &BamLoadOptions::default(), None).unwrap();
+        assert_eq!(full_stats, gather_multifastx_stats(&filenames).unwrap());
+    }
+
+    #[test]
+    fn test_multifastx_sniffed_formats() {
+        //a SAM file with an unrecognized extension should be sniffed and loaded as SAM
+        let misnamed_sam = std::env::temp_dir().join("fastleng_test_misnamed_sam.txt");
+        std::fs::copy("./test_data/five_strings.sam", &misnamed_sam).unwrap();
+        let filenames = [
+            misnamed_sam.to_str().unwrap(),
+            "./test_data/five_strings.fq"
+        ];
+        let hash_stats = gather_multifastx_stats(&filenames).unwrap();
+        std::fs::remove_file(&misnamed_sam).unwrap();
+
+        let mut expected = stats_basic_fasta2();
+        merge_length_counts(&mut expected, &stats_basic_fasta2());
+        assert_eq!(hash_stats, expected);
+    }
+
+    #[test]
+    fn test_truncated_gzip() {
+        //this file was cut in half partway through the gzip stream
+        let error_msg: String = gather_fastx_stats("./test_data/truncated.fq.gz").unwrap_err().to_string();
+        assert!(error_msg.starts_with("File appears to be truncated or corrupt, "), "{}", error_msg);
+        assert!(error_msg.contains("records were read successfully"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stream_named_pipe() {
+        //a named pipe fed by a separate process; no extension, so the contents decide the format
+        let run_through_pipe = |source_fn: &str, pipe_name: &str| {
+            let pipe_path = std::env::temp_dir().join(pipe_name);
+            let pipe_str: &str = pipe_path.to_str().unwrap();
+            let _ = std::fs::remove_file(&pipe_path);
+            assert!(std::process::Command::new("mkfifo").arg(pipe_str).status().unwrap().success());
+            assert!(is_stream_input(pipe_str));
+            let mut writer = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(format!("cat {source_fn} > {pipe_str}"))
+                .spawn()
+                .unwrap();
+            let mut hash_stats: BTreeMap<usize, u64> = BTreeMap::new();
+            let load_result = stream_multifastx_lengths(&[pipe_str], &LoaderConfig::new(), |_seq_id, seq_len| {
+                *hash_stats.entry(seq_len).or_insert(0) += 1;
+                Ok(())
+            });
+            writer.wait().unwrap();
+            std::fs::remove_file(&pipe_path).unwrap();
+            load_result.map(|_count| hash_stats)
+        };
+
+        let piped_stats = run_through_pipe("./test_data/five_strings.fq", "fastleng_test_pipe_fq").unwrap();
+        assert_eq!(piped_stats, stats_basic_fasta2());
+        let piped_stats = run_through_pipe("./test_data/long_strings.fa.gz", "fastleng_test_pipe_gz").unwrap();
+        assert_eq!(piped_stats, stats_basic_fasta4());
+        assert!(run_through_pipe("./test_data/five_strings.sam", "fastleng_test_pipe_sam").is_err());
+    }
+
+    #[test]
+    fn test_paired_stats() {
+        //each file against itself doubles every length
+        let paired_stats = gather_paired_stats("./test_data/long_strings.fa", "./test_data/long_strings.fa.gz", &LoaderConfig::new()).unwrap();
+        let expected: BTreeMap<usize, u64> = [(100, 2), (200, 2), (300, 2), (2000, 1)].iter().cloned().collect();
+        assert_eq!(paired_stats, expected);
+
+        //head limits the number of pairs
+        let head_stats = gather_paired_stats("./test_data/five_strings.fa", "./test_data/five_strings.fq", &LoaderConfig::new().head(2)).unwrap();
+        let expected: BTreeMap<usize, u64> = [(2, 1), (4, 1)].iter().cloned().collect();
+        assert_eq!(head_stats, expected);
+
+        //different record counts are an error in either order, as are alignment files
+        assert!(gather_paired_stats("./test_data/five_strings.fa", "./test_data/single_string.fa", &LoaderConfig::new()).is_err());
+        assert!(gather_paired_stats("./test_data/single_string.fa", "./test_data/five_strings.fa", &LoaderConfig::new()).is_err());
+        assert!(gather_paired_stats("./test_data/five_strings.fa", "./test_data/five_strings.sam", &LoaderConfig::new()).is_err());
+    }
+
+    #[test]
+    fn test_bgzf_decompression_threads() {
+        //the parallel path (when built) must count exactly what the sequential path does, including records split across blocks
+        let filename = "./test_data/bgzf_strings.fq.gz";
+        let sequential_stats = gather_multi_stats(&[filename], &LoaderConfig::new()).unwrap();
+        assert_eq!(sequential_stats.values().sum::<u64>(), 300);
+        assert_eq!(sequential_stats.iter().map(|(seq_len, count)| *seq_len as u64 * count).sum::<u64>(), 35473);
+        for threads in [2, 4, 16] {
+            let parallel_stats = gather_multi_stats(&[filename], &LoaderConfig::new().decompression_threads(threads)).unwrap();
+            assert_eq!(parallel_stats, sequential_stats);
+        }
+
+        //plain gzip falls back to the sequential path
+        let plain_stats = gather_multi_stats(&["./test_data/long_strings.fa.gz"], &LoaderConfig::new().decompression_threads(4)).unwrap();
+        assert_eq!(plain_stats, stats_basic_fasta4());
+    }
+
+    #[test]
+    fn test_crlf_line_endings() {
+        //Windows line endings must give the same lengths and names as the LF originals, including wrapped FASTA
+        for (lf_filename, crlf_filename) in [("./test_data/multiline.fa", "./test_data/crlf_multiline.fa"), ("./test_data/five_strings.fq", "./test_data/crlf_strings.fq")] {
+            let collect_records = |filename: &str| {
+                let mut streamed: Vec<(Vec<u8>, usize)> = vec![];
+                stream_fastx_lengths(filename, |seq_id, seq_len| {
+                    streamed.push((seq_id.to_vec(), seq_len));
+                    Ok(())
+                }).unwrap();
+                streamed
+            };
+            assert_eq!(collect_records(crlf_filename), collect_records(lf_filename));
+            let config = LoaderConfig::new().validate_qual_length(true);
+            assert_eq!(gather_multi_stats(&[crlf_filename], &config).unwrap(), gather_multi_stats(&[lf_filename], &config).unwrap());
+        }
+
+        //a mix of endings in one file, without a final newline
+        let mixed_stats = gather_fastx_stats("./test_data/mixed_line_endings.fa").unwrap();
+        let expected: BTreeMap<usize, u64> = [(1, 1), (3, 1), (6, 1)].iter().cloned().collect();
+        assert_eq!(mixed_stats, expected);
+    }
+
+    #[test]
+    fn test_parse_error_record_index() {
+        //the third record is missing its "+" line
+        let filename = "./test_data/malformed_record.fq";
+        let error_message: String = gather_fastx_stats(filename).unwrap_err().to_string();
+        assert!(error_message.contains("Failed at record 3 of \"./test_data/malformed_record.fq\""), "{error_message}");
+
+        //the same index is reported from a reader
+        let contents: Vec<u8> = std::fs::read(filename).unwrap();
+        let reader_error = stream_fastx_reader_lengths(&contents[..], "in-memory", &LoaderConfig::new(), |_seq_id, _seq_len| Ok(())).unwrap_err();
+        assert!(reader_error.to_string().starts_with("Failed at record 3 of \"in-memory\""), "{reader_error}");
+    }
+
+    #[test]
+    fn test_quality_by_length() {
+        let filenames = ["./test_data/quality_by_length.fq"];
+        let quality_counts: LengthQualityCounts = gather_multifastx_quality_stats(&filenames, &LoaderConfig::new()).unwrap();
+        assert_eq!(quality_counts.length_counts, gather_multifastx_stats(&filenames).unwrap());
+        let expected: BTreeMap<usize, f64> = [(2, 25.0), (4, 30.0), (8, 25.0)].iter().cloned().collect();
+        assert_eq!(quality_counts.mean_quality_by_length(), expected);
+
+        //the record filters apply
+        let filtered_counts = gather_multifastx_quality_stats(&filenames, &LoaderConfig::new().min_mean_qual(26.0).max_length(5)).unwrap();
+        let expected: BTreeMap<usize, f64> = [(4, 40.0)].iter().cloned().collect();
+        assert_eq!(filtered_counts.mean_quality_by_length(), expected);
+
+        //only FASTQ is supported
+        assert!(gather_multifastx_quality_stats(&["./test_data/five_strings.fa"], &LoaderConfig::new()).is_err());
+        assert!(gather_multifastx_quality_stats(&["./test_data/five_strings.sam"], &LoaderConfig::new()).is_err());
+        assert_eq!(LengthQualityCounts::default().mean_quality_by_length(), BTreeMap::new());
+    }
+
+    #[test]
+    fn test_ambiguous_bases() {
+        let filenames = ["./test_data/ambiguous_bases.fa"];
+        let counts: AmbiguousLengthCounts = gather_multifastx_ambiguous_stats(&filenames, &LoaderConfig::new()).unwrap();
+        assert_eq!(counts.length_counts, gather_multifastx_stats(&filenames).unwrap());
+        let expected: BTreeMap<char, u64> = [
+            ('B', 1), ('D', 1), ('H', 1), ('K', 1), ('M', 1), ('N', 4), ('R', 1), ('S', 1), ('V', 1), ('W', 1), ('Y', 1)
+        ].iter().cloned().collect();
+        assert_eq!(counts.ambiguous_counts, expected);
+
+        //filtered sequences are not counted, but every code is still reported
+        let filtered_counts = gather_multifastx_ambiguous_stats(&filenames, &LoaderConfig::new().max_length(8)).unwrap();
+        assert_eq!(filtered_counts.ambiguous_counts[&'R'], 1);
+        assert_eq!(filtered_counts.ambiguous_counts[&'N'], 0);
+        assert_eq!(filtered_counts.ambiguous_counts.len(), AMBIGUOUS_BASES.len());
+        assert!(gather_multifastx_ambiguous_stats(&["./test_data/five_strings.sam"], &LoaderConfig::new()).is_err());
+    }
+}
@@ -0,0 +1,450 @@
+
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{Cursor, Read};
+
+/// The number of (decompressed) bytes inspected when detecting the format of a file
+const SNIFF_LENGTH: u64 = 65536;
+
+/// The UTF-8 byte order mark that some Windows editors add to the start of text files
+pub const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+
+/// The input name that reads from standard input instead of a file
+pub const STDIN_NAME: &str = "-";
+
+/// The URL schemes that are handed to htslib instead of being opened as local paths
+const REMOTE_URL_SCHEMES: [&str; 5] = ["http://", "https://", "ftp://", "s3://", "gs://"];
+
+/// The input file formats that can be detected from the file contents
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputFormat {
+    /// A FASTA file, starting with `>`
+    Fasta,
+    /// A FASTQ file, starting with `@` but without a SAM structure
+    Fastq,
+    /// A text SAM file, starting with a header line (e.g. `@HD`) or a tab-delimited alignment line
+    Sam,
+    /// A binary BAM file, a BGZF compressed stream starting with `BAM\1`
+    Bam,
+    /// A CRAM file, starting with `CRAM`
+    Cram,
+    /// The format could not be determined
+    Unknown
+}
+
+impl InputFormat {
+    /// Returns true if this format is handled by the BAM/SAM loader
+    pub fn is_alignment(&self) -> bool {
+        matches!(self, InputFormat::Sam | InputFormat::Bam | InputFormat::Cram)
+    }
+
+    /// Returns the lowercase name of the format, e.g. "fastq"
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputFormat::Fasta => "fasta",
+            InputFormat::Fastq => "fastq",
+            InputFormat::Sam => "sam",
+            InputFormat::Bam => "bam",
+            InputFormat::Cram => "cram",
+            InputFormat::Unknown => "unknown"
+        }
+    }
+
+    /// Parses a format from its name (case-insensitive), the reverse of `name()`; "unknown" and anything unrecognized return `None`.
+    /// # Examples
+    /// ```
+    /// use fastleng::format::InputFormat;
+    /// assert_eq!(InputFormat::from_name("FASTQ"), Some(InputFormat::Fastq));
+    /// assert_eq!(InputFormat::from_name("bam"), Some(InputFormat::Bam));
+    /// assert_eq!(InputFormat::from_name("unknown"), None);
+    /// ```
+    pub fn from_name(name: &str) -> Option<InputFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "fasta" => Some(InputFormat::Fasta),
+            "fastq" => Some(InputFormat::Fastq),
+            "sam" => Some(InputFormat::Sam),
+            "bam" => Some(InputFormat::Bam),
+            "cram" => Some(InputFormat::Cram),
+            _ => None
+        }
+    }
+}
+
+/// This will describe the formats of a set of inputs with a single name, which is "mixed" if they are not all the same.
+/// # Arguments
+/// * `formats` - the format of each input
+/// # Examples
+/// ```
+/// use fastleng::format::{describe_formats, InputFormat};
+/// assert_eq!(describe_formats(&[InputFormat::Fastq, InputFormat::Fastq]), "fastq");
+/// assert_eq!(describe_formats(&[InputFormat::Fastq, InputFormat::Bam]), "mixed");
+/// ```
+pub fn describe_formats(formats: &[InputFormat]) -> &'static str {
+    match formats.first() {
+        None => InputFormat::Unknown.name(),
+        Some(first_format) if formats.iter().all(|input_format| input_format == first_format) => first_format.name(),
+        Some(_) => "mixed"
+    }
+}
+
+/// Controls how the format of each input file is determined
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FormatDetection {
+    /// Trust unambiguous FASTX extensions (e.g. `.fq.gz`) and sniff the contents of everything else
+    #[default]
+    Auto,
+    /// Always sniff the file contents, ignoring the extension
+    Content,
+    /// Only use the file extension, never reading the contents; unrecognized extensions are an error
+    Extension
+}
+
+/// This will determine the format of a file from its extension alone, returning `None` if the extension is not recognized.
+/// A trailing `.gz` is ignored, so `reads.fq.gz` is FASTQ.
+/// # Arguments
+/// * `filename` - the file name to inspect
+/// * `include_alignments` - if true, the SAM/BAM/CRAM extensions are also recognized; otherwise only the unambiguous FASTX extensions are
+/// # Examples
+/// ```
+/// use fastleng::format::{format_from_extension, InputFormat};
+/// assert_eq!(format_from_extension("reads.fq.gz", false), Some(InputFormat::Fastq));
+/// assert_eq!(format_from_extension("contigs.fasta", false), Some(InputFormat::Fasta));
+/// assert_eq!(format_from_extension("reads.bam", false), None);
+/// assert_eq!(format_from_extension("reads.bam", true), Some(InputFormat::Bam));
+/// ```
+pub fn format_from_extension(filename: &str, include_alignments: bool) -> Option<InputFormat> {
+    let lower_name: String = filename.to_ascii_lowercase();
+    let base_name: &str = lower_name.strip_suffix(".gz").unwrap_or(&lower_name);
+    let extension: &str = base_name.rsplit_once('.').map(|(_prefix, extension)| extension).unwrap_or("");
+    match extension {
+        "fa" | "fasta" => Some(InputFormat::Fasta),
+        "fq" | "fastq" => Some(InputFormat::Fastq),
+        "sam" if include_alignments => Some(InputFormat::Sam),
+        //compressed BAM/CRAM never carry an extra .gz
+        "bam" if include_alignments && base_name.len() == lower_name.len() => Some(InputFormat::Bam),
+        "cram" if include_alignments && base_name.len() == lower_name.len() => Some(InputFormat::Cram),
+        _ => None
+    }
+}
+
+/// Returns true if `filename` is standard input (`-`) or an existing special file such as a named pipe or process substitution (e.g. `/dev/fd/63`).
+/// These can only be read once, so their contents must not be inspected before loading; see `open_sniffed_stream(...)`.
+/// # Arguments
+/// * `filename` - the input name to check
+/// # Examples
+/// ```
+/// use fastleng::format::is_stream_input;
+/// assert!(is_stream_input("-"));
+/// assert!(!is_stream_input("./test_data/five_strings.fa"));
+/// assert!(!is_stream_input("./test_data/does_not_exist.fa"));
+/// ```
+pub fn is_stream_input(filename: &str) -> bool {
+    filename == STDIN_NAME || std::fs::metadata(filename).is_ok_and(|metadata| !metadata.is_file() && !metadata.is_dir())
+}
+
+/// Returns true if `filename` is a remote URL (e.g. `https://...` or `s3://...`) that htslib should open directly.
+/// Only BAM/SAM/CRAM inputs can be read this way; everything except plain `http`/`ftp` needs htslib built with libcurl (see the README), and `s3`/`gs` also need credentials in the usual htslib environment variables.
+/// # Arguments
+/// * `filename` - the input name to check
+/// # Examples
+/// ```
+/// use fastleng::format::is_remote_url;
+/// assert!(is_remote_url("https://example.com/reads.bam"));
+/// assert!(is_remote_url("s3://bucket/reads.bam"));
+/// assert!(!is_remote_url("./test_data/long_strings.bam"));
+/// ```
+pub fn is_remote_url(filename: &str) -> bool {
+    REMOTE_URL_SCHEMES.iter().any(|scheme| filename.starts_with(scheme))
+}
+
+/// This will determine the format of a file using the given detection strategy.
+/// Stream inputs (see `is_stream_input(...)`) cannot be sniffed without consuming them, so they are an error unless the extension decides the format.
+/// Remote URLs (see `is_remote_url(...)`) are never downloaded just to sniff them, so their format always comes from the extension and must be an alignment format.
+/// # Arguments
+/// * `filename` - the file to inspect
+/// * `detection` - how the format should be determined
+/// # Examples
+/// ```
+/// use fastleng::format::{resolve_format, FormatDetection, InputFormat};
+/// assert_eq!(resolve_format("./test_data/five_strings.fq", FormatDetection::Auto).unwrap(), InputFormat::Fastq);
+/// assert_eq!(resolve_format("./test_data/five_strings.sam", FormatDetection::Extension).unwrap(), InputFormat::Sam);
+/// assert!(resolve_format("./test_data/unknown.txt", FormatDetection::Extension).is_err());
+/// ```
+pub fn resolve_format(filename: &str, detection: FormatDetection) -> Result<InputFormat, Box<dyn std::error::Error>> {
+    if is_remote_url(filename) {
+        return match format_from_extension(filename, true) {
+            Some(input_format) if input_format.is_alignment() => Ok(input_format),
+            _ => Err(format!("Only BAM/SAM/CRAM inputs can be read from a URL: {:?}", filename).into())
+        };
+    }
+    match detection {
+        FormatDetection::Auto => match format_from_extension(filename, false) {
+            Some(input_format) => Ok(input_format),
+            None => detect_file_format(filename)
+        },
+        FormatDetection::Content => detect_file_format(filename),
+        FormatDetection::Extension => format_from_extension(filename, true)
+            .ok_or_else(|| format!("Unrecognized file extension: {:?}", filename).into())
+    }
+}
+
+/// Detects the format of a regular file, refusing stream inputs that would be consumed by the detection
+fn detect_file_format(filename: &str) -> Result<InputFormat, Box<dyn std::error::Error>> {
+    if is_stream_input(filename) {
+        return Err(format!("Cannot detect the format of a pipe or stdin without consuming it: {:?}", filename).into());
+    }
+    detect_format(filename)
+}
+
+/// This will detect the format of a file by peeking at its first bytes, looking through gzip/BGZF compression if present.
+/// SAM and FASTQ both start with `@`, so a line counts as SAM when it is a header line (an `@` + two letter tag followed by a tab)
+/// or an alignment line with at least the 11 mandatory tab-delimited fields.
+/// # Arguments
+/// * `filename` - the file to inspect
+/// # Examples
+/// ```
+/// use fastleng::format::{detect_format, InputFormat};
+/// assert_eq!(detect_format("./test_data/five_strings.fa").unwrap(), InputFormat::Fasta);
+/// assert_eq!(detect_format("./test_data/five_strings.sam").unwrap(), InputFormat::Sam);
+/// assert_eq!(detect_format("./test_data/long_strings.bam").unwrap(), InputFormat::Bam);
+/// ```
+pub fn detect_format(filename: &str) -> Result<InputFormat, Box<dyn std::error::Error>> {
+    Ok(sniff_reader(File::open(filename)?)?.input_format)
+}
+
+/// An input that has been opened for a single pass, with its format already detected from the first bytes
+pub struct SniffedStream {
+    /// The detected format of the contents
+    pub input_format: InputFormat,
+    /// The decompressed contents, starting from the very first byte after any UTF-8 byte order mark
+    pub reader: Box<dyn Read + Send>,
+    /// True if the contents started with a UTF-8 byte order mark, which is not included in `reader`
+    pub byte_order_mark: bool
+}
+
+/// This will open a file or standard input (`-`) for a single pass and detect its format, without losing any of the contents.
+/// The inspected bytes are pushed back in front of the rest of the stream, so this works on pipes and process substitution that cannot seek.
+/// Any gzip/BGZF compression and a leading UTF-8 byte order mark are removed, so the returned reader always yields the plain contents.
+/// # Arguments
+/// * `filename` - the file to open, or `-` for standard input
+/// # Examples
+/// ```
+/// use std::io::Read;
+/// use fastleng::format::{open_sniffed_stream, InputFormat};
+/// let mut sniffed = open_sniffed_stream("./test_data/long_strings.fa.gz").unwrap();
+/// assert_eq!(sniffed.input_format, InputFormat::Fasta);
+/// let mut contents: String = String::new();
+/// sniffed.reader.read_to_string(&mut contents).unwrap();
+/// assert!(contents.starts_with('>'));
+/// ```
+pub fn open_sniffed_stream(filename: &str) -> Result<SniffedStream, Box<dyn std::error::Error>> {
+    if filename == STDIN_NAME {
+        sniff_reader(std::io::stdin())
+    } else {
+        sniff_reader(File::open(filename)?)
+    }
+}
+
+/// Detects the format of the data in `reader`, keeping a reader over the full (decompressed) contents
+pub(crate) fn sniff_reader<R: Read + Send + 'static>(reader: R) -> Result<SniffedStream, Box<dyn std::error::Error>> {
+    //check for the gzip magic bytes first, so we know whether we need to decompress; BGZF is a multi-member gzip
+    let (magic, full_reader) = peek_bytes(reader, 2)?;
+    let plain_reader: Box<dyn Read + Send> = if magic == [0x1f, 0x8b] {
+        Box::new(MultiGzDecoder::new(full_reader))
+    } else {
+        Box::new(full_reader)
+    };
+
+    //a byte order mark would hide the first record from the parsers, so drop it here
+    let (bom, full_reader) = peek_bytes(plain_reader, UTF8_BOM.len() as u64)?;
+    let byte_order_mark: bool = bom == UTF8_BOM;
+    let plain_reader: Box<dyn Read + Send> = if byte_order_mark {
+        Box::new(full_reader.into_inner().1)
+    } else {
+        Box::new(full_reader)
+    };
+
+    //we only need the start of the (decompressed) data
+    let (head, plain_reader) = peek_bytes(plain_reader, SNIFF_LENGTH)?;
+    Ok(SniffedStream {
+        input_format: detect_format_bytes(&head),
+        reader: Box::new(plain_reader),
+        byte_order_mark
+    })
+}
+
+/// A reader that yields the peeked bytes before continuing with the rest of the original reader
+type PeekedReader<R> = std::io::Chain<Cursor<Vec<u8>>, R>;
+
+/// Reads up to `length` bytes from `reader` (fewer only at the end of the data), returning them along with a reader that yields them again followed by the rest.
+/// Unlike `BufRead::fill_buf`, this keeps reading until it has enough, since a pipe may only have a few bytes available at a time.
+fn peek_bytes<R: Read>(mut reader: R, length: u64) -> std::io::Result<(Vec<u8>, PeekedReader<R>)> {
+    let mut head: Vec<u8> = vec![];
+    (&mut reader).take(length).read_to_end(&mut head)?;
+    Ok((head.clone(), Cursor::new(head).chain(reader)))
+}
+
+/// This will detect the format from the first (already decompressed) bytes of a file, ignoring a leading UTF-8 byte order mark.
+/// # Arguments
+/// * `head` - the first bytes of the file contents
+/// # Examples
+/// ```
+/// use fastleng::format::{detect_format_bytes, InputFormat};
+/// assert_eq!(detect_format_bytes(b">seq1\nACGT\n"), InputFormat::Fasta);
+/// assert_eq!(detect_format_bytes(b"@seq1\nACGT\n+\nIIII\n"), InputFormat::Fastq);
+/// assert_eq!(detect_format_bytes(b"@HD\tVN:1.6\n"), InputFormat::Sam);
+/// ```
+pub fn detect_format_bytes(head: &[u8]) -> InputFormat {
+    let head: &[u8] = head.strip_prefix(&UTF8_BOM).unwrap_or(head);
+    if head.starts_with(b"BAM\x01") {
+        return InputFormat::Bam;
+    }
+    if head.starts_with(b"CRAM") {
+        return InputFormat::Cram;
+    }
+
+    let first_line: &[u8] = head.split(|&c| c == b'\n').next().unwrap_or(b"");
+    let first_line: &[u8] = first_line.strip_suffix(b"\r").unwrap_or(first_line);
+    if is_sam_line(first_line) {
+        InputFormat::Sam
+    } else {
+        match first_line.first() {
+            Some(b'>') => InputFormat::Fasta,
+            Some(b'@') => InputFormat::Fastq,
+            _ => InputFormat::Unknown
+        }
+    }
+}
+
+/// Returns true if a line looks like a SAM header line or alignment line
+fn is_sam_line(line: &[u8]) -> bool {
+    let is_header: bool = line.len() >= 4 && line[0] == b'@' &&
+        line[1].is_ascii_alphabetic() && line[2].is_ascii_alphabetic() && line[3] == b'\t';
+    //the 11 mandatory fields of an alignment line; a FASTQ header with this many tabs is not realistic
+    let is_alignment: bool = line.iter().filter(|&&c| c == b'\t').count() >= 10;
+    is_header || is_alignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_files() {
+        assert_eq!(detect_format("./test_data/five_strings.fa").unwrap(), InputFormat::Fasta);
+        assert_eq!(detect_format("./test_data/long_strings.fa.gz").unwrap(), InputFormat::Fasta);
+        assert_eq!(detect_format("./test_data/five_strings.fq").unwrap(), InputFormat::Fastq);
+        assert_eq!(detect_format("./test_data/five_strings.sam").unwrap(), InputFormat::Sam);
+        assert_eq!(detect_format("./test_data/long_strings.bam").unwrap(), InputFormat::Bam);
+        assert_eq!(detect_format("./test_data/five_strings.cram").unwrap(), InputFormat::Cram);
+        assert_eq!(detect_format("./test_data/panic_file.fa").unwrap(), InputFormat::Unknown);
+        assert!(detect_format("./test_data/does_not_exist.fa").is_err());
+    }
+
+    #[test]
+    fn test_detect_bytes() {
+        //headerless SAM is identified by its tab structure
+        let headerless_sam: &[u8] = b"read1\t4\t*\t0\t255\t*\t*\t0\t0\tACGT\t~~~~\n";
+        assert_eq!(detect_format_bytes(headerless_sam), InputFormat::Sam);
+
+        //these are all FASTQ despite starting with @ + letters
+        assert_eq!(detect_format_bytes(b"@HDread\nACGT\n+\nIIII\n"), InputFormat::Fastq);
+        assert_eq!(detect_format_bytes(b"@SQ read with spaces\nACGT\n+\nIIII\n"), InputFormat::Fastq);
+        assert_eq!(detect_format_bytes(b"@seq1\r\nACGT\r\n+\r\nIIII\r\n"), InputFormat::Fastq);
+
+        assert_eq!(detect_format_bytes(b">seq1\r\nACGT\r\n"), InputFormat::Fasta);
+        assert_eq!(detect_format_bytes(b"\xEF\xBB\xBF>seq1\nACGT\n"), InputFormat::Fasta);
+        assert_eq!(detect_format_bytes(b"BAM\x01\x00\x00"), InputFormat::Bam);
+        assert_eq!(detect_format_bytes(b""), InputFormat::Unknown);
+        assert_eq!(detect_format_bytes(b"not a sequence file\n"), InputFormat::Unknown);
+        assert_eq!(InputFormat::Cram.name(), "cram");
+        assert_eq!(describe_formats(&[]), "unknown");
+        assert!(InputFormat::Bam.is_alignment());
+        assert!(InputFormat::Cram.is_alignment());
+        assert!(!InputFormat::Fastq.is_alignment());
+    }
+
+    #[test]
+    fn test_format_from_extension() {
+        assert_eq!(format_from_extension("a.fa", false), Some(InputFormat::Fasta));
+        assert_eq!(format_from_extension("a.FASTA.GZ", false), Some(InputFormat::Fasta));
+        assert_eq!(format_from_extension("dir.fq/a.fastq", false), Some(InputFormat::Fastq));
+        assert_eq!(format_from_extension("a.sam", false), None);
+        assert_eq!(format_from_extension("a.sam.gz", true), Some(InputFormat::Sam));
+        assert_eq!(format_from_extension("a.cram", true), Some(InputFormat::Cram));
+        assert_eq!(format_from_extension("a.bam.gz", true), None);
+        assert_eq!(format_from_extension("a.txt", true), None);
+        assert_eq!(format_from_extension("fastq", true), None);
+    }
+
+    #[test]
+    fn test_peek_bytes() {
+        //a reader that hands out one byte at a time, like a slow pipe
+        struct TrickleReader(Vec<u8>);
+        impl Read for TrickleReader {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                if self.0.is_empty() || buf.is_empty() {
+                    return Ok(0);
+                }
+                buf[0] = self.0.remove(0);
+                Ok(1)
+            }
+        }
+        let (head, mut full_reader) = peek_bytes(TrickleReader(b">seq1\nACGT\n".to_vec()), 5).unwrap();
+        assert_eq!(head, b">seq1");
+        let mut contents: Vec<u8> = vec![];
+        full_reader.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, b">seq1\nACGT\n");
+
+        //asking for more than there is just returns everything
+        let (head, _full_reader) = peek_bytes(&b"AC"[..], 5).unwrap();
+        assert_eq!(head, b"AC");
+    }
+
+    #[test]
+    fn test_resolve_format() {
+        //auto trusts the extension instead of the contents
+        let misnamed_sam = std::env::temp_dir().join("fastleng_test_resolve_sam.fq");
+        std::fs::copy("./test_data/five_strings.sam", &misnamed_sam).unwrap();
+        let misnamed_str: &str = misnamed_sam.to_str().unwrap();
+        assert_eq!(resolve_format(misnamed_str, FormatDetection::Auto).unwrap(), InputFormat::Fastq);
+        assert_eq!(resolve_format(misnamed_str, FormatDetection::Content).unwrap(), InputFormat::Sam);
+        std::fs::remove_file(&misnamed_sam).unwrap();
+
+        //alignment extensions are still sniffed in auto mode
+        assert_eq!(resolve_format("./test_data/long_strings.bam", FormatDetection::Auto).unwrap(), InputFormat::Bam);
+
+        //strict mode never opens the file
+        assert_eq!(resolve_format("./test_data/does_not_exist.fa", FormatDetection::Extension).unwrap(), InputFormat::Fasta);
+        assert!(resolve_format("./test_data/does_not_exist.fa", FormatDetection::Content).is_err());
+
+        //URLs always use the extension, even when content detection was requested
+        assert_eq!(resolve_format("https://example.com/data/reads.bam", FormatDetection::Content).unwrap(), InputFormat::Bam);
+        assert_eq!(resolve_format("s3://bucket/reads.cram", FormatDetection::Auto).unwrap(), InputFormat::Cram);
+        assert!(resolve_format("https://example.com/data/reads.fq.gz", FormatDetection::Auto).is_err());
+        assert!(resolve_format("ftp://example.com/data/reads", FormatDetection::Auto).is_err());
+    }
+
+    #[test]
+    fn test_is_remote_url() {
+        for url in ["http://host/a.bam", "https://host/a.bam", "ftp://host/a.bam", "s3://bucket/a.bam", "gs://bucket/a.bam"] {
+            assert!(is_remote_url(url), "{url}");
+        }
+        for filename in ["-", "a.bam", "/data/https/a.bam", "file.s3://a.bam"] {
+            assert!(!is_remote_url(filename), "{filename}");
+        }
+    }
+
+    #[test]
+    fn test_format_names() {
+        for input_format in [InputFormat::Fasta, InputFormat::Fastq, InputFormat::Sam, InputFormat::Bam, InputFormat::Cram] {
+            assert_eq!(InputFormat::from_name(input_format.name()), Some(input_format));
+        }
+        assert_eq!(InputFormat::from_name("Fasta"), Some(InputFormat::Fasta));
+        assert_eq!(InputFormat::from_name(InputFormat::Unknown.name()), None);
+        assert_eq!(InputFormat::from_name("fa"), None);
+
+        //the contents win over a misleading extension
+        assert_eq!(detect_format("./test_data/fastq_contents.fa").unwrap(), InputFormat::Fastq);
+    }
+}
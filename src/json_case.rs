@@ -0,0 +1,378 @@
+
+use serde::ser::{
+    self, Serialize, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple, SerializeTupleStruct,
+    SerializeTupleVariant, Serializer
+};
+
+/// The naming convention used for the field names of JSON outputs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JsonCase {
+    /// The field names as they are in the Rust structs, e.g. `total_bases`
+    #[default]
+    Snake,
+    /// The field names converted to camelCase, e.g. `totalBases`
+    Camel
+}
+
+/// Wraps a value so that it serializes with its field names in the given case.
+/// Only field names are renamed; the keys of maps (e.g. group names or lengths) are always left as-is.
+/// Entries are written as they are serialized rather than collected into a JSON object, so a `BTreeMap` of lengths stays in ascending numeric order.
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::json_case::{CasedJson, JsonCase};
+/// use fastleng::length_stats::compute_basic_stats;
+/// let length_counts: BTreeMap<usize, u64> = [(5, 10), (10, 3)].iter().cloned().collect();
+/// let basic_stats = compute_basic_stats(&length_counts);
+/// let json_text: String = serde_json::to_string(&CasedJson::new(&basic_stats, JsonCase::Camel)).unwrap();
+/// assert!(json_text.starts_with("{\"totalBases\":80,"));
+/// ```
+pub struct CasedJson<'a, T: ?Sized> {
+    /// The value to serialize
+    value: &'a T,
+    /// The case to use for the field names
+    json_case: JsonCase
+}
+
+impl<'a, T: ?Sized> CasedJson<'a, T> {
+    /// Wraps `value` for serialization with `json_case` field names
+    pub fn new(value: &'a T, json_case: JsonCase) -> CasedJson<'a, T> {
+        CasedJson { value, json_case }
+    }
+}
+
+impl<T: Serialize + ?Sized> Serialize for CasedJson<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.json_case {
+            JsonCase::Snake => self.value.serialize(serializer),
+            JsonCase::Camel => self.value.serialize(CamelCaseSerializer(serializer))
+        }
+    }
+}
+
+/// This will convert a snake_case name to camelCase, e.g. `mean_length` to `meanLength`.
+/// # Arguments
+/// * `name` - the snake_case name to convert
+/// # Examples
+/// ```
+/// use fastleng::json_case::to_camel_case;
+/// assert_eq!(to_camel_case("total_bases"), "totalBases");
+/// assert_eq!(to_camel_case("n50"), "n50");
+/// ```
+pub fn to_camel_case(name: &str) -> String {
+    let mut camel_name: String = String::with_capacity(name.len());
+    let mut upper_next: bool = false;
+    for c in name.chars() {
+        if c == '_' {
+            //leading underscores have nothing before them to join to
+            upper_next = !camel_name.is_empty();
+        } else if upper_next {
+            camel_name.push(c.to_ascii_uppercase());
+            upper_next = false;
+        } else {
+            camel_name.push(c);
+        }
+    }
+    camel_name
+}
+
+/// A value that is serialized through `CamelCaseSerializer`, used to carry the renaming into nested values
+struct CamelCaseValue<'a, T: ?Sized>(&'a T);
+
+impl<T: Serialize + ?Sized> Serialize for CamelCaseValue<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(CamelCaseSerializer(serializer))
+    }
+}
+
+/// A serializer that forwards everything to the wrapped serializer, renaming struct fields to camelCase along the way.
+/// Structs are written as maps so that the renamed fields do not need a `'static` lifetime.
+struct CamelCaseSerializer<S>(S);
+
+/// Forwards the elements of sequences, tuples, and variants, carrying the renaming into each element
+struct CamelCaseCompound<C>(C);
+
+/// Forwards the entries of maps and structs
+struct CamelCaseMap<M> {
+    /// The wrapped map serializer
+    map: M,
+    /// True if the keys are field names that should be renamed
+    rename_keys: bool
+}
+
+impl<S: Serializer> Serializer for CamelCaseSerializer<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = CamelCaseCompound<S::SerializeSeq>;
+    type SerializeTuple = CamelCaseCompound<S::SerializeTuple>;
+    type SerializeTupleStruct = CamelCaseCompound<S::SerializeTupleStruct>;
+    type SerializeTupleVariant = CamelCaseCompound<S::SerializeTupleVariant>;
+    type SerializeMap = CamelCaseMap<S::SerializeMap>;
+    type SerializeStruct = CamelCaseMap<S::SerializeMap>;
+    type SerializeStructVariant = CamelCaseCompound<S::SerializeStructVariant>;
+
+    fn serialize_bool(self, v: bool) -> Result<S::Ok, S::Error> { self.0.serialize_bool(v) }
+    fn serialize_i8(self, v: i8) -> Result<S::Ok, S::Error> { self.0.serialize_i8(v) }
+    fn serialize_i16(self, v: i16) -> Result<S::Ok, S::Error> { self.0.serialize_i16(v) }
+    fn serialize_i32(self, v: i32) -> Result<S::Ok, S::Error> { self.0.serialize_i32(v) }
+    fn serialize_i64(self, v: i64) -> Result<S::Ok, S::Error> { self.0.serialize_i64(v) }
+    fn serialize_u8(self, v: u8) -> Result<S::Ok, S::Error> { self.0.serialize_u8(v) }
+    fn serialize_u16(self, v: u16) -> Result<S::Ok, S::Error> { self.0.serialize_u16(v) }
+    fn serialize_u32(self, v: u32) -> Result<S::Ok, S::Error> { self.0.serialize_u32(v) }
+    fn serialize_u64(self, v: u64) -> Result<S::Ok, S::Error> { self.0.serialize_u64(v) }
+    fn serialize_f32(self, v: f32) -> Result<S::Ok, S::Error> { self.0.serialize_f32(v) }
+    fn serialize_f64(self, v: f64) -> Result<S::Ok, S::Error> { self.0.serialize_f64(v) }
+    fn serialize_char(self, v: char) -> Result<S::Ok, S::Error> { self.0.serialize_char(v) }
+    fn serialize_str(self, v: &str) -> Result<S::Ok, S::Error> { self.0.serialize_str(v) }
+    fn serialize_bytes(self, v: &[u8]) -> Result<S::Ok, S::Error> { self.0.serialize_bytes(v) }
+    fn serialize_none(self) -> Result<S::Ok, S::Error> { self.0.serialize_none() }
+    fn serialize_unit(self) -> Result<S::Ok, S::Error> { self.0.serialize_unit() }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<S::Ok, S::Error> {
+        self.0.serialize_some(&CamelCaseValue(value))
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<S::Ok, S::Error> {
+        self.0.serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(self, name: &'static str, variant_index: u32, variant: &'static str) -> Result<S::Ok, S::Error> {
+        self.0.serialize_unit_variant(name, variant_index, variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, name: &'static str, value: &T) -> Result<S::Ok, S::Error> {
+        self.0.serialize_newtype_struct(name, &CamelCaseValue(value))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, name: &'static str, variant_index: u32, variant: &'static str, value: &T) -> Result<S::Ok, S::Error> {
+        self.0.serialize_newtype_variant(name, variant_index, variant, &CamelCaseValue(value))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+        Ok(CamelCaseCompound(self.0.serialize_seq(len)?))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, S::Error> {
+        Ok(CamelCaseCompound(self.0.serialize_tuple(len)?))
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, S::Error> {
+        Ok(CamelCaseCompound(self.0.serialize_tuple_struct(name, len)?))
+    }
+
+    fn serialize_tuple_variant(self, name: &'static str, variant_index: u32, variant: &'static str, len: usize) -> Result<Self::SerializeTupleVariant, S::Error> {
+        Ok(CamelCaseCompound(self.0.serialize_tuple_variant(name, variant_index, variant, len)?))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+        //real maps always know their length, while derived structs with flattened fields are written as a map of unknown length
+        Ok(CamelCaseMap {
+            map: self.0.serialize_map(len)?,
+            rename_keys: len.is_none()
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct, S::Error> {
+        Ok(CamelCaseMap {
+            map: self.0.serialize_map(Some(len))?,
+            rename_keys: true
+        })
+    }
+
+    fn serialize_struct_variant(self, name: &'static str, variant_index: u32, variant: &'static str, len: usize) -> Result<Self::SerializeStructVariant, S::Error> {
+        //the field names here must be 'static, so these are left as they are
+        Ok(CamelCaseCompound(self.0.serialize_struct_variant(name, variant_index, variant, len)?))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.0.is_human_readable()
+    }
+}
+
+impl<C: SerializeSeq> SerializeSeq for CamelCaseCompound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.0.serialize_element(&CamelCaseValue(value))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.0.end()
+    }
+}
+
+impl<C: SerializeTuple> SerializeTuple for CamelCaseCompound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.0.serialize_element(&CamelCaseValue(value))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.0.end()
+    }
+}
+
+impl<C: SerializeTupleStruct> SerializeTupleStruct for CamelCaseCompound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.0.serialize_field(&CamelCaseValue(value))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.0.end()
+    }
+}
+
+impl<C: SerializeTupleVariant> SerializeTupleVariant for CamelCaseCompound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.0.serialize_field(&CamelCaseValue(value))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.0.end()
+    }
+}
+
+impl<C: SerializeStructVariant> SerializeStructVariant for CamelCaseCompound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), C::Error> {
+        self.0.serialize_field(key, &CamelCaseValue(value))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.0.end()
+    }
+}
+
+impl<M: SerializeMap> SerializeMap for CamelCaseMap<M> {
+    type Ok = M::Ok;
+    type Error = M::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), M::Error> {
+        if self.rename_keys {
+            //the field names of flattened structs arrive here as plain strings
+            match serde_json::to_value(key).map_err(ser::Error::custom)? {
+                serde_json::Value::String(name) => self.map.serialize_key(&to_camel_case(&name)),
+                _ => self.map.serialize_key(&CamelCaseValue(key))
+            }
+        } else {
+            self.map.serialize_key(&CamelCaseValue(key))
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), M::Error> {
+        self.map.serialize_value(&CamelCaseValue(value))
+    }
+
+    fn end(self) -> Result<M::Ok, M::Error> {
+        self.map.end()
+    }
+}
+
+impl<M: SerializeMap> SerializeStruct for CamelCaseMap<M> {
+    type Ok = M::Ok;
+    type Error = M::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), M::Error> {
+        self.map.serialize_entry(&to_camel_case(key), &CamelCaseValue(value))
+    }
+
+    fn end(self) -> Result<M::Ok, M::Error> {
+        self.map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    use crate::length_stats::{compute_length_stats, LengthStats};
+
+    #[derive(Serialize)]
+    struct FlattenedOutput<'a> {
+        #[serde(flatten)]
+        stats: &'a LengthStats,
+        group_stats: BTreeMap<String, &'a LengthStats>,
+        length_range: Option<(usize, usize)>,
+        failed_files: Vec<&'a str>
+    }
+
+    #[test]
+    fn test_to_camel_case() {
+        assert_eq!(to_camel_case("total_bases"), "totalBases");
+        assert_eq!(to_camel_case("geometric_mean_length"), "geometricMeanLength");
+        assert_eq!(to_camel_case("n50"), "n50");
+        assert_eq!(to_camel_case("_private__name_"), "privateName");
+        assert_eq!(to_camel_case(""), "");
+    }
+
+    #[test]
+    fn test_cased_json() {
+        let length_counts: BTreeMap<usize, u64> = [(1, 1), (2, 2)].iter().cloned().collect();
+        let stats = compute_length_stats(&length_counts);
+
+        //snake case is exactly the default serialization
+        assert_eq!(serde_json::to_string(&CasedJson::new(&stats, JsonCase::Snake)).unwrap(), serde_json::to_string(&stats).unwrap());
+
+        //camel case renames every field but keeps the values and order
+        let snake_value: serde_json::Value = serde_json::to_value(&stats).unwrap();
+        let camel_text: String = serde_json::to_string(&CasedJson::new(&stats, JsonCase::Camel)).unwrap();
+        let camel_value: serde_json::Value = serde_json::from_str(&camel_text).unwrap();
+        for (name, _value) in stats.iter_fields() {
+            assert_eq!(camel_value[to_camel_case(name)], snake_value[name]);
+        }
+        assert!(camel_text.starts_with("{\"totalBases\":5,\"totalGigabases\":"));
+
+        //flattened fields and nested structs are renamed, but map keys are not
+        let output = FlattenedOutput {
+            stats: &stats,
+            group_stats: [("sample_a".to_string(), &stats)].into_iter().collect(),
+            length_range: Some((1, 2)),
+            failed_files: vec!["reads_1.fq"]
+        };
+        let output_value: serde_json::Value = serde_json::from_str(&serde_json::to_string(&CasedJson::new(&output, JsonCase::Camel)).unwrap()).unwrap();
+        assert_eq!(output_value["meanLength"], snake_value["mean_length"]);
+        assert_eq!(output_value["groupStats"]["sample_a"]["totalSequences"], 3);
+        assert_eq!(output_value["lengthRange"], serde_json::json!([1, 2]));
+        assert_eq!(output_value["failedFiles"], serde_json::json!(["reads_1.fq"]));
+        assert!(output_value.get("mean_length").is_none());
+    }
+
+    #[test]
+    fn test_map_key_order() {
+        //numeric map keys come out in ascending numeric order (not "10" before "2") in every case and nesting, so outputs are diffable
+        let length_counts: BTreeMap<usize, u64> = [(100, 1), (2, 3), (10, 2), (9, 4)].iter().cloned().collect();
+        let detailed_counts = crate::length_stats::compute_detailed_histogram(&length_counts);
+        let log_counts = crate::length_stats::compute_log_histogram(&length_counts, 2);
+        let grouped_counts: BTreeMap<String, &BTreeMap<usize, u64>> = [("group".to_string(), &length_counts)].into_iter().collect();
+        let key_positions = |json_text: &str, keys: &[usize]| -> Vec<usize> {
+            keys.iter().map(|key| json_text.find(&format!("\"{key}\":")).unwrap()).collect()
+        };
+        for json_case in [JsonCase::Snake, JsonCase::Camel] {
+            for json_text in [
+                serde_json::to_string(&CasedJson::new(&length_counts, json_case)).unwrap(),
+                serde_json::to_string_pretty(&CasedJson::new(&length_counts, json_case)).unwrap().replace("\": ", "\":"),
+                serde_json::to_string(&CasedJson::new(&detailed_counts, json_case)).unwrap(),
+                serde_json::to_string(&CasedJson::new(&grouped_counts, json_case)).unwrap()
+            ] {
+                let positions: Vec<usize> = key_positions(&json_text, &[2, 9, 10, 100]);
+                assert!(positions.windows(2).all(|pair| pair[0] < pair[1]), "{json_text}");
+            }
+            let log_text: String = serde_json::to_string(&CasedJson::new(&log_counts, json_case)).unwrap();
+            assert_eq!(log_text, "{\"2\":3,\"8\":6,\"64\":1}");
+        }
+    }
+}
@@ -1,10 +1,14 @@
 
 extern crate serde;
 
+use log::warn;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
+use crate::tdigest::TDigest;
+
 /// This will compute the total number of bases and sequences by iterating over the length stats and return a tuple (`total_bases`, `total_seqs`).
+/// If either total would overflow a `u64` (only possible for corrupt or adversarial inputs), it saturates at `u64::MAX` and a warning is logged.
 /// # Arguments
 /// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
 /// # Examples
@@ -22,9 +26,26 @@ use std::collections::BTreeMap;
 pub fn compute_total_counts(length_counts: &BTreeMap<usize, u64>) -> (u64, u64) {
     let mut total_bases: u64 = 0;
     let mut total_seqs: u64 = 0;
+    let mut overflowed: bool = false;
     for (length, count) in length_counts.iter() {
-        total_bases += (*length as u64) * count;
-        total_seqs += count;
+        let length_bases: Option<u64> = (*length as u64).checked_mul(*count);
+        total_bases = match length_bases.and_then(|length_bases| total_bases.checked_add(length_bases)) {
+            Some(new_total) => new_total,
+            None => {
+                overflowed = true;
+                u64::MAX
+            }
+        };
+        total_seqs = match total_seqs.checked_add(*count) {
+            Some(new_total) => new_total,
+            None => {
+                overflowed = true;
+                u64::MAX
+            }
+        };
+    }
+    if overflowed {
+        warn!("Total counts overflowed and were saturated at {}, the statistics are not reliable", u64::MAX);
     }
     (total_bases, total_seqs)
 }
@@ -49,9 +70,9 @@ pub fn compute_total_counts(length_counts: &BTreeMap<usize, u64>) -> (u64, u64)
 pub fn compute_median_length(length_counts: &BTreeMap<usize, u64>, total_seqs: u64) -> f64 {
     //find the middle index
     let middle_seq_index: u64 = total_seqs / 2;
-    let mut total_observed = 0;
+    let mut total_observed: u64 = 0;
     for (seq_len, seq_count) in length_counts.iter() {
-        total_observed += seq_count;
+        total_observed = total_observed.saturating_add(*seq_count);
 
         //loop until we observe more than the target index
         if total_observed > middle_seq_index {
@@ -64,6 +85,295 @@ pub fn compute_median_length(length_counts: &BTreeMap<usize, u64>, total_seqs: u
     0.0
 }
 
+/// This will compute a percentile of the sequence lengths, e.g. 50.0 for the median.
+/// By default (`interpolate = false`), this is a step function that returns the length of the sequence at rank `floor(percentile / 100 * total_seqs)`,
+/// which matches `compute_median_length(...)` for the 50th percentile and is always an observed length.
+/// With `interpolate = true`, the fractional rank `percentile / 100 * (total_seqs - 1)` is linearly interpolated between the two bracketing lengths,
+/// which matches the default ("linear") behavior of `numpy.percentile` and gives a continuous value (e.g. the mean of the middle two lengths for the median of an even number).
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `total_seqs` - the total number of sequences represented by `length_counts`, this can be computed by `compute_total_counts(...)`
+/// * `percentile` - the percentile to compute, must be in the range 0.0-100.0
+/// * `interpolate` - if true, interpolate between the bracketing lengths instead of stepping
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::compute_length_percentile;
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (1, 1),
+///     (2, 1),
+///     (3, 1),
+///     (4, 1)
+/// ].iter().cloned().collect();
+/// assert_eq!(compute_length_percentile(&length_counts, 4, 50.0, false), 3.0);
+/// assert_eq!(compute_length_percentile(&length_counts, 4, 50.0, true), 2.5);
+/// assert_eq!(compute_length_percentile(&length_counts, 4, 25.0, true), 1.75);
+/// ```
+pub fn compute_length_percentile(length_counts: &BTreeMap<usize, u64>, total_seqs: u64, percentile: f64, interpolate: bool) -> f64 {
+    //make sure this is in our allowed range
+    assert!((0.0..=100.0).contains(&percentile));
+    if total_seqs == 0 {
+        return 0.0;
+    }
+
+    if interpolate {
+        let rank: f64 = percentile / 100.0 * (total_seqs - 1) as f64;
+        let lower_index: u64 = rank.floor() as u64;
+        let upper_index: u64 = rank.ceil() as u64;
+        let lower_length: f64 = length_at_index(length_counts, lower_index) as f64;
+        let upper_length: f64 = length_at_index(length_counts, upper_index) as f64;
+        lower_length + (upper_length - lower_length) * (rank - lower_index as f64)
+    } else {
+        let index: u64 = ((percentile / 100.0 * total_seqs as f64).floor() as u64).min(total_seqs - 1);
+        length_at_index(length_counts, index) as f64
+    }
+}
+
+/// Returns the length of the sequence at 0-based `index` when all sequences are sorted by length
+fn length_at_index(length_counts: &BTreeMap<usize, u64>, index: u64) -> usize {
+    let mut total_observed: u64 = 0;
+    for (seq_len, seq_count) in length_counts.iter() {
+        total_observed = total_observed.saturating_add(*seq_count);
+        if total_observed > index {
+            return *seq_len;
+        }
+    }
+    panic!("index {} is out of range for the length counts", index);
+}
+
+/// This will round a value to a fixed number of decimal places, e.g. for cleaner human-facing reports.
+/// If the scaling would overflow (i.e. a huge `decimals`), the value is returned unchanged.
+/// # Arguments
+/// * `value` - the value to round
+/// * `decimals` - the number of decimal places to keep
+/// # Examples
+/// ```
+/// use fastleng::length_stats::round_to_decimals;
+/// assert_eq!(round_to_decimals(3.3333333333333335, 2), 3.33);
+/// assert_eq!(round_to_decimals(2.5, 0), 3.0);
+/// ```
+pub fn round_to_decimals(value: f64, decimals: u32) -> f64 {
+    let scale: f64 = 10.0_f64.powi(decimals as i32);
+    let rounded: f64 = (value * scale).round() / scale;
+    if rounded.is_finite() { rounded } else { value }
+}
+
+/// This will compute the (sample) standard deviation of the sequence lengths, using the `n - 1` denominator.
+/// Returns 0.0 if there are fewer than two sequences.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `mean_length` - the mean length of the sequences represented by `length_counts`
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::compute_length_stddev;
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (2, 1),
+///     (4, 1)
+/// ].iter().cloned().collect();
+/// let stddev: f64 = compute_length_stddev(&length_counts, 3.0);
+/// assert_eq!(stddev, 2.0_f64.sqrt());
+/// ```
+pub fn compute_length_stddev(length_counts: &BTreeMap<usize, u64>, mean_length: f64) -> f64 {
+    let mut squared_deviations: f64 = 0.0;
+    let mut total_seqs: u64 = 0;
+    for (seq_len, seq_count) in length_counts.iter() {
+        let deviation: f64 = *seq_len as f64 - mean_length;
+        squared_deviations += deviation * deviation * (*seq_count as f64);
+        total_seqs = total_seqs.saturating_add(*seq_count);
+    }
+    if total_seqs < 2 {
+        0.0
+    } else {
+        (squared_deviations / (total_seqs - 1) as f64).sqrt()
+    }
+}
+
+/// This will compute the mean absolute deviation of the sequence lengths around the median, a dispersion measure that is less sensitive to outliers than the standard deviation.
+/// Returns 0.0 if there are no sequences.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `median_length` - the median length of the sequences represented by `length_counts`
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::compute_mad;
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (2, 1),
+///     (4, 1),
+///     (9, 1)
+/// ].iter().cloned().collect();
+/// let mad: f64 = compute_mad(&length_counts, 4.0);
+/// assert_eq!(mad, 7.0 / 3.0);
+/// ```
+pub fn compute_mad(length_counts: &BTreeMap<usize, u64>, median_length: f64) -> f64 {
+    let mut absolute_deviations: f64 = 0.0;
+    let mut total_seqs: u64 = 0;
+    for (seq_len, seq_count) in length_counts.iter() {
+        absolute_deviations += (*seq_len as f64 - median_length).abs() * (*seq_count as f64);
+        total_seqs = total_seqs.saturating_add(*seq_count);
+    }
+    if total_seqs == 0 {
+        0.0
+    } else {
+        absolute_deviations / total_seqs as f64
+    }
+}
+
+/// This will compute the geometric mean length of the sequences, which is less sensitive than the arithmetic mean to a long tail of very long sequences.
+/// The logarithms of the lengths are summed to avoid overflow; zero-length sequences are skipped since their logarithm is undefined.
+/// Returns 0.0 if there are no sequences with a non-zero length.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::compute_geometric_mean_length;
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (1, 1),
+///     (100, 1)
+/// ].iter().cloned().collect();
+/// let geometric_mean: f64 = compute_geometric_mean_length(&length_counts);
+/// assert!((geometric_mean - 10.0).abs() < 1e-9);
+/// ```
+pub fn compute_geometric_mean_length(length_counts: &BTreeMap<usize, u64>) -> f64 {
+    let mut log_sum: f64 = 0.0;
+    let mut total_seqs: u64 = 0;
+    for (seq_len, seq_count) in length_counts.iter().filter(|(seq_len, _seq_count)| **seq_len > 0) {
+        log_sum += (*seq_len as f64).ln() * (*seq_count as f64);
+        total_seqs = total_seqs.saturating_add(*seq_count);
+    }
+    if total_seqs == 0 {
+        0.0
+    } else {
+        (log_sum / total_seqs as f64).exp()
+    }
+}
+
+/// This will compute the harmonic mean length of the sequences, which is dominated by the shorter sequences.
+/// The reciprocals of the lengths are summed; zero-length sequences are skipped to avoid dividing by zero.
+/// Returns 0.0 if there are no sequences with a non-zero length.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::compute_harmonic_mean_length;
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (1, 1),
+///     (3, 1)
+/// ].iter().cloned().collect();
+/// let harmonic_mean: f64 = compute_harmonic_mean_length(&length_counts);
+/// assert_eq!(harmonic_mean, 1.5);
+/// ```
+pub fn compute_harmonic_mean_length(length_counts: &BTreeMap<usize, u64>) -> f64 {
+    let mut reciprocal_sum: f64 = 0.0;
+    let mut total_seqs: u64 = 0;
+    for (seq_len, seq_count) in length_counts.iter().filter(|(seq_len, _seq_count)| **seq_len > 0) {
+        reciprocal_sum += (*seq_count as f64) / (*seq_len as f64);
+        total_seqs = total_seqs.saturating_add(*seq_count);
+    }
+    if total_seqs == 0 {
+        0.0
+    } else {
+        total_seqs as f64 / reciprocal_sum
+    }
+}
+
+/// This will compute the fraction of sequences that are strictly shorter than `threshold`.
+/// With the mean length as the threshold, this is a quick skewness indicator: near 0.5 for symmetric distributions, and further away for skewed ones.
+/// Returns 0.0 if there are no sequences.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `total_seqs` - the total number of sequences represented by `length_counts`, this can be computed by `compute_total_counts(...)`
+/// * `threshold` - the length to compare against
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::{compute_fraction_below,compute_total_counts};
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3)
+/// ].iter().cloned().collect();
+/// let (_total_bases, total_seqs) = compute_total_counts(&length_counts);
+/// let fraction: f64 = compute_fraction_below(&length_counts, total_seqs, 6.15);
+/// assert_eq!(fraction, 10.0 / 13.0);
+/// ```
+pub fn compute_fraction_below(length_counts: &BTreeMap<usize, u64>, total_seqs: u64, threshold: f64) -> f64 {
+    if total_seqs == 0 {
+        return 0.0;
+    }
+
+    //the map is sorted, so we can stop as soon as we reach the threshold
+    let mut below_count: u64 = 0;
+    for (seq_len, seq_count) in length_counts.iter() {
+        if (*seq_len as f64) >= threshold {
+            break;
+        }
+        below_count = below_count.saturating_add(*seq_count);
+    }
+    below_count as f64 / total_seqs as f64
+}
+
+/// This will compute the Gini coefficient of the sequence lengths, a single-number measure of how unevenly the bases are spread across the sequences.
+/// This uses the standard formula over the sorted lengths, `G = 2 * sum(i * x_i) / (n * sum(x_i)) - (n + 1) / n` with 1-based ranks `i`.
+/// Identical lengths give 0.0, and the value approaches 1.0 as the bases concentrate in a few sequences; empty inputs (or all zero lengths) give 0.0.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::compute_gini;
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (1, 1),
+///     (2, 1),
+///     (3, 1),
+///     (4, 1)
+/// ].iter().cloned().collect();
+/// assert_eq!(compute_gini(&length_counts), 0.25);
+/// ```
+pub fn compute_gini(length_counts: &BTreeMap<usize, u64>) -> f64 {
+    let mut total_seqs: f64 = 0.0;
+    let mut total_bases: f64 = 0.0;
+    let mut weighted_sum: f64 = 0.0;
+    for (seq_len, seq_count) in length_counts.iter() {
+        //the ranks for this length are total_seqs+1 ..= total_seqs+seq_count
+        let count: f64 = *seq_count as f64;
+        let rank_sum: f64 = count * total_seqs + count * (count + 1.0) / 2.0;
+        weighted_sum += rank_sum * (*seq_len as f64);
+        total_seqs += count;
+        total_bases += count * (*seq_len as f64);
+    }
+    if total_bases == 0.0 {
+        0.0
+    } else {
+        2.0 * weighted_sum / (total_seqs * total_bases) - (total_seqs + 1.0) / total_seqs
+    }
+}
+
+/// This will compute the fraction of all bases that are in the single longest sequence, e.g. how much of an assembly is in the largest contig.
+/// When several sequences share the longest length, only one of them is counted; empty inputs give 0.0.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `total_bases` - the total number of bases represented by the `length_counts` parameter, this can be computed by `compute_total_counts(...)`
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::{compute_largest_fraction,compute_total_counts};
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3)
+/// ].iter().cloned().collect();
+/// let (total_bases, _total_seqs) = compute_total_counts(&length_counts);
+/// assert_eq!(compute_largest_fraction(&length_counts, total_bases), 0.125);
+/// ```
+pub fn compute_largest_fraction(length_counts: &BTreeMap<usize, u64>, total_bases: u64) -> f64 {
+    match length_counts.keys().next_back() {
+        Some(&max_length) if total_bases > 0 => max_length as f64 / total_bases as f64,
+        _ => 0.0
+    }
+}
+
 /// This will compute the N-score (e.g. N50) for the sequence lengths provided. 
 /// For details on this measure, see <https://www.molecularecologist.com/2017/03/29/whats-n50/>.
 /// # Arguments
@@ -87,10 +397,85 @@ pub fn compute_n_score(length_counts: &BTreeMap<usize, u64>, total_bases: u64, t
     assert!((1..=99).contains(&target));
 
     //calculate the target number of bases
-    let target_bases: f64 = (target as u64*total_bases) as f64 / 100.0;
+    let target_bases: f64 = (target as u128 * total_bases as u128) as f64 / 100.0;
+    let mut current_bases: u64 = 0;
+    for (seq_len, seq_count) in length_counts.iter().rev() {
+        current_bases = current_bases.saturating_add((*seq_len as u64).saturating_mul(*seq_count));
+        if current_bases as f64 >= target_bases {
+            return *seq_len;
+        }
+    }
+
+    //this only happens with empty files
+    assert!(total_bases == 0 && length_counts.is_empty());
+    0
+}
+
+/// This will compute the N-score (e.g. N50) of only the sequences that are at least `min_length` long, e.g. the "N50 of contigs >= 10kb".
+/// Both the base total and the walk are restricted to those sequences, so this is the same as filtering first but without building a filtered copy.
+/// If no sequences are long enough, this returns 0.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `target` - the score target; e.g. for N50, N75, and N90, this parameter should be 50, 75, and 90 respectively
+/// * `min_length` - the shortest sequence length to include
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::compute_n_score_above;
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3)
+/// ].iter().cloned().collect();
+/// assert_eq!(compute_n_score_above(&length_counts, 50, 0), 5);
+/// assert_eq!(compute_n_score_above(&length_counts, 50, 6), 10);
+/// assert_eq!(compute_n_score_above(&length_counts, 50, 11), 0);
+/// ```
+pub fn compute_n_score_above(length_counts: &BTreeMap<usize, u64>, target: usize, min_length: usize) -> usize {
+    //make sure this is in our allowed range
+    assert!((1..=99).contains(&target));
+
+    //BTreeMap ranges let us skip the short sequences entirely
+    let total_bases: u64 = length_counts.range(min_length..)
+        .map(|(seq_len, seq_count)| (*seq_len as u64).saturating_mul(*seq_count))
+        .fold(0, u64::saturating_add);
+    let target_bases: f64 = (target as u128 * total_bases as u128) as f64 / 100.0;
+    let mut current_bases: u64 = 0;
+    for (seq_len, seq_count) in length_counts.range(min_length..).rev() {
+        current_bases = current_bases.saturating_add((*seq_len as u64).saturating_mul(*seq_count));
+        if current_bases as f64 >= target_bases {
+            return *seq_len;
+        }
+    }
+    0
+}
+
+/// This will compute the shortest length such that the sequences at least that long contain `fraction` of all bases, counting from the longest sequence.
+/// This generalizes `compute_n_score(...)` to any fraction, e.g. 0.5 gives the N50 and 0.333 gives an "N33.3".
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `total_bases` - the total number of bases represented by the `length_counts` parameter, this can be computed by `compute_total_counts(...)`
+/// * `fraction` - the fraction of bases to capture, must be in the range (0.0, 1.0]
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::{compute_length_for_base_fraction,compute_total_counts};
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3)
+/// ].iter().cloned().collect();
+/// let (total_bases, _total_seqs) = compute_total_counts(&length_counts);
+/// assert_eq!(compute_length_for_base_fraction(&length_counts, total_bases, 0.375), 10);
+/// assert_eq!(compute_length_for_base_fraction(&length_counts, total_bases, 0.376), 5);
+/// ```
+pub fn compute_length_for_base_fraction(length_counts: &BTreeMap<usize, u64>, total_bases: u64, fraction: f64) -> usize {
+    //make sure this is in our allowed range
+    assert!(fraction > 0.0 && fraction <= 1.0);
+
+    //same reverse walk as the N-score, just with a fractional target
+    let target_bases: f64 = fraction * total_bases as f64;
     let mut current_bases: u64 = 0;
     for (seq_len, seq_count) in length_counts.iter().rev() {
-        current_bases += (*seq_len as u64) * *seq_count;
+        current_bases = current_bases.saturating_add((*seq_len as u64).saturating_mul(*seq_count));
         if current_bases as f64 >= target_bases {
             return *seq_len;
         }
@@ -101,15 +486,137 @@ pub fn compute_n_score(length_counts: &BTreeMap<usize, u64>, total_bases: u64, t
     0
 }
 
+/// This will count the fewest sequences, taken from longest to shortest, whose combined length reaches `fraction` of all bases, e.g. how many contigs make up 90% of an assembly.
+/// With a fraction of 0.5 the count is the L50; only as many sequences of the final length as needed are included.
+/// Returns the number of sequences and their combined length, or `(0, 0)` for empty input.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `total_bases` - the total number of bases represented by the `length_counts` parameter, this can be computed by `compute_total_counts(...)`
+/// * `fraction` - the fraction of bases to cover, must be in the range (0.0, 1.0]
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::{compute_total_counts,sequences_to_cover_fraction};
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3)
+/// ].iter().cloned().collect();
+/// let (total_bases, _total_seqs) = compute_total_counts(&length_counts);
+/// assert_eq!(sequences_to_cover_fraction(&length_counts, total_bases, 0.375), (3, 30));
+/// assert_eq!(sequences_to_cover_fraction(&length_counts, total_bases, 0.5), (5, 40));
+/// ```
+pub fn sequences_to_cover_fraction(length_counts: &BTreeMap<usize, u64>, total_bases: u64, fraction: f64) -> (u64, u64) {
+    //make sure this is in our allowed range
+    assert!(fraction > 0.0 && fraction <= 1.0);
+
+    //same reverse walk as the N-score, but only use as many of the last length as we need
+    let target_bases: f64 = fraction * total_bases as f64;
+    let mut current_seqs: u64 = 0;
+    let mut current_bases: u64 = 0;
+    for (&seq_len, &seq_count) in length_counts.iter().rev() {
+        if current_bases as f64 >= target_bases || seq_len == 0 {
+            break;
+        }
+        let needed_count: u64 = ((target_bases - current_bases as f64) / seq_len as f64).ceil() as u64;
+        let used_count: u64 = seq_count.min(needed_count.max(1));
+        current_seqs = current_seqs.saturating_add(used_count);
+        current_bases = current_bases.saturating_add((seq_len as u64).saturating_mul(used_count));
+    }
+    (current_seqs, current_bases)
+}
+
+/// This will compute multiple N-scores (e.g. N50 and N90) in a single pass over the sequence lengths.
+/// This is equivalent to calling `compute_n_score(...)` once per target, but only walks `length_counts` one time.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `total_bases` - the total number of bases represented by the `length_counts` parameter, this can be computed by `compute_total_counts(...)`
+/// * `targets` - the score targets, each must be in the range 1-99; duplicates are allowed and the order does not matter
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::{compute_all_n_scores,compute_total_counts};
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3)
+/// ].iter().cloned().collect();
+/// let (total_bases, _total_seqs) = compute_total_counts(&length_counts);
+/// let n_scores = compute_all_n_scores(&length_counts, total_bases, &[10, 50]);
+/// assert_eq!(n_scores[&10], 10);
+/// assert_eq!(n_scores[&50], 5);
+/// ```
+pub fn compute_all_n_scores(length_counts: &BTreeMap<usize, u64>, total_bases: u64, targets: &[usize]) -> BTreeMap<usize, usize> {
+    //make sure these are in our allowed range, then order them so we can check them as we walk
+    let mut sorted_targets: Vec<usize> = targets.to_vec();
+    sorted_targets.sort_unstable();
+    sorted_targets.dedup();
+    for target in sorted_targets.iter() {
+        assert!((1..=99).contains(target));
+    }
+
+    let mut n_scores: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut target_iter = sorted_targets.iter().peekable();
+    let mut current_bases: u64 = 0;
+    for (seq_len, seq_count) in length_counts.iter().rev() {
+        current_bases = current_bases.saturating_add((*seq_len as u64).saturating_mul(*seq_count));
+
+        //a single length may satisfy multiple targets at once
+        while let Some(&&target) = target_iter.peek() {
+            let target_bases: f64 = (target as u128 * total_bases as u128) as f64 / 100.0;
+            if current_bases as f64 >= target_bases {
+                n_scores.insert(target, *seq_len);
+                target_iter.next();
+            } else {
+                break;
+            }
+        }
+
+        if target_iter.peek().is_none() {
+            break;
+        }
+    }
+
+    //this only happens with empty files
+    for &target in target_iter {
+        assert!(total_bases == 0 && length_counts.is_empty());
+        n_scores.insert(target, 0);
+    }
+    n_scores
+}
+
+/// The names of the statistics in `LengthStats`, in the order they are serialized; these are also the names accepted by `compute_selected_stats(...)`
+pub const LENGTH_STAT_NAMES: [&str; 18] = [
+    "total_bases", "total_gigabases", "total_sequences", "mean_length", "geometric_mean_length", "harmonic_mean_length", "stddev_length",
+    "sem_length", "mad_length", "fraction_below_mean", "gini_coefficient", "longest_seq_base_fraction", "median_length",
+    "n10", "n25", "n50", "n75", "n90"
+];
+
 /// This struct encapsulates the various statistics we return
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct LengthStats {
     /// The total number of bases analyzed
     pub total_bases: u64,
+    /// The total number of bases analyzed in gigabases (i.e. `total_bases / 1e9`), for readability
+    pub total_gigabases: f64,
     /// The total number of sequences (i.e. strings) analyzed
     pub total_sequences: u64,
     /// The average length of the sequences
     pub mean_length: f64,
+    /// The geometric mean length of the sequences, excluding any zero-length sequences
+    pub geometric_mean_length: f64,
+    /// The harmonic mean length of the sequences, excluding any zero-length sequences
+    pub harmonic_mean_length: f64,
+    /// The sample standard deviation of the sequence lengths
+    pub stddev_length: f64,
+    /// The standard error of the mean length (i.e. `stddev_length / sqrt(total_sequences)`)
+    pub sem_length: f64,
+    /// The mean absolute deviation of the sequence lengths from the median length
+    pub mad_length: f64,
+    /// The fraction of sequences that are shorter than the mean length, a quick skewness indicator
+    pub fraction_below_mean: f64,
+    /// The Gini coefficient of the sequence lengths, from 0.0 when all lengths are identical towards 1.0 when a few sequences contain most of the bases
+    pub gini_coefficient: f64,
+    /// The fraction of all bases that are in the single longest sequence, e.g. how much of an assembly is in the largest contig
+    pub longest_seq_base_fraction: f64,
     /// The median length of the sequences
     pub median_length: f64,
     /// N10 - 10% of bases are in sequences of length greater than this value
@@ -124,6 +631,61 @@ pub struct LengthStats {
     pub n90: usize
 }
 
+impl LengthStats {
+    /// Rounds all of the floating point statistics in place to `decimals` places; the integer statistics are unchanged.
+    /// # Arguments
+    /// * `decimals` - the number of decimal places to keep
+    pub fn round_floats(&mut self, decimals: u32) {
+        self.total_gigabases = round_to_decimals(self.total_gigabases, decimals);
+        self.mean_length = round_to_decimals(self.mean_length, decimals);
+        self.geometric_mean_length = round_to_decimals(self.geometric_mean_length, decimals);
+        self.harmonic_mean_length = round_to_decimals(self.harmonic_mean_length, decimals);
+        self.stddev_length = round_to_decimals(self.stddev_length, decimals);
+        self.sem_length = round_to_decimals(self.sem_length, decimals);
+        self.mad_length = round_to_decimals(self.mad_length, decimals);
+        self.fraction_below_mean = round_to_decimals(self.fraction_below_mean, decimals);
+        self.gini_coefficient = round_to_decimals(self.gini_coefficient, decimals);
+        self.longest_seq_base_fraction = round_to_decimals(self.longest_seq_base_fraction, decimals);
+        self.median_length = round_to_decimals(self.median_length, decimals);
+    }
+
+    /// Returns an iterator over the `(name, value)` pairs of every statistic, in declaration order.
+    /// Integer statistics are converted to `f64` so that all values can be handled uniformly (e.g. for tabular reports).
+    /// # Examples
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use fastleng::length_stats::compute_length_stats;
+    /// let length_counts: BTreeMap<usize, u64> = [(10, 3)].iter().cloned().collect();
+    /// let summary_stats = compute_length_stats(&length_counts);
+    /// for (name, value) in summary_stats.iter_fields() {
+    ///     println!("{name}\t{value}");
+    /// }
+    /// ```
+    pub fn iter_fields(&self) -> impl Iterator<Item = (&'static str, f64)> {
+        let fields: Vec<(&'static str, f64)> = vec![
+            ("total_bases", self.total_bases as f64),
+            ("total_gigabases", self.total_gigabases),
+            ("total_sequences", self.total_sequences as f64),
+            ("mean_length", self.mean_length),
+            ("geometric_mean_length", self.geometric_mean_length),
+            ("harmonic_mean_length", self.harmonic_mean_length),
+            ("stddev_length", self.stddev_length),
+            ("sem_length", self.sem_length),
+            ("mad_length", self.mad_length),
+            ("fraction_below_mean", self.fraction_below_mean),
+            ("gini_coefficient", self.gini_coefficient),
+            ("longest_seq_base_fraction", self.longest_seq_base_fraction),
+            ("median_length", self.median_length),
+            ("n10", self.n10 as f64),
+            ("n25", self.n25 as f64),
+            ("n50", self.n50 as f64),
+            ("n75", self.n75 as f64),
+            ("n90", self.n90 as f64)
+        ];
+        fields.into_iter()
+    }
+}
+
 /// This will compute multiple different summary statistics based on the length BTreeMap and return a HashMap with all the various metrics
 /// # Arguments
 /// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
@@ -140,20 +702,56 @@ pub struct LengthStats {
 /// assert_eq!(summary_stats.total_sequences, 13);
 /// ```
 pub fn compute_length_stats(length_counts: &BTreeMap<usize, u64>) -> LengthStats {
+    compute_length_stats_with_interpolation(length_counts, false)
+}
+
+/// This is identical to `compute_length_stats(...)`, except the median can optionally be linearly interpolated, see `compute_length_percentile(...)`.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `interpolate` - if true, the median is interpolated between the two middle lengths instead of being an observed length
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::compute_length_stats_with_interpolation;
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 2),
+///     (10, 2)
+/// ].iter().cloned().collect();
+/// assert_eq!(compute_length_stats_with_interpolation(&length_counts, false).median_length, 10.0);
+/// assert_eq!(compute_length_stats_with_interpolation(&length_counts, true).median_length, 7.5);
+/// ```
+pub fn compute_length_stats_with_interpolation(length_counts: &BTreeMap<usize, u64>, interpolate: bool) -> LengthStats {
     //first get all the totals
     let (total_bases, total_seqs): (u64, u64) = compute_total_counts(length_counts);
-    let median_length: f64 = compute_median_length(length_counts, total_seqs);
-    let n10: usize = compute_n_score(length_counts, total_bases, 10);
-    let n25: usize = compute_n_score(length_counts, total_bases, 25);
-    let n50: usize = compute_n_score(length_counts, total_bases, 50);
-    let n75: usize = compute_n_score(length_counts, total_bases, 75);
-    let n90: usize = compute_n_score(length_counts, total_bases, 90);
+    let median_length: f64 = if interpolate {
+        compute_length_percentile(length_counts, total_seqs, 50.0, true)
+    } else {
+        compute_median_length(length_counts, total_seqs)
+    };
+    let n_scores: BTreeMap<usize, usize> = compute_all_n_scores(length_counts, total_bases, &[10, 25, 50, 75, 90]);
+    let n10: usize = n_scores[&10];
+    let n25: usize = n_scores[&25];
+    let n50: usize = n_scores[&50];
+    let n75: usize = n_scores[&75];
+    let n90: usize = n_scores[&90];
+    let mean_length: f64 = (total_bases as f64) / (total_seqs as f64);
+    let stddev_length: f64 = compute_length_stddev(length_counts, mean_length);
+    let sem_length: f64 = if total_seqs > 0 { stddev_length / (total_seqs as f64).sqrt() } else { 0.0 };
 
     //now put the composite stats together
     let final_stats: LengthStats = LengthStats {
         total_bases, 
+        total_gigabases: (total_bases as f64) / 1e9,
         total_sequences: total_seqs,
-        mean_length: (total_bases as f64) / (total_seqs as f64),
+        mean_length,
+        geometric_mean_length: compute_geometric_mean_length(length_counts),
+        harmonic_mean_length: compute_harmonic_mean_length(length_counts),
+        stddev_length,
+        sem_length,
+        mad_length: compute_mad(length_counts, median_length),
+        fraction_below_mean: compute_fraction_below(length_counts, total_seqs, mean_length),
+        gini_coefficient: compute_gini(length_counts),
+        longest_seq_base_fraction: compute_largest_fraction(length_counts, total_bases),
         median_length,
         n10,
         n25,
@@ -164,6 +762,894 @@ pub fn compute_length_stats(length_counts: &BTreeMap<usize, u64>) -> LengthStats
     final_stats
 }
 
+/// A chosen subset of the `LengthStats` statistics, keyed by name, see `compute_selected_stats`
+#[derive(Serialize, Debug, Default, PartialEq)]
+#[serde(transparent)]
+pub struct SelectedStats {
+    /// The value of each selected statistic; integer statistics stay integers
+    pub values: BTreeMap<String, serde_json::Value>
+}
+
+impl SelectedStats {
+    /// Rounds all of the floating point statistics in place to `decimals` places; the integer statistics are unchanged.
+    /// # Arguments
+    /// * `decimals` - the number of decimal places to keep
+    pub fn round_floats(&mut self, decimals: u32) {
+        for value in self.values.values_mut() {
+            if let Some(float_value) = value.as_f64().filter(|_| value.is_f64()) {
+                *value = serde_json::Value::from(round_to_decimals(float_value, decimals));
+            }
+        }
+    }
+}
+
+/// This will match a statistic name to its name in `LENGTH_STAT_NAMES`, also accepting the length statistics without the `_length` suffix (e.g. `median` for `median_length`).
+/// Returns `None` if there is no such statistic.
+/// # Examples
+/// ```
+/// use fastleng::length_stats::resolve_stat_name;
+/// assert_eq!(resolve_stat_name("n50"), Some("n50"));
+/// assert_eq!(resolve_stat_name("median"), Some("median_length"));
+/// assert_eq!(resolve_stat_name("n51"), None);
+/// ```
+pub fn resolve_stat_name(stat_name: &str) -> Option<&'static str> {
+    let long_name: String = format!("{stat_name}_length");
+    LENGTH_STAT_NAMES.iter()
+        .find(|&&known_name| known_name == stat_name || known_name == long_name)
+        .copied()
+}
+
+/// This will compute only the requested statistics from the length BTreeMap, skipping the work for the rest (e.g. the N-scores or the Gini coefficient).
+/// Each requested statistic has the same value as in `compute_length_stats_with_interpolation(...)`.
+/// Returns an error naming the valid statistics if any name is unknown, see `resolve_stat_name(...)`.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `stat_names` - the statistics to compute
+/// * `interpolate` - if true, the median is interpolated between the two middle lengths instead of being an observed length
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::{compute_selected_stats, SelectedStats};
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3)
+/// ].iter().cloned().collect();
+/// let selected_stats: SelectedStats = compute_selected_stats(&length_counts, &["n50", "median", "mean"], false).unwrap();
+/// assert_eq!(selected_stats.values.len(), 3);
+/// assert_eq!(selected_stats.values["n50"], 5);
+/// assert_eq!(selected_stats.values["median_length"], 5.0);
+/// assert!(compute_selected_stats(&length_counts, &["skew"], false).is_err());
+/// ```
+pub fn compute_selected_stats<T: AsRef<str>>(length_counts: &BTreeMap<usize, u64>, stat_names: &[T], interpolate: bool) -> Result<SelectedStats, Box<dyn std::error::Error>> {
+    let resolved_names: Vec<&'static str> = stat_names.iter()
+        .map(|stat_name| resolve_stat_name(stat_name.as_ref()).ok_or_else(|| {
+            format!("Unknown statistic {:?}, expected one of: {}", stat_name.as_ref(), LENGTH_STAT_NAMES.join(", "))
+        }))
+        .collect::<Result<Vec<&'static str>, String>>()?;
+
+    //the totals are cheap and needed by most of the others; the median and N-scores are only computed if something needs them
+    let (total_bases, total_seqs): (u64, u64) = compute_total_counts(length_counts);
+    let mean_length: f64 = (total_bases as f64) / (total_seqs as f64);
+    let needs_median: bool = resolved_names.iter().any(|&stat_name| stat_name == "median_length" || stat_name == "mad_length");
+    let median_length: f64 = if !needs_median {
+        0.0
+    } else if interpolate {
+        compute_length_percentile(length_counts, total_seqs, 50.0, true)
+    } else {
+        compute_median_length(length_counts, total_seqs)
+    };
+    let n_targets: Vec<usize> = resolved_names.iter()
+        .filter_map(|stat_name| stat_name.strip_prefix('n').and_then(|target| target.parse::<usize>().ok()))
+        .collect();
+    let n_scores: BTreeMap<usize, usize> = if n_targets.is_empty() { BTreeMap::new() } else { compute_all_n_scores(length_counts, total_bases, &n_targets) };
+
+    let mut selected_stats: SelectedStats = Default::default();
+    for stat_name in resolved_names.into_iter() {
+        let value: serde_json::Value = match stat_name {
+            "total_bases" => total_bases.into(),
+            "total_gigabases" => ((total_bases as f64) / 1e9).into(),
+            "total_sequences" => total_seqs.into(),
+            "mean_length" => mean_length.into(),
+            "geometric_mean_length" => compute_geometric_mean_length(length_counts).into(),
+            "harmonic_mean_length" => compute_harmonic_mean_length(length_counts).into(),
+            "stddev_length" => compute_length_stddev(length_counts, mean_length).into(),
+            "sem_length" => {
+                let stddev_length: f64 = compute_length_stddev(length_counts, mean_length);
+                (if total_seqs > 0 { stddev_length / (total_seqs as f64).sqrt() } else { 0.0 }).into()
+            },
+            "mad_length" => compute_mad(length_counts, median_length).into(),
+            "fraction_below_mean" => compute_fraction_below(length_counts, total_seqs, mean_length).into(),
+            "gini_coefficient" => compute_gini(length_counts).into(),
+            "longest_seq_base_fraction" => compute_largest_fraction(length_counts, total_bases).into(),
+            "median_length" => median_length.into(),
+            n_name => {
+                let target: usize = n_name[1..].parse().unwrap();
+                n_scores[&target].into()
+            }
+        };
+        selected_stats.values.insert(stat_name.to_string(), value);
+    }
+    Ok(selected_stats)
+}
+
+/// A reduced set of summary statistics that avoids the N-score calculations, see `compute_basic_stats`
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct BasicLengthStats {
+    /// The total number of bases analyzed
+    pub total_bases: u64,
+    /// The total number of bases analyzed in gigabases (i.e. `total_bases / 1e9`), for readability
+    pub total_gigabases: f64,
+    /// The total number of sequences (i.e. strings) analyzed
+    pub total_sequences: u64,
+    /// The average length of the sequences
+    pub mean_length: f64,
+    /// The median length of the sequences
+    pub median_length: f64,
+    /// The length of the shortest sequence, 0 if there are no sequences
+    pub min_length: usize,
+    /// The length of the longest sequence, 0 if there are no sequences
+    pub max_length: usize
+}
+
+impl BasicLengthStats {
+    /// Rounds all of the floating point statistics in place to `decimals` places; the integer statistics are unchanged.
+    /// # Arguments
+    /// * `decimals` - the number of decimal places to keep
+    pub fn round_floats(&mut self, decimals: u32) {
+        self.total_gigabases = round_to_decimals(self.total_gigabases, decimals);
+        self.mean_length = round_to_decimals(self.mean_length, decimals);
+        self.median_length = round_to_decimals(self.median_length, decimals);
+    }
+}
+
+/// This will compute only the totals, mean, median, minimum, and maximum lengths, skipping the N-scores and other more expensive metrics.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::{compute_basic_stats,BasicLengthStats};
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3)
+/// ].iter().cloned().collect();
+/// let basic_stats: BasicLengthStats = compute_basic_stats(&length_counts);
+/// assert_eq!(basic_stats.total_bases, 80);
+/// assert_eq!(basic_stats.min_length, 5);
+/// assert_eq!(basic_stats.max_length, 10);
+/// ```
+pub fn compute_basic_stats(length_counts: &BTreeMap<usize, u64>) -> BasicLengthStats {
+    let (total_bases, total_seqs): (u64, u64) = compute_total_counts(length_counts);
+    BasicLengthStats {
+        total_bases,
+        total_gigabases: (total_bases as f64) / 1e9,
+        total_sequences: total_seqs,
+        mean_length: (total_bases as f64) / (total_seqs as f64),
+        median_length: compute_median_length(length_counts, total_seqs),
+        //BTreeMap keys are sorted, so the ends are the extremes
+        min_length: length_counts.keys().next().copied().unwrap_or(0),
+        max_length: length_counts.keys().next_back().copied().unwrap_or(0)
+    }
+}
+
+/// Summary statistics with percentiles estimated from a t-digest instead of the full length counts, see `compute_approximate_stats`
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ApproximateLengthStats {
+    /// The total number of bases analyzed
+    pub total_bases: u64,
+    /// The total number of bases analyzed in gigabases (i.e. `total_bases / 1e9`), for readability
+    pub total_gigabases: f64,
+    /// The total number of sequences (i.e. strings) analyzed
+    pub total_sequences: u64,
+    /// The average length of the sequences
+    pub mean_length: f64,
+    /// The length of the shortest sequence, 0 if there are no sequences
+    pub min_length: usize,
+    /// The length of the longest sequence, 0 if there are no sequences
+    pub max_length: usize,
+    /// The approximate 10th percentile length
+    pub p10_length: f64,
+    /// The approximate 25th percentile length
+    pub p25_length: f64,
+    /// The approximate median length
+    pub median_length: f64,
+    /// The approximate 75th percentile length
+    pub p75_length: f64,
+    /// The approximate 90th percentile length
+    pub p90_length: f64
+}
+
+impl ApproximateLengthStats {
+    /// Rounds all of the floating point statistics in place to `decimals` places; the integer statistics are unchanged.
+    /// # Arguments
+    /// * `decimals` - the number of decimal places to keep
+    pub fn round_floats(&mut self, decimals: u32) {
+        self.total_gigabases = round_to_decimals(self.total_gigabases, decimals);
+        self.mean_length = round_to_decimals(self.mean_length, decimals);
+        self.p10_length = round_to_decimals(self.p10_length, decimals);
+        self.p25_length = round_to_decimals(self.p25_length, decimals);
+        self.median_length = round_to_decimals(self.median_length, decimals);
+        self.p75_length = round_to_decimals(self.p75_length, decimals);
+        self.p90_length = round_to_decimals(self.p90_length, decimals);
+    }
+}
+
+/// This will compute the totals, mean, and extremes exactly along with percentiles estimated from a t-digest of the sequence lengths.
+/// # Arguments
+/// * `length_digest` - a t-digest with one entry per sequence length
+/// * `total_bases` - the exact total number of bases, since the digest only tracks the number of sequences
+/// # Examples
+/// ```
+/// use fastleng::length_stats::{compute_approximate_stats, ApproximateLengthStats};
+/// use fastleng::tdigest::{TDigest, DEFAULT_COMPRESSION};
+/// let mut length_digest = TDigest::new(DEFAULT_COMPRESSION);
+/// (0..10).for_each(|_| length_digest.add(5.0, 1));
+/// (0..3).for_each(|_| length_digest.add(10.0, 1));
+/// let approximate_stats: ApproximateLengthStats = compute_approximate_stats(&mut length_digest, 80);
+/// assert_eq!(approximate_stats.total_sequences, 13);
+/// assert_eq!(approximate_stats.median_length, 5.0);
+/// assert_eq!(approximate_stats.max_length, 10);
+/// ```
+pub fn compute_approximate_stats(length_digest: &mut TDigest, total_bases: u64) -> ApproximateLengthStats {
+    let total_seqs: u64 = length_digest.count();
+    ApproximateLengthStats {
+        total_bases,
+        total_gigabases: (total_bases as f64) / 1e9,
+        total_sequences: total_seqs,
+        mean_length: (total_bases as f64) / (total_seqs as f64),
+        min_length: length_digest.min().unwrap_or(0.0) as usize,
+        max_length: length_digest.max().unwrap_or(0.0) as usize,
+        p10_length: length_digest.quantile(0.1),
+        p25_length: length_digest.quantile(0.25),
+        median_length: length_digest.quantile(0.5),
+        p75_length: length_digest.quantile(0.75),
+        p90_length: length_digest.quantile(0.9)
+    }
+}
+
+/// The change in a single statistic between a baseline and a comparison dataset
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct FieldComparison {
+    /// The name of the statistic, matching the `LengthStats` field
+    pub field: String,
+    /// The value in the baseline dataset
+    pub baseline: f64,
+    /// The value in the comparison dataset
+    pub comparison: f64,
+    /// The absolute change, `comparison - baseline`
+    pub absolute_change: f64,
+    /// The change as a percentage of the baseline; `None` if the baseline is zero
+    pub percent_change: Option<f64>
+}
+
+impl FieldComparison {
+    /// Rounds all of the values in place to `decimals` places.
+    /// # Arguments
+    /// * `decimals` - the number of decimal places to keep
+    pub fn round_floats(&mut self, decimals: u32) {
+        self.baseline = round_to_decimals(self.baseline, decimals);
+        self.comparison = round_to_decimals(self.comparison, decimals);
+        self.absolute_change = round_to_decimals(self.absolute_change, decimals);
+        self.percent_change = self.percent_change.map(|percent| round_to_decimals(percent, decimals));
+    }
+}
+
+/// This will compare every statistic of two datasets, e.g. before and after filtering, and return the changes in field order.
+/// # Arguments
+/// * `baseline` - the statistics the changes are relative to
+/// * `comparison` - the statistics to compare against the baseline
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::{compare_length_stats, compute_length_stats};
+/// let baseline_counts: BTreeMap<usize, u64> = [(10, 4)].iter().cloned().collect();
+/// let comparison_counts: BTreeMap<usize, u64> = [(10, 2), (20, 1)].iter().cloned().collect();
+/// let comparisons = compare_length_stats(&compute_length_stats(&baseline_counts), &compute_length_stats(&comparison_counts));
+/// assert_eq!(comparisons[0].field, "total_bases");
+/// assert_eq!(comparisons[0].absolute_change, 0.0);
+/// assert_eq!(comparisons[2].field, "total_sequences");
+/// assert_eq!(comparisons[2].percent_change, Some(-25.0));
+/// ```
+pub fn compare_length_stats(baseline: &LengthStats, comparison: &LengthStats) -> Vec<FieldComparison> {
+    baseline.iter_fields().zip(comparison.iter_fields())
+        .map(|((field, baseline_value), (_field, comparison_value))| {
+            let absolute_change: f64 = comparison_value - baseline_value;
+            FieldComparison {
+                field: field.to_string(),
+                baseline: baseline_value,
+                comparison: comparison_value,
+                absolute_change,
+                //a zero baseline has no meaningful relative change
+                percent_change: if baseline_value == 0.0 { None } else { Some(100.0 * absolute_change / baseline_value) }
+            }
+        })
+        .collect()
+}
+
+/// This will add all of the counts from `other_counts` into `length_counts`.
+/// # Arguments
+/// * `length_counts` - the BTreeMap to add counts into
+/// * `other_counts` - the BTreeMap of counts to add
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::merge_length_counts;
+/// let mut length_counts: BTreeMap<usize, u64> = [(5, 10)].iter().cloned().collect();
+/// let other_counts: BTreeMap<usize, u64> = [(5, 1), (10, 3)].iter().cloned().collect();
+/// merge_length_counts(&mut length_counts, &other_counts);
+/// assert_eq!(length_counts[&5], 11);
+/// assert_eq!(length_counts[&10], 3);
+/// ```
+pub fn merge_length_counts(length_counts: &mut BTreeMap<usize, u64>, other_counts: &BTreeMap<usize, u64>) {
+    for (seq_len, seq_count) in other_counts.iter() {
+        //insert 0 if absent; then increment
+        let len_count: &mut u64 = length_counts.entry(*seq_len).or_insert(0);
+        *len_count = len_count.saturating_add(*seq_count);
+    }
+}
+
+/// This will return a copy of the length counts with all sequences of the specified lengths removed.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `excluded_lengths` - the exact sequence lengths to remove
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::exclude_lengths;
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3),
+///     (35, 100)
+/// ].iter().cloned().collect();
+/// let filtered_counts = exclude_lengths(&length_counts, &[35]);
+/// assert_eq!(filtered_counts.len(), 2);
+/// assert!(!filtered_counts.contains_key(&35));
+/// ```
+pub fn exclude_lengths(length_counts: &BTreeMap<usize, u64>, excluded_lengths: &[usize]) -> BTreeMap<usize, u64> {
+    length_counts.iter()
+        .filter(|(seq_len, _seq_count)| !excluded_lengths.contains(seq_len))
+        .map(|(&seq_len, &seq_count)| (seq_len, seq_count))
+        .collect()
+}
+
+/// This will remove any zero-length sequences (e.g. a FASTA header with no sequence) from the length counts in place and return how many were removed.
+/// They contribute no bases, so leaving them in would only drag down the mean, median, and N-scores.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::remove_zero_lengths;
+/// let mut length_counts: BTreeMap<usize, u64> = [
+///     (0, 2),
+///     (10, 3)
+/// ].iter().cloned().collect();
+/// assert_eq!(remove_zero_lengths(&mut length_counts), 2);
+/// assert!(!length_counts.contains_key(&0));
+/// assert_eq!(remove_zero_lengths(&mut length_counts), 0);
+/// ```
+pub fn remove_zero_lengths(length_counts: &mut BTreeMap<usize, u64>) -> u64 {
+    length_counts.remove(&0).unwrap_or(0)
+}
+
+/// This will remove the `count` longest individual sequences, e.g. a single chimeric mega-read, so the stats describe the bulk of the distribution.
+/// Only one sequence is removed per drop, so a length shared by several sequences is decremented and only removed once none are left.
+/// Returns the dropped lengths, longest first, which has fewer than `count` entries only if every sequence was dropped.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length; modified in place
+/// * `count` - the number of sequences to drop
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::drop_longest_lengths;
+/// let mut length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3),
+///     (50000, 1)
+/// ].iter().cloned().collect();
+/// assert_eq!(drop_longest_lengths(&mut length_counts, 2), vec![50000, 10]);
+/// assert_eq!(length_counts.get(&50000), None);
+/// assert_eq!(length_counts.get(&10), Some(&2));
+/// ```
+pub fn drop_longest_lengths(length_counts: &mut BTreeMap<usize, u64>, count: usize) -> Vec<usize> {
+    //the dropped lengths are exactly the longest ones, so reuse that walk
+    let dropped_lengths: Vec<usize> = top_n_lengths(length_counts, count);
+    for seq_len in dropped_lengths.iter() {
+        let seq_count: &mut u64 = length_counts.get_mut(seq_len).expect("dropped lengths come from the counts");
+        *seq_count -= 1;
+        if *seq_count == 0 {
+            length_counts.remove(seq_len);
+        }
+    }
+    dropped_lengths
+}
+
+/// The default maximum number of sequences that `expand_lengths(...)` will expand, to avoid running out of memory on large inputs
+pub const DEFAULT_EXPAND_LENGTHS_CAP: u64 = 10_000_000;
+
+/// This will expand the length counts back into a sorted list of individual sequence lengths, repeating each length by its count.
+/// This is only sensible for small inputs, so an error is returned instead if there are more than `max_sequences` sequences.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `max_sequences` - the maximum number of sequences allowed in the expanded list
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::expand_lengths;
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 2),
+///     (10, 1)
+/// ].iter().cloned().collect();
+/// assert_eq!(expand_lengths(&length_counts, 100).unwrap(), vec![5, 5, 10]);
+/// assert!(expand_lengths(&length_counts, 2).is_err());
+/// ```
+pub fn expand_lengths(length_counts: &BTreeMap<usize, u64>, max_sequences: u64) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+    let (_total_bases, total_seqs): (u64, u64) = compute_total_counts(length_counts);
+    if total_seqs > max_sequences {
+        return Err(format!("Refusing to expand {} sequence lengths, the limit is {}", total_seqs, max_sequences).into());
+    }
+
+    let mut expanded: Vec<usize> = Vec::with_capacity(total_seqs as usize);
+    for (&seq_len, &seq_count) in length_counts.iter() {
+        expanded.extend(std::iter::repeat_n(seq_len, seq_count as usize));
+    }
+    Ok(expanded)
+}
+
+/// An inclusive window of sequence lengths, e.g. to characterize only the main read-length mode
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct LengthRange {
+    /// The minimum sequence length to keep (inclusive)
+    pub min: usize,
+    /// The maximum sequence length to keep (inclusive)
+    pub max: usize
+}
+
+impl std::str::FromStr for LengthRange {
+    type Err = String;
+
+    /// Parses a range in the form `MIN:MAX`, where `MIN` must not be larger than `MAX`
+    fn from_str(range_str: &str) -> Result<LengthRange, String> {
+        let (min_str, max_str) = range_str.split_once(':')
+            .ok_or_else(|| format!("expected a range in the form MIN:MAX, got {:?}", range_str))?;
+        let min: usize = min_str.trim().parse().map_err(|e| format!("invalid minimum length {:?}: {}", min_str, e))?;
+        let max: usize = max_str.trim().parse().map_err(|e| format!("invalid maximum length {:?}: {}", max_str, e))?;
+        if min > max {
+            return Err(format!("minimum length {} is larger than maximum length {}", min, max));
+        }
+        Ok(LengthRange { min, max })
+    }
+}
+
+/// This will return a copy of the length counts with only the sequences inside an inclusive length window.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `length_range` - the inclusive window of lengths to keep
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::{filter_length_range, LengthRange};
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3),
+///     (35, 100)
+/// ].iter().cloned().collect();
+/// let length_range: LengthRange = "5:10".parse().unwrap();
+/// let filtered_counts = filter_length_range(&length_counts, &length_range);
+/// assert_eq!(filtered_counts, [(5, 10), (10, 3)].iter().cloned().collect());
+/// ```
+pub fn filter_length_range(length_counts: &BTreeMap<usize, u64>, length_range: &LengthRange) -> BTreeMap<usize, u64> {
+    length_counts.range(length_range.min..=length_range.max)
+        .map(|(&seq_len, &seq_count)| (seq_len, seq_count))
+        .collect()
+}
+
+/// A single length bin of the detailed histogram, with the count normalized against the totals
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct HistogramEntry {
+    /// The number of sequences with this length
+    pub count: u64,
+    /// The fraction of all sequences that have this length
+    pub seq_fraction: f64,
+    /// The fraction of all bases that are in sequences of this length
+    pub base_fraction: f64
+}
+
+impl HistogramEntry {
+    /// Rounds the fractions in place to `decimals` places
+    /// # Arguments
+    /// * `decimals` - the number of decimal places to keep
+    pub fn round_floats(&mut self, decimals: u32) {
+        self.seq_fraction = round_to_decimals(self.seq_fraction, decimals);
+        self.base_fraction = round_to_decimals(self.base_fraction, decimals);
+    }
+}
+
+/// This will convert the length counts into a detailed histogram where each length also reports the fraction of sequences and bases it contains.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::compute_detailed_histogram;
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3)
+/// ].iter().cloned().collect();
+/// let detailed = compute_detailed_histogram(&length_counts);
+/// assert_eq!(detailed[&10].count, 3);
+/// assert_eq!(detailed[&10].base_fraction, 30.0 / 80.0);
+/// ```
+pub fn compute_detailed_histogram(length_counts: &BTreeMap<usize, u64>) -> BTreeMap<usize, HistogramEntry> {
+    let (total_bases, total_seqs): (u64, u64) = compute_total_counts(length_counts);
+    length_counts.iter()
+        .map(|(&seq_len, &seq_count)| {
+            let bases: u64 = (seq_len as u64).saturating_mul(seq_count);
+            let entry = HistogramEntry {
+                count: seq_count,
+                seq_fraction: if total_seqs > 0 { seq_count as f64 / total_seqs as f64 } else { 0.0 },
+                base_fraction: if total_bases > 0 { bases as f64 / total_bases as f64 } else { 0.0 }
+            };
+            (seq_len, entry)
+        })
+        .collect()
+}
+
+/// This will convert the length counts into a histogram weighted by bases instead of sequences, i.e. each length maps to `length * count`.
+/// This shows where the bases are, even when short sequences dominate by count.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::compute_base_histogram;
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3)
+/// ].iter().cloned().collect();
+/// let base_histogram: BTreeMap<usize, u64> = compute_base_histogram(&length_counts);
+/// assert_eq!(base_histogram[&5], 50);
+/// assert_eq!(base_histogram[&10], 30);
+/// ```
+pub fn compute_base_histogram(length_counts: &BTreeMap<usize, u64>) -> BTreeMap<usize, u64> {
+    length_counts.iter()
+        .map(|(&seq_len, &seq_count)| (seq_len, (seq_len as u64).saturating_mul(seq_count)))
+        .collect()
+}
+
+/// This will bucket the length counts into logarithmic bins, which suit long-read distributions that span orders of magnitude.
+/// Each bin is keyed by its lower bound, a power of `log_base`, and holds every length from that bound up to (but not including) the next power.
+/// Since log(0) is undefined, zero-length sequences get their own bin keyed by 0; all other bins start at 1.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `log_base` - the ratio between consecutive bin bounds, e.g. 2 for powers of two; must be at least 2
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::compute_log_histogram;
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (0, 1),
+///     (3, 2),
+///     (5, 10),
+///     (7, 1),
+///     (1000, 3)
+/// ].iter().cloned().collect();
+/// let log_histogram: BTreeMap<usize, u64> = compute_log_histogram(&length_counts, 2);
+/// let expected: BTreeMap<usize, u64> = [(0, 1), (2, 2), (4, 11), (512, 3)].iter().cloned().collect();
+/// assert_eq!(log_histogram, expected);
+/// ```
+pub fn compute_log_histogram(length_counts: &BTreeMap<usize, u64>, log_base: usize) -> BTreeMap<usize, u64> {
+    assert!(log_base >= 2);
+    let mut log_histogram: BTreeMap<usize, u64> = BTreeMap::new();
+    for (&seq_len, &seq_count) in length_counts.iter() {
+        //integer powers avoid rounding errors right at the bin edges
+        let mut lower_bound: usize = if seq_len == 0 { 0 } else { 1 };
+        while lower_bound > 0 && seq_len / lower_bound >= log_base {
+            lower_bound *= log_base;
+        }
+        let bin_count: &mut u64 = log_histogram.entry(lower_bound).or_insert(0);
+        *bin_count = bin_count.saturating_add(seq_count);
+    }
+    log_histogram
+}
+
+/// This will compute the data behind every N-score: for each distinct length from longest to shortest, the cumulative fraction of bases in sequences of at least that length.
+/// For example, the N50 is the first length in the curve where the cumulative fraction reaches 0.5.
+/// Returns an empty curve if `total_bases` is 0.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `total_bases` - the total number of bases represented by the `length_counts` parameter, this can be computed by `compute_total_counts(...)`
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::{compute_nx_curve,compute_total_counts};
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3)
+/// ].iter().cloned().collect();
+/// let (total_bases, _total_seqs) = compute_total_counts(&length_counts);
+/// let nx_curve: Vec<(usize, f64)> = compute_nx_curve(&length_counts, total_bases);
+/// assert_eq!(nx_curve, vec![(10, 0.375), (5, 1.0)]);
+/// ```
+pub fn compute_nx_curve(length_counts: &BTreeMap<usize, u64>, total_bases: u64) -> Vec<(usize, f64)> {
+    if total_bases == 0 {
+        return vec![];
+    }
+
+    let mut current_bases: u64 = 0;
+    length_counts.iter().rev()
+        .map(|(&seq_len, &seq_count)| {
+            current_bases = current_bases.saturating_add((seq_len as u64).saturating_mul(seq_count));
+            (seq_len, current_bases as f64 / total_bases as f64)
+        })
+        .collect()
+}
+
+/// This will compute the NG-score (e.g. NG50) for the sequence lengths provided.
+/// This is identical to the N-score, except the target is a percentage of an expected genome size instead of the total observed bases.
+/// Returns `None` if the sequences do not contain enough bases to reach the target.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `genome_size` - the expected genome size in bases
+/// * `target` - the score target; e.g. for NG50, this parameter should be 50
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::compute_ng_score;
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3)
+/// ].iter().cloned().collect();
+/// assert_eq!(compute_ng_score(&length_counts, 100, 50), Some(5));
+/// assert_eq!(compute_ng_score(&length_counts, 1000, 50), None);
+/// ```
+pub fn compute_ng_score(length_counts: &BTreeMap<usize, u64>, genome_size: u64, target: usize) -> Option<usize> {
+    //make sure this is in our allowed range
+    assert!((1..=99).contains(&target));
+
+    //calculate the target number of bases
+    let target_bases: f64 = (target as u128 * genome_size as u128) as f64 / 100.0;
+    let mut current_bases: u64 = 0;
+    for (seq_len, seq_count) in length_counts.iter().rev() {
+        current_bases = current_bases.saturating_add((*seq_len as u64).saturating_mul(*seq_count));
+        if current_bases as f64 >= target_bases {
+            return Some(*seq_len);
+        }
+    }
+    None
+}
+
+/// This struct encapsulates the NG-scores relative to an expected genome size
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct NgStats {
+    /// The expected genome size (in bases) that the NG-scores are relative to
+    pub genome_size: u64,
+    /// The expected sequencing depth, i.e. `total_bases / genome_size`; `None` for a genome size of 0
+    pub expected_coverage: Option<f64>,
+    /// NG10 - bases in sequences of length greater than this value cover 10% of the genome size; `None` if never reached
+    pub ng10: Option<usize>,
+    /// NG25 - bases in sequences of length greater than this value cover 25% of the genome size; `None` if never reached
+    pub ng25: Option<usize>,
+    /// NG50 - bases in sequences of length greater than this value cover 50% of the genome size; `None` if never reached
+    pub ng50: Option<usize>,
+    /// NG75 - bases in sequences of length greater than this value cover 75% of the genome size; `None` if never reached
+    pub ng75: Option<usize>,
+    /// NG90 - bases in sequences of length greater than this value cover 90% of the genome size; `None` if never reached
+    pub ng90: Option<usize>
+}
+
+impl NgStats {
+    /// Rounds the expected coverage in place to `decimals` places; the NG-scores are unchanged.
+    /// # Arguments
+    /// * `decimals` - the number of decimal places to keep
+    pub fn round_floats(&mut self, decimals: u32) {
+        self.expected_coverage = self.expected_coverage.map(|coverage| round_to_decimals(coverage, decimals));
+    }
+}
+
+/// This will compute the standard NG-scores for the sequence lengths relative to an expected genome size, along with the expected coverage of that genome.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `genome_size` - the expected genome size in bases
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::{compute_ng_stats,NgStats};
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3)
+/// ].iter().cloned().collect();
+/// let ng_stats: NgStats = compute_ng_stats(&length_counts, 100);
+/// assert_eq!(ng_stats.ng25, Some(10));
+/// assert_eq!(ng_stats.ng90, None);
+/// assert_eq!(ng_stats.expected_coverage, Some(0.8));
+/// ```
+pub fn compute_ng_stats(length_counts: &BTreeMap<usize, u64>, genome_size: u64) -> NgStats {
+    let (total_bases, _total_seqs) = compute_total_counts(length_counts);
+    NgStats {
+        genome_size,
+        expected_coverage: if genome_size > 0 { Some(total_bases as f64 / genome_size as f64) } else { None },
+        ng10: compute_ng_score(length_counts, genome_size, 10),
+        ng25: compute_ng_score(length_counts, genome_size, 25),
+        ng50: compute_ng_score(length_counts, genome_size, 50),
+        ng75: compute_ng_score(length_counts, genome_size, 75),
+        ng90: compute_ng_score(length_counts, genome_size, 90)
+    }
+}
+
+/// This struct encapsulates the basic statistics of the longest sequences, e.g. the longest 10% of reads
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct TopFractionStats {
+    /// The fraction of sequences (by count) that these statistics cover, e.g. 0.1 for the longest 10%
+    pub fraction: f64,
+    /// The number of sequences included, which is the fraction of all sequences rounded up
+    pub total_sequences: u64,
+    /// The total number of bases in the included sequences
+    pub total_bases: u64,
+    /// The fraction of all bases that are in the included sequences
+    pub base_fraction: f64,
+    /// The mean length of the included sequences
+    pub mean_length: f64,
+    /// The shortest of the included sequences
+    pub min_length: usize
+}
+
+impl TopFractionStats {
+    /// Rounds all of the floating point statistics in place to `decimals` places; the integer statistics are unchanged.
+    /// # Arguments
+    /// * `decimals` - the number of decimal places to keep
+    pub fn round_floats(&mut self, decimals: u32) {
+        self.base_fraction = round_to_decimals(self.base_fraction, decimals);
+        self.mean_length = round_to_decimals(self.mean_length, decimals);
+    }
+}
+
+/// This will compute the basic statistics of only the longest `fraction` of sequences by count, e.g. the mean length of the longest 10% of reads.
+/// The number of sequences included is rounded up, so any non-empty input includes at least one sequence; ties at the cutoff length are split as needed.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `fraction` - the fraction of sequences to include, must be in the range (0.0, 1.0]
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::{compute_top_fraction_stats,TopFractionStats};
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3)
+/// ].iter().cloned().collect();
+/// let top_stats: TopFractionStats = compute_top_fraction_stats(&length_counts, 0.25);
+/// assert_eq!(top_stats.total_sequences, 4);
+/// assert_eq!(top_stats.total_bases, 35);
+/// assert_eq!(top_stats.mean_length, 8.75);
+/// assert_eq!(top_stats.min_length, 5);
+/// ```
+pub fn compute_top_fraction_stats(length_counts: &BTreeMap<usize, u64>, fraction: f64) -> TopFractionStats {
+    //make sure this is in our allowed range
+    assert!(fraction > 0.0 && fraction <= 1.0);
+
+    //same reverse walk as the N-score, but the target is a number of sequences
+    let (all_bases, all_seqs) = compute_total_counts(length_counts);
+    let target_seqs: u64 = (fraction * all_seqs as f64).ceil() as u64;
+    let mut current_seqs: u64 = 0;
+    let mut current_bases: u64 = 0;
+    let mut min_length: usize = 0;
+    for (seq_len, seq_count) in length_counts.iter().rev() {
+        if current_seqs >= target_seqs {
+            break;
+        }
+        let used_count: u64 = (*seq_count).min(target_seqs - current_seqs);
+        current_seqs += used_count;
+        current_bases = current_bases.saturating_add((*seq_len as u64).saturating_mul(used_count));
+        min_length = *seq_len;
+    }
+
+    TopFractionStats {
+        fraction,
+        total_sequences: current_seqs,
+        total_bases: current_bases,
+        base_fraction: if all_bases == 0 { 0.0 } else { current_bases as f64 / all_bases as f64 },
+        mean_length: if current_seqs == 0 { 0.0 } else { current_bases as f64 / current_seqs as f64 },
+        min_length
+    }
+}
+
+/// This will list the `n` longest individual sequence lengths, longest first, so a length shared by several sequences is repeated.
+/// If there are fewer than `n` sequences, every length is returned.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `n` - the number of lengths to return
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::top_n_lengths;
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3),
+///     (50, 1)
+/// ].iter().cloned().collect();
+/// assert_eq!(top_n_lengths(&length_counts, 3), vec![50, 10, 10]);
+/// assert_eq!(top_n_lengths(&length_counts, 100).len(), 14);
+/// ```
+pub fn top_n_lengths(length_counts: &BTreeMap<usize, u64>, n: usize) -> Vec<usize> {
+    let mut top_lengths: Vec<usize> = vec![];
+    for (&seq_len, &seq_count) in length_counts.iter().rev() {
+        let remaining: usize = n - top_lengths.len();
+        if remaining == 0 {
+            break;
+        }
+        let take_count: usize = seq_count.min(remaining as u64) as usize;
+        top_lengths.extend(std::iter::repeat_n(seq_len, take_count));
+    }
+    top_lengths
+}
+
+/// This will find the peaks (local maxima) of the length histogram that stand out by at least `min_prominence` sequences, e.g. a short-fragment or adapter-dimer mode alongside the main one.
+/// The prominence of a peak is how far its count drops before reaching a higher peak (or the end of the histogram) on whichever side drops the least, as in topographic prominence.
+/// Lengths between the observed ones count as zero sequences, and a flat-topped peak is reported at its shortest length.
+/// Returns the length and count of each peak, from shortest to longest.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `min_prominence` - the smallest prominence of a reported peak; 0 reports every local maximum
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::detect_peaks;
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (50, 8),
+///     (51, 20),
+///     (52, 7),
+///     (1000, 30),
+///     (1001, 100),
+///     (1002, 95),
+///     (1003, 97),
+///     (1004, 40)
+/// ].iter().cloned().collect();
+/// assert_eq!(detect_peaks(&length_counts, 10), vec![(51, 20), (1001, 100)]);
+/// assert_eq!(detect_peaks(&length_counts, 50), vec![(1001, 100)]);
+/// ```
+pub fn detect_peaks(length_counts: &BTreeMap<usize, u64>, min_prominence: u64) -> Vec<(usize, u64)> {
+    //pad the ends and fill each gap between observed lengths with a single zero, which is enough to separate the peaks on either side
+    let mut profile: Vec<(usize, u64)> = Vec::with_capacity(2 * length_counts.len() + 1);
+    profile.push((0, 0));
+    for (&seq_len, &seq_count) in length_counts.iter() {
+        let previous_len: usize = profile[profile.len() - 1].0;
+        if profile.len() > 1 && seq_len > previous_len + 1 {
+            profile.push((previous_len + 1, 0));
+        }
+        profile.push((seq_len, seq_count));
+    }
+    profile.push((0, 0));
+
+    let mut peaks: Vec<(usize, u64)> = vec![];
+    let mut index: usize = 1;
+    while index + 1 < profile.len() {
+        //find the end of any plateau starting here
+        let (peak_len, peak_count) = profile[index];
+        let mut plateau_end: usize = index;
+        while profile[plateau_end + 1].1 == peak_count && plateau_end + 2 < profile.len() {
+            plateau_end += 1;
+        }
+        if peak_count > profile[index - 1].1 && peak_count > profile[plateau_end + 1].1 {
+            //walk out on each side until a higher count (or the padded end), tracking the lowest count along the way
+            let left_min: u64 = profile[..index].iter().rev()
+                .take_while(|&&(_seq_len, seq_count)| seq_count <= peak_count)
+                .map(|&(_seq_len, seq_count)| seq_count)
+                .min()
+                .unwrap_or(0);
+            let right_min: u64 = profile[plateau_end + 1..].iter()
+                .take_while(|&&(_seq_len, seq_count)| seq_count <= peak_count)
+                .map(|&(_seq_len, seq_count)| seq_count)
+                .min()
+                .unwrap_or(0);
+            if peak_count - left_min.max(right_min) >= min_prominence {
+                peaks.push((peak_len, peak_count));
+            }
+        }
+        index = plateau_end + 1;
+    }
+    peaks
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -181,6 +1667,45 @@ mod tests {
         assert_eq!((expected_total_bases, expected_total_seqs), computed_results);
     }
 
+    #[test]
+    fn test_compute_total_counts_overflow() {
+        //right at the limit is still exact
+        let max_lens: BTreeMap<usize, u64> = [(1, u64::MAX)].iter().cloned().collect();
+        assert_eq!(compute_total_counts(&max_lens), (u64::MAX, u64::MAX));
+
+        //the base product overflows but the sequence count does not
+        let product_lens: BTreeMap<usize, u64> = [(4, u64::MAX / 2)].iter().cloned().collect();
+        assert_eq!(compute_total_counts(&product_lens), (u64::MAX, u64::MAX / 2));
+
+        //both sums overflow across multiple lengths
+        let sum_lens: BTreeMap<usize, u64> = [
+            (1, u64::MAX - 1),
+            (2, 2)
+        ].iter().cloned().collect();
+        assert_eq!(compute_total_counts(&sum_lens), (u64::MAX, u64::MAX));
+    }
+
+    #[test]
+    fn test_compute_length_stats_overflow() {
+        //the long sequences alone overflow the bases, so every walk must saturate instead of panicking or wrapping
+        let seq_lens: BTreeMap<usize, u64> = [
+            (10, u64::MAX - 1),
+            (1000000, u64::MAX / 2)
+        ].iter().cloned().collect();
+        let stats: LengthStats = compute_length_stats(&seq_lens);
+        assert_eq!(stats.total_bases, u64::MAX);
+        assert_eq!(stats.total_sequences, u64::MAX);
+        assert_eq!(stats.median_length, 10.0);
+        assert_eq!(stats.n10, 1000000);
+        assert_eq!(stats.n50, 1000000);
+        assert_eq!(stats.n90, 1000000);
+
+        //the assembly-style report walks the same counts
+        let text: String = crate::output::format_assembly_stats(&seq_lens, "overflow.fa");
+        assert!(text.contains("N50 = 1000000, n = "));
+        assert!(text.contains("N100 = 1000000, n = "));
+    }
+
     #[test]
     fn test_compute_median_length() {
         //odd one
@@ -305,6 +1830,44 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_compute_all_n_scores() {
+        let all_targets: Vec<usize> = (1..100).collect();
+        let test_sets: Vec<BTreeMap<usize, u64>> = vec![
+            [(1, 1), (2, 1), (3, 1)].iter().cloned().collect(),
+            [(1, 1000), (1000, 1)].iter().cloned().collect(),
+            [(1, 1001), (1000, 1)].iter().cloned().collect(),
+            [(2, 3), (3, 2), (4, 1)].iter().cloned().collect(),
+            (1..101).map(|x| (x, 1)).collect(),
+            [(0, 5)].iter().cloned().collect(),
+            BTreeMap::new()
+        ];
+
+        //every target should match the single target version
+        for seq_lens in test_sets.iter() {
+            let (total_bases, _total_seqs) = compute_total_counts(seq_lens);
+            let n_scores = compute_all_n_scores(seq_lens, total_bases, &all_targets);
+            assert_eq!(n_scores.len(), all_targets.len());
+            for target in all_targets.iter() {
+                assert_eq!(n_scores[target], compute_n_score(seq_lens, total_bases, *target));
+            }
+        }
+
+        //unsorted and duplicate targets are fine
+        let seq_lens = &test_sets[1];
+        let (total_bases, _total_seqs) = compute_total_counts(seq_lens);
+        let n_scores = compute_all_n_scores(seq_lens, total_bases, &[90, 10, 50, 10]);
+        let expected: BTreeMap<usize, usize> = [(10, 1000), (50, 1000), (90, 1)].iter().cloned().collect();
+        assert_eq!(n_scores, expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_compute_all_n_scores_out_of_range() {
+        let seq_lens: BTreeMap<usize, u64> = [(10, 100)].iter().cloned().collect();
+        let _n_scores = compute_all_n_scores(&seq_lens, 1000, &[50, 100]);
+    }
+
     #[test]
     fn test_full_all_same() {
         let seq_lens: BTreeMap<usize, u64> = [
@@ -313,8 +1876,17 @@ mod tests {
 
         let expected_stats: LengthStats = LengthStats {
             total_bases: 1000,
+            total_gigabases: 0.000001,
             total_sequences: 100,
             mean_length: 10.0,
+            geometric_mean_length: 10.0_f64.ln().exp(),
+            harmonic_mean_length: 10.0,
+            stddev_length: 0.0,
+            sem_length: 0.0,
+            mad_length: 0.0,
+            fraction_below_mean: 0.0,
+            gini_coefficient: 0.0,
+            longest_seq_base_fraction: 0.01,
             median_length: 10.0,
             n10: 10,
             n25: 10,
@@ -326,4 +1898,699 @@ mod tests {
         let actual_stats: LengthStats = compute_length_stats(&seq_lens);
         assert_eq!(expected_stats, actual_stats);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_compute_detailed_histogram() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (1, 2),
+            (2, 1),
+            (4, 1)
+        ].iter().cloned().collect();
+
+        let detailed = compute_detailed_histogram(&seq_lens);
+        assert_eq!(detailed.len(), 3);
+        assert_eq!(detailed[&1], HistogramEntry { count: 2, seq_fraction: 0.5, base_fraction: 0.25 });
+        assert_eq!(detailed[&2], HistogramEntry { count: 1, seq_fraction: 0.25, base_fraction: 0.25 });
+        assert_eq!(detailed[&4], HistogramEntry { count: 1, seq_fraction: 0.25, base_fraction: 0.5 });
+
+        //only zero-length sequences should not divide by zero
+        let seq_lens: BTreeMap<usize, u64> = [
+            (0, 2)
+        ].iter().cloned().collect();
+        let detailed = compute_detailed_histogram(&seq_lens);
+        assert_eq!(detailed[&0], HistogramEntry { count: 2, seq_fraction: 1.0, base_fraction: 0.0 });
+    }
+
+    #[test]
+    fn test_exclude_lengths() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (1, 1),
+            (35, 10),
+            (36, 5),
+            (100, 2)
+        ].iter().cloned().collect();
+
+        let filtered = exclude_lengths(&seq_lens, &[35, 36]);
+        let expected: BTreeMap<usize, u64> = [(1, 1), (100, 2)].iter().cloned().collect();
+        assert_eq!(filtered, expected);
+
+        //excluding absent lengths or nothing at all is a no-op
+        assert_eq!(exclude_lengths(&seq_lens, &[2, 50]), seq_lens);
+        assert_eq!(exclude_lengths(&seq_lens, &[]), seq_lens);
+    }
+
+    #[test]
+    fn test_remove_zero_lengths() {
+        let mut seq_lens: BTreeMap<usize, u64> = [
+            (0, 4),
+            (3, 2),
+            (7, 1)
+        ].iter().cloned().collect();
+        assert_eq!(remove_zero_lengths(&mut seq_lens), 4);
+
+        //the zero-length sequences would otherwise be counted in the totals and pull the median to 0
+        let stats: LengthStats = compute_length_stats(&seq_lens);
+        assert_eq!(stats.total_sequences, 3);
+        assert_eq!(stats.median_length, 3.0);
+        assert_eq!(stats.total_bases, 13);
+    }
+
+    #[test]
+    fn test_total_gigabases() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (15000, 2_000_000)
+        ].iter().cloned().collect();
+        let stats: LengthStats = compute_length_stats(&seq_lens);
+        assert_eq!(stats.total_bases, 30_000_000_000);
+        assert_eq!(stats.total_gigabases, 30.0);
+    }
+
+    #[test]
+    fn test_iter_fields() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (10, 100)
+        ].iter().cloned().collect();
+        let stats: LengthStats = compute_length_stats(&seq_lens);
+
+        let fields: Vec<(&'static str, f64)> = stats.iter_fields().collect();
+        assert_eq!(fields.len(), 18);
+        assert_eq!(fields[0], ("total_bases", 1000.0));
+        assert_eq!(fields[3], ("mean_length", 10.0));
+        assert!(fields.contains(&("n50", 10.0)));
+
+        //make sure every serialized field is represented
+        let json_value = serde_json::to_value(&stats).unwrap();
+        let json_fields = json_value.as_object().unwrap();
+        assert_eq!(json_fields.len(), fields.len());
+        for (name, _value) in fields.iter() {
+            assert!(json_fields.contains_key(*name));
+        }
+    }
+
+    #[test]
+    fn test_ng_stats() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (1, 10),
+            (10, 2),
+            (100, 1)
+        ].iter().cloned().collect();
+
+        //against the observed total, NG matches N
+        let stats: LengthStats = compute_length_stats(&seq_lens);
+        let ng_stats: NgStats = compute_ng_stats(&seq_lens, stats.total_bases);
+        assert_eq!(ng_stats.ng50, Some(stats.n50));
+        assert_eq!(ng_stats.ng90, Some(stats.n90));
+
+        //a much larger genome is never covered
+        let ng_stats: NgStats = compute_ng_stats(&seq_lens, 1000);
+        assert_eq!(ng_stats, NgStats {
+            genome_size: 1000,
+            expected_coverage: Some(stats.total_bases as f64 / 1000.0),
+            ng10: Some(100),
+            ng25: None,
+            ng50: None,
+            ng75: None,
+            ng90: None
+        });
+
+        //the coverage scales with the genome size, and a zero genome size has none
+        let mut ng_stats: NgStats = compute_ng_stats(&seq_lens, 3);
+        assert_eq!(ng_stats.expected_coverage, Some(stats.total_bases as f64 / 3.0));
+        ng_stats.round_floats(2);
+        assert_eq!(ng_stats.expected_coverage, Some(round_to_decimals(stats.total_bases as f64 / 3.0, 2)));
+        assert_eq!(compute_ng_stats(&seq_lens, 0).expected_coverage, None);
+    }
+
+    #[test]
+    fn test_alternate_means() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (0, 5),
+            (2, 1),
+            (8, 1)
+        ].iter().cloned().collect();
+
+        //zero-length sequences are ignored by both
+        assert!((compute_geometric_mean_length(&seq_lens) - 4.0).abs() < 1e-9);
+        assert_eq!(compute_harmonic_mean_length(&seq_lens), 3.2);
+
+        //empty and all-zero inputs have nothing to average
+        let empty_lens: BTreeMap<usize, u64> = BTreeMap::new();
+        assert_eq!(compute_geometric_mean_length(&empty_lens), 0.0);
+        assert_eq!(compute_harmonic_mean_length(&empty_lens), 0.0);
+        let zero_lens: BTreeMap<usize, u64> = [(0, 3)].iter().cloned().collect();
+        assert_eq!(compute_geometric_mean_length(&zero_lens), 0.0);
+        assert_eq!(compute_harmonic_mean_length(&zero_lens), 0.0);
+    }
+
+    #[test]
+    fn test_round_floats() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (1, 1),
+            (2, 2)
+        ].iter().cloned().collect();
+        let mut stats: LengthStats = compute_length_stats(&seq_lens);
+        assert_eq!(stats.mean_length, 5.0 / 3.0);
+        stats.round_floats(2);
+        assert_eq!(stats.mean_length, 1.67);
+        assert_eq!(stats.total_gigabases, 0.0);
+        assert_eq!(stats.median_length, 2.0);
+        assert_eq!(stats.total_bases, 5);
+
+        let mut detailed = compute_detailed_histogram(&seq_lens);
+        detailed.get_mut(&1).unwrap().round_floats(3);
+        assert_eq!(detailed[&1].seq_fraction, 0.333);
+        assert_eq!(detailed[&1].base_fraction, 0.2);
+
+        //absurd precision is a no-op instead of overflowing to infinity
+        assert_eq!(round_to_decimals(1.0 / 3.0, 400), 1.0 / 3.0);
+    }
+
+    #[test]
+    fn test_nx_curve() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (1, 10),
+            (10, 2),
+            (100, 1)
+        ].iter().cloned().collect();
+        let stats: LengthStats = compute_length_stats(&seq_lens);
+        let nx_curve = compute_nx_curve(&seq_lens, stats.total_bases);
+        assert_eq!(nx_curve, vec![(100, 100.0 / 130.0), (10, 120.0 / 130.0), (1, 1.0)]);
+
+        //the N50 is the first point covering half of the bases
+        let n50 = nx_curve.iter().find(|(_seq_len, fraction)| *fraction >= 0.5).unwrap().0;
+        assert_eq!(n50, stats.n50);
+
+        let empty_lens: BTreeMap<usize, u64> = BTreeMap::new();
+        assert!(compute_nx_curve(&empty_lens, 0).is_empty());
+    }
+
+    #[test]
+    fn test_base_histogram() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (0, 4),
+            (1, 10),
+            (50000, 2)
+        ].iter().cloned().collect();
+        let base_histogram = compute_base_histogram(&seq_lens);
+        let expected: BTreeMap<usize, u64> = [(0, 0), (1, 10), (50000, 100000)].iter().cloned().collect();
+        assert_eq!(base_histogram, expected);
+
+        //the bases should add up to the total
+        let (total_bases, _total_seqs) = compute_total_counts(&seq_lens);
+        assert_eq!(base_histogram.values().sum::<u64>(), total_bases);
+    }
+
+    #[test]
+    fn test_stddev_sem() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (2, 2),
+            (4, 1),
+            (8, 1)
+        ].iter().cloned().collect();
+        let stats: LengthStats = compute_length_stats(&seq_lens);
+
+        //mean is 4, squared deviations sum to 4 + 4 + 0 + 16 = 24
+        assert_eq!(stats.mean_length, 4.0);
+        assert_eq!(stats.stddev_length, 8.0_f64.sqrt());
+        assert_eq!(stats.sem_length, 8.0_f64.sqrt() / 2.0);
+
+        //a single sequence has no spread, and an empty input must not divide by zero
+        let single_lens: BTreeMap<usize, u64> = [(5, 1)].iter().cloned().collect();
+        let single_stats: LengthStats = compute_length_stats(&single_lens);
+        assert_eq!(single_stats.stddev_length, 0.0);
+        assert_eq!(single_stats.sem_length, 0.0);
+        let empty_lens: BTreeMap<usize, u64> = BTreeMap::new();
+        let empty_stats: LengthStats = compute_length_stats(&empty_lens);
+        assert_eq!(empty_stats.stddev_length, 0.0);
+        assert_eq!(empty_stats.sem_length, 0.0);
+    }
+
+    #[test]
+    fn test_compute_mad() {
+        //median is 3, absolute deviations sum to 2 + 0 + 0 + 97 = 99
+        let seq_lens: BTreeMap<usize, u64> = [
+            (1, 1),
+            (3, 2),
+            (100, 1)
+        ].iter().cloned().collect();
+        let stats: LengthStats = compute_length_stats(&seq_lens);
+        assert_eq!(stats.median_length, 3.0);
+        assert_eq!(stats.mad_length, 99.0 / 4.0);
+        assert_eq!(compute_mad(&seq_lens, stats.median_length), stats.mad_length);
+
+        //identical lengths have no deviation, and an empty input must not divide by zero
+        let same_lens: BTreeMap<usize, u64> = [(5, 10)].iter().cloned().collect();
+        assert_eq!(compute_mad(&same_lens, 5.0), 0.0);
+        assert_eq!(compute_mad(&BTreeMap::new(), 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_filter_length_range() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (1, 1),
+            (50, 3),
+            (100, 2),
+            (100000, 1)
+        ].iter().cloned().collect();
+
+        //both ends are inclusive
+        let filtered = filter_length_range(&seq_lens, &LengthRange { min: 50, max: 100 });
+        let expected: BTreeMap<usize, u64> = [(50, 3), (100, 2)].iter().cloned().collect();
+        assert_eq!(filtered, expected);
+
+        //a window with nothing inside is empty
+        assert!(filter_length_range(&seq_lens, &LengthRange { min: 2, max: 49 }).is_empty());
+    }
+
+    #[test]
+    fn test_compute_n_score_above() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (1, 10),
+            (50, 3),
+            (100, 2),
+            (1000, 1),
+            (100000, 1)
+        ].iter().cloned().collect();
+
+        //should always match filtering first and then computing the N-score
+        for min_length in [0, 1, 2, 50, 99, 100, 1000, 100000] {
+            let filtered = filter_length_range(&seq_lens, &LengthRange { min: min_length, max: usize::MAX });
+            let (total_bases, _total_seqs) = compute_total_counts(&filtered);
+            for target in [10, 50, 90, 99] {
+                assert_eq!(compute_n_score_above(&seq_lens, target, min_length), compute_n_score(&filtered, total_bases, target), "{min_length} {target}");
+            }
+        }
+
+        //nothing long enough
+        assert_eq!(compute_n_score_above(&seq_lens, 50, 100001), 0);
+        assert_eq!(compute_n_score_above(&BTreeMap::new(), 50, 0), 0);
+    }
+
+    #[test]
+    fn test_parse_length_range() {
+        assert_eq!("10:20".parse::<LengthRange>(), Ok(LengthRange { min: 10, max: 20 }));
+        assert_eq!("7:7".parse::<LengthRange>(), Ok(LengthRange { min: 7, max: 7 }));
+        assert!("20:10".parse::<LengthRange>().is_err());
+        assert!("10".parse::<LengthRange>().is_err());
+        assert!("a:10".parse::<LengthRange>().is_err());
+        assert!("10:-1".parse::<LengthRange>().is_err());
+    }
+
+    #[test]
+    fn test_expand_lengths() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (3, 2),
+            (1, 1),
+            (7, 3)
+        ].iter().cloned().collect();
+        assert_eq!(expand_lengths(&seq_lens, DEFAULT_EXPAND_LENGTHS_CAP).unwrap(), vec![1, 3, 3, 7, 7, 7]);
+
+        //the cap is inclusive
+        assert!(expand_lengths(&seq_lens, 6).is_ok());
+        assert!(expand_lengths(&seq_lens, 5).is_err());
+
+        let empty_lens: BTreeMap<usize, u64> = BTreeMap::new();
+        assert!(expand_lengths(&empty_lens, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_fraction_below() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (1, 6),
+            (10, 3),
+            (100, 1)
+        ].iter().cloned().collect();
+        let (_total_bases, total_seqs) = compute_total_counts(&seq_lens);
+
+        //the threshold itself is not below
+        assert_eq!(compute_fraction_below(&seq_lens, total_seqs, 10.0), 0.6);
+        assert_eq!(compute_fraction_below(&seq_lens, total_seqs, 10.5), 0.9);
+        assert_eq!(compute_fraction_below(&seq_lens, total_seqs, 0.5), 0.0);
+        assert_eq!(compute_fraction_below(&seq_lens, total_seqs, 1000.0), 1.0);
+
+        //a long tail pulls the mean (13.6) up, so most reads are below it
+        let stats: LengthStats = compute_length_stats(&seq_lens);
+        assert_eq!(stats.fraction_below_mean, 0.9);
+
+        let empty_lens: BTreeMap<usize, u64> = BTreeMap::new();
+        assert_eq!(compute_fraction_below(&empty_lens, 0, 1.0), 0.0);
+        assert_eq!(compute_length_stats(&empty_lens).fraction_below_mean, 0.0);
+    }
+
+    #[test]
+    fn test_basic_stats() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (3, 2),
+            (1, 1),
+            (7, 3)
+        ].iter().cloned().collect();
+        let basic_stats: BasicLengthStats = compute_basic_stats(&seq_lens);
+        let full_stats: LengthStats = compute_length_stats(&seq_lens);
+
+        //the shared metrics must agree with the full calculation
+        assert_eq!(basic_stats.total_bases, full_stats.total_bases);
+        assert_eq!(basic_stats.total_gigabases, full_stats.total_gigabases);
+        assert_eq!(basic_stats.total_sequences, full_stats.total_sequences);
+        assert_eq!(basic_stats.mean_length, full_stats.mean_length);
+        assert_eq!(basic_stats.median_length, full_stats.median_length);
+        assert_eq!(basic_stats.min_length, 1);
+        assert_eq!(basic_stats.max_length, 7);
+
+        let empty_lens: BTreeMap<usize, u64> = BTreeMap::new();
+        let empty_stats: BasicLengthStats = compute_basic_stats(&empty_lens);
+        assert_eq!(empty_stats.total_sequences, 0);
+        assert_eq!(empty_stats.min_length, 0);
+        assert_eq!(empty_stats.max_length, 0);
+    }
+
+    #[test]
+    fn test_approximate_stats() {
+        use crate::tdigest::DEFAULT_COMPRESSION;
+
+        //lengths 1-1000, one of each; the approximate percentiles should be close to the exact ones
+        let seq_lens: BTreeMap<usize, u64> = (1..=1000).map(|length| (length, 1)).collect();
+        let mut length_digest: TDigest = TDigest::new(DEFAULT_COMPRESSION);
+        seq_lens.keys().for_each(|&length| length_digest.add(length as f64, 1));
+        let full_stats: LengthStats = compute_length_stats(&seq_lens);
+        let mut approximate_stats: ApproximateLengthStats = compute_approximate_stats(&mut length_digest, full_stats.total_bases);
+        assert_eq!(approximate_stats.total_sequences, full_stats.total_sequences);
+        assert_eq!(approximate_stats.mean_length, full_stats.mean_length);
+        assert_eq!(approximate_stats.min_length, 1);
+        assert_eq!(approximate_stats.max_length, 1000);
+        assert!((approximate_stats.median_length - full_stats.median_length).abs() < 10.0);
+        assert!((approximate_stats.p10_length - 100.0).abs() < 10.0);
+        assert!((approximate_stats.p90_length - 900.0).abs() < 10.0);
+
+        approximate_stats.round_floats(0);
+        assert_eq!(approximate_stats.p25_length.fract(), 0.0);
+
+        let mut empty_digest: TDigest = TDigest::new(DEFAULT_COMPRESSION);
+        let empty_stats: ApproximateLengthStats = compute_approximate_stats(&mut empty_digest, 0);
+        assert_eq!(empty_stats.total_sequences, 0);
+        assert_eq!(empty_stats.max_length, 0);
+        assert_eq!(empty_stats.median_length, 0.0);
+    }
+
+    #[test]
+    fn test_compare_length_stats() {
+        let baseline_lens: BTreeMap<usize, u64> = [(10, 4)].iter().cloned().collect();
+        let comparison_lens: BTreeMap<usize, u64> = [(10, 2), (20, 1)].iter().cloned().collect();
+        let baseline: LengthStats = compute_length_stats(&baseline_lens);
+        let comparisons = compare_length_stats(&baseline, &compute_length_stats(&comparison_lens));
+        assert_eq!(comparisons.len(), baseline.iter_fields().count());
+
+        let mean_change = comparisons.iter().find(|c| c.field == "mean_length").unwrap();
+        assert_eq!(mean_change.baseline, 10.0);
+        assert_eq!(mean_change.comparison, 40.0 / 3.0);
+        assert_eq!(mean_change.absolute_change, 40.0 / 3.0 - 10.0);
+
+        //the baseline has no spread, so there is no percent change
+        let stddev_change = comparisons.iter().find(|c| c.field == "stddev_length").unwrap();
+        assert_eq!(stddev_change.baseline, 0.0);
+        assert!(stddev_change.absolute_change > 0.0);
+        assert_eq!(stddev_change.percent_change, None);
+
+        //comparing against itself is all zero
+        for comparison in compare_length_stats(&baseline, &baseline) {
+            assert_eq!(comparison.absolute_change, 0.0);
+            assert!(comparison.percent_change.is_none_or(|percent| percent == 0.0));
+        }
+    }
+
+    #[test]
+    fn test_compute_length_percentile() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (1, 6),
+            (10, 3),
+            (100, 1)
+        ].iter().cloned().collect();
+        let (_total_bases, total_seqs) = compute_total_counts(&seq_lens);
+
+        //the step mode agrees with the median and always returns an observed length
+        assert_eq!(compute_length_percentile(&seq_lens, total_seqs, 50.0, false), compute_median_length(&seq_lens, total_seqs));
+        assert_eq!(compute_length_percentile(&seq_lens, total_seqs, 0.0, false), 1.0);
+        assert_eq!(compute_length_percentile(&seq_lens, total_seqs, 65.0, false), 10.0);
+        assert_eq!(compute_length_percentile(&seq_lens, total_seqs, 100.0, false), 100.0);
+
+        //these match numpy.percentile([1]*6 + [10]*3 + [100], p)
+        assert_eq!(compute_length_percentile(&seq_lens, total_seqs, 0.0, true), 1.0);
+        assert_eq!(compute_length_percentile(&seq_lens, total_seqs, 50.0, true), 1.0);
+        assert!((compute_length_percentile(&seq_lens, total_seqs, 60.0, true) - 4.6).abs() < 1e-9);
+        assert!((compute_length_percentile(&seq_lens, total_seqs, 95.0, true) - 59.5).abs() < 1e-9);
+        assert_eq!(compute_length_percentile(&seq_lens, total_seqs, 100.0, true), 100.0);
+
+        //a single sequence and empty inputs
+        let single_lens: BTreeMap<usize, u64> = [(7, 1)].iter().cloned().collect();
+        assert_eq!(compute_length_percentile(&single_lens, 1, 30.0, true), 7.0);
+        let empty_lens: BTreeMap<usize, u64> = BTreeMap::new();
+        assert_eq!(compute_length_percentile(&empty_lens, 0, 50.0, true), 0.0);
+        assert_eq!(compute_length_percentile(&empty_lens, 0, 50.0, false), 0.0);
+    }
+
+    #[test]
+    fn test_compute_gini() {
+        //by hand: ranks 1-4 for lengths 0, 0, 0, 10 gives 2 * 40 / (4 * 10) - 5 / 4
+        let skewed_lens: BTreeMap<usize, u64> = [
+            (0, 3),
+            (10, 1)
+        ].iter().cloned().collect();
+        assert_eq!(compute_gini(&skewed_lens), 0.75);
+
+        //by hand: ranks 1-2 for length 1 and 3-5 for length 3, sum(i * x_i) = 3 + 36 = 39, giving 2 * 39 / (5 * 11) - 6 / 5
+        let grouped_lens: BTreeMap<usize, u64> = [
+            (1, 2),
+            (3, 3)
+        ].iter().cloned().collect();
+        assert!((compute_gini(&grouped_lens) - (78.0 / 55.0 - 1.2)).abs() < 1e-12);
+
+        //identical lengths, a single sequence, and empty inputs are all perfectly even
+        let same_lens: BTreeMap<usize, u64> = [(7, 100)].iter().cloned().collect();
+        assert!(compute_gini(&same_lens).abs() < 1e-12);
+        let single_lens: BTreeMap<usize, u64> = [(7, 1)].iter().cloned().collect();
+        assert_eq!(compute_gini(&single_lens), 0.0);
+        let empty_lens: BTreeMap<usize, u64> = BTreeMap::new();
+        assert_eq!(compute_gini(&empty_lens), 0.0);
+        assert_eq!(compute_length_stats(&empty_lens).gini_coefficient, 0.0);
+    }
+
+    #[test]
+    fn test_compute_largest_fraction() {
+        //a single contig holding half of the bases
+        let length_counts: BTreeMap<usize, u64> = [(10, 5), (50, 1)].iter().cloned().collect();
+        assert_eq!(compute_largest_fraction(&length_counts, 100), 0.5);
+
+        //ties at the longest length only count one of the sequences
+        let tied_counts: BTreeMap<usize, u64> = [(10, 1), (20, 2)].iter().cloned().collect();
+        assert_eq!(compute_largest_fraction(&tied_counts, 50), 0.4);
+        assert_eq!(compute_length_stats(&tied_counts).longest_seq_base_fraction, 0.4);
+
+        //empty inputs (or only zero-length sequences) have no bases to hold
+        assert_eq!(compute_largest_fraction(&BTreeMap::new(), 0), 0.0);
+        let zero_counts: BTreeMap<usize, u64> = [(0, 3)].iter().cloned().collect();
+        assert_eq!(compute_largest_fraction(&zero_counts, 0), 0.0);
+    }
+
+    #[test]
+    fn test_compute_length_for_base_fraction() {
+        //ordered lengths 100, 90, ..., 10 for a total of 550 bases
+        let length_counts: BTreeMap<usize, u64> = (1..=10).map(|i| (i * 10, 1)).collect();
+        let (total_bases, _total_seqs) = compute_total_counts(&length_counts);
+
+        //the integer percentages should always match the N-scores
+        for target in [10, 25, 50, 75, 90] {
+            assert_eq!(
+                compute_length_for_base_fraction(&length_counts, total_bases, target as f64 / 100.0),
+                compute_n_score(&length_counts, total_bases, target)
+            );
+        }
+
+        //100 + 90 = 190 is exactly 190 / 550 of the bases, so anything past that needs the next length
+        assert_eq!(compute_length_for_base_fraction(&length_counts, total_bases, 190.0 / 550.0), 90);
+        assert_eq!(compute_length_for_base_fraction(&length_counts, total_bases, 191.0 / 550.0), 80);
+        assert_eq!(compute_length_for_base_fraction(&length_counts, total_bases, 0.001), 100);
+        assert_eq!(compute_length_for_base_fraction(&length_counts, total_bases, 1.0), 10);
+
+        let empty_counts: BTreeMap<usize, u64> = BTreeMap::new();
+        assert_eq!(compute_length_for_base_fraction(&empty_counts, 0, 0.5), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_length_for_base_fraction_range() {
+        let length_counts: BTreeMap<usize, u64> = [(10, 1)].iter().cloned().collect();
+        compute_length_for_base_fraction(&length_counts, 10, 0.0);
+    }
+
+    #[test]
+    fn test_compute_top_fraction_stats() {
+        //1 to 10, once each
+        let length_counts: BTreeMap<usize, u64> = (1..=10).map(|seq_len| (seq_len, 1)).collect();
+        let top_stats = compute_top_fraction_stats(&length_counts, 0.1);
+        assert_eq!(top_stats, TopFractionStats { fraction: 0.1, total_sequences: 1, total_bases: 10, base_fraction: 10.0 / 55.0, mean_length: 10.0, min_length: 10 });
+
+        //a quarter of 10 rounds up to 3 sequences
+        let top_stats = compute_top_fraction_stats(&length_counts, 0.25);
+        assert_eq!((top_stats.total_sequences, top_stats.total_bases, top_stats.mean_length, top_stats.min_length), (3, 27, 9.0, 8));
+
+        //everything is the same as the overall stats
+        let top_stats = compute_top_fraction_stats(&length_counts, 1.0);
+        assert_eq!((top_stats.total_sequences, top_stats.total_bases, top_stats.base_fraction, top_stats.min_length), (10, 55, 1.0, 1));
+
+        //empty inputs have nothing to include
+        let top_stats = compute_top_fraction_stats(&BTreeMap::new(), 0.5);
+        assert_eq!(top_stats, TopFractionStats { fraction: 0.5, total_sequences: 0, total_bases: 0, base_fraction: 0.0, mean_length: 0.0, min_length: 0 });
+    }
+
+    #[test]
+    fn test_log_histogram() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (0, 4),
+            (1, 10),
+            (9, 1),
+            (10, 2),
+            (99, 3),
+            (100, 1),
+            (50000, 2)
+        ].iter().cloned().collect();
+        let log_histogram = compute_log_histogram(&seq_lens, 10);
+        let expected: BTreeMap<usize, u64> = [(0, 4), (1, 11), (10, 5), (100, 1), (10000, 2)].iter().cloned().collect();
+        assert_eq!(log_histogram, expected);
+
+        //exact powers start their own bin, and nothing is lost
+        let log_histogram = compute_log_histogram(&seq_lens, 2);
+        let expected: BTreeMap<usize, u64> = [(0, 4), (1, 10), (8, 3), (64, 4), (32768, 2)].iter().cloned().collect();
+        assert_eq!(log_histogram, expected);
+        assert_eq!(log_histogram.values().sum::<u64>(), seq_lens.values().sum::<u64>());
+
+        //the largest lengths do not overflow the bin bounds
+        let huge_lens: BTreeMap<usize, u64> = [(usize::MAX, 1)].iter().cloned().collect();
+        assert_eq!(compute_log_histogram(&huge_lens, 2), [(1 << (usize::BITS - 1), 1)].iter().cloned().collect());
+        assert!(compute_log_histogram(&BTreeMap::new(), 2).is_empty());
+    }
+
+    #[test]
+    fn test_selected_stats() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (0, 1),
+            (5, 10),
+            (10, 3),
+            (1000, 2)
+        ].iter().cloned().collect();
+
+        //selecting everything matches the full stats exactly
+        for interpolate in [false, true] {
+            let all_stats: SelectedStats = compute_selected_stats(&seq_lens, &LENGTH_STAT_NAMES, interpolate).unwrap();
+            let full_stats: LengthStats = compute_length_stats_with_interpolation(&seq_lens, interpolate);
+            let full_value = serde_json::to_value(&full_stats).unwrap();
+            assert_eq!(serde_json::to_value(&all_stats).unwrap(), full_value);
+        }
+        assert_eq!(LENGTH_STAT_NAMES.to_vec(), compute_length_stats(&seq_lens).iter_fields().map(|(name, _value)| name).collect::<Vec<&str>>());
+
+        //a subset only includes what was asked for, with short names resolved
+        let mut selected: SelectedStats = compute_selected_stats(&seq_lens, &["mad", "n90", "stddev", "gini_coefficient"], false).unwrap();
+        let keys: Vec<&str> = selected.values.keys().map(|key| key.as_str()).collect();
+        assert_eq!(keys, vec!["gini_coefficient", "mad_length", "n90", "stddev_length"]);
+        assert_eq!(selected.values["n90"], 1000);
+        selected.round_floats(1);
+        assert_eq!(selected.values["stddev_length"], 339.6);
+        assert_eq!(selected.values["n90"], 1000);
+
+        //unknown names are an error, and an empty selection is empty
+        let error = compute_selected_stats(&seq_lens, &["n50", "skewness"], false).unwrap_err();
+        assert!(error.to_string().contains("skewness"));
+        assert!(compute_selected_stats::<&str>(&seq_lens, &[], false).unwrap().values.is_empty());
+    }
+
+    #[test]
+    fn test_detect_peaks() {
+        //a small adapter-dimer peak, then a main peak with a noisy shoulder
+        let seq_lens: BTreeMap<usize, u64> = [
+            (60, 5),
+            (61, 12),
+            (62, 12),
+            (63, 4),
+            (500, 10),
+            (501, 60),
+            (502, 55),
+            (503, 58),
+            (504, 20)
+        ].iter().cloned().collect();
+        assert_eq!(detect_peaks(&seq_lens, 0), vec![(61, 12), (501, 60), (503, 58)]);
+        assert_eq!(detect_peaks(&seq_lens, 5), vec![(61, 12), (501, 60)]);
+        assert_eq!(detect_peaks(&seq_lens, 13), vec![(501, 60)]);
+        assert_eq!(detect_peaks(&seq_lens, 61), vec![]);
+
+        //the histogram drops to zero beyond its ends, so a peak at either end (or a single length) is still found
+        let edge_lens: BTreeMap<usize, u64> = [(0, 9), (1, 3), (2, 7)].iter().cloned().collect();
+        assert_eq!(detect_peaks(&edge_lens, 0), vec![(0, 9), (2, 7)]);
+        assert_eq!(detect_peaks(&edge_lens, 5), vec![(0, 9)]);
+        assert_eq!(detect_peaks(&[(100, 1)].iter().cloned().collect(), 1), vec![(100, 1)]);
+
+        //a gap in the lengths separates two peaks just as a dip would
+        let gapped_lens: BTreeMap<usize, u64> = [(10, 4), (12, 4)].iter().cloned().collect();
+        assert_eq!(detect_peaks(&gapped_lens, 4), vec![(10, 4), (12, 4)]);
+        assert!(detect_peaks(&BTreeMap::new(), 0).is_empty());
+    }
+
+    #[test]
+    fn test_top_n_lengths() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (0, 2),
+            (7, 1),
+            (20, 1000000000),
+            (300, 2)
+        ].iter().cloned().collect();
+        assert_eq!(top_n_lengths(&seq_lens, 1), vec![300]);
+        assert_eq!(top_n_lengths(&seq_lens, 4), vec![300, 300, 20, 20]);
+
+        //huge counts are not expanded past n, and asking for more than there are is fine
+        assert_eq!(top_n_lengths(&seq_lens, 5).len(), 5);
+        let small_lens: BTreeMap<usize, u64> = [(0, 1), (7, 2)].iter().cloned().collect();
+        assert_eq!(top_n_lengths(&small_lens, 10), vec![7, 7, 0]);
+        assert!(top_n_lengths(&small_lens, 0).is_empty());
+        assert!(top_n_lengths(&BTreeMap::new(), 3).is_empty());
+    }
+
+    #[test]
+    fn test_sequences_to_cover_fraction() {
+        let seq_lens: BTreeMap<usize, u64> = [
+            (0, 2),
+            (1, 4),
+            (2, 2),
+            (10, 1)
+        ].iter().cloned().collect();
+        let (total_bases, _total_seqs) = compute_total_counts(&seq_lens);
+        assert_eq!(total_bases, 18);
+        assert_eq!(sequences_to_cover_fraction(&seq_lens, total_bases, 0.5), (1, 10));
+        assert_eq!(sequences_to_cover_fraction(&seq_lens, total_bases, 0.6), (2, 12));
+        assert_eq!(sequences_to_cover_fraction(&seq_lens, total_bases, 0.9), (6, 17));
+
+        //the count at 0.5 is always the L50, and all bases never include the zero-length sequences
+        let n50: usize = compute_n_score(&seq_lens, total_bases, 50);
+        assert_eq!(sequences_to_cover_fraction(&seq_lens, total_bases, 0.5).0, seq_lens.range(n50..).map(|(_l, c)| c).sum::<u64>());
+        assert_eq!(sequences_to_cover_fraction(&seq_lens, total_bases, 1.0), (7, 18));
+        assert_eq!(sequences_to_cover_fraction(&BTreeMap::new(), 0, 0.5), (0, 0));
+    }
+
+    #[test]
+    fn test_drop_longest_lengths() {
+        let mut seq_lens: BTreeMap<usize, u64> = [
+            (2, 5),
+            (7, 2),
+            (100, 1)
+        ].iter().cloned().collect();
+        assert!(drop_longest_lengths(&mut seq_lens, 0).is_empty());
+        assert_eq!(drop_longest_lengths(&mut seq_lens, 2), vec![100, 7]);
+        let expected: BTreeMap<usize, u64> = [(2, 5), (7, 1)].iter().cloned().collect();
+        assert_eq!(seq_lens, expected);
+
+        //the stats only change by what was dropped
+        let summary_stats: LengthStats = compute_length_stats(&seq_lens);
+        assert_eq!(summary_stats.total_sequences, 6);
+        assert_eq!(summary_stats.total_bases, 17);
+
+        //dropping more than there are empties the counts
+        assert_eq!(drop_longest_lengths(&mut seq_lens, 10), vec![7, 2, 2, 2, 2, 2]);
+        assert!(seq_lens.is_empty());
+    }
+}
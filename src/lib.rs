@@ -15,10 +15,56 @@ let length_counts: BTreeMap<usize, u64> = gather_fastx_stats(&filename).unwrap()
 //compute the stats
 let length_metrics: LengthStats = compute_length_stats(&length_counts);
 ```
+
+Loading options (such as reading only the first records, sampling, or length filters) are collected in a `LoaderConfig`:
+```rust
+use std::collections::BTreeMap;
+use fastleng::loader::{gather_stats, LoaderConfig};
+
+let config = LoaderConfig::new().head(5).min_length(10);
+let length_counts: BTreeMap<usize, u64> = gather_stats("./test_data/long_strings.fa", &config).unwrap();
+```
+
+For long-running callers that add files (or individual lengths) over time, a `LengthAccumulator` keeps the counts between calls:
+```rust
+use fastleng::accumulator::LengthAccumulator;
+use fastleng::length_stats::LengthStats;
+
+let mut accumulator = LengthAccumulator::new();
+accumulator.add_file("./test_data/long_strings.fa").unwrap();
+accumulator.add_file("./test_data/five_strings.sam").unwrap();
+let length_metrics: LengthStats = accumulator.snapshot();
+```
+
+## Logging
+The library only emits messages (e.g. loading progress and warnings) through the [`log`](https://docs.rs/log) facade and never initializes a logger itself.
+Messages are discarded unless the embedding application installs a logger of its choice, such as `env_logger`, which is what the `fastleng` binary uses.
 */
+/// Contains a running accumulator of length counts that files and lengths can be added to over time
+pub mod accumulator;
 /// Contains the logic for loading length information from an unaligned BAM/SAM file
 pub mod bam_loader;
 /// Contains the logic for loading length information from a fastx file
 pub mod fastx_loader;
+/// Contains a BGZF reader that decompresses blocks on several threads
+#[cfg(feature = "parallel-bgzf")]
+pub mod bgzf;
+/// Contains the logic for detecting the format of an input file from its contents
+pub mod format;
+/// Contains the serialization wrapper for writing JSON outputs with camelCase field names
+pub mod json_case;
 /// Contains the logic for calculating the summary statistics from the counts
-pub mod length_stats;
\ No newline at end of file
+pub mod length_stats;
+/// Contains the shared loader configuration and the format-dispatching entry point for loading lengths
+pub mod loader;
+/// Contains the logic for writing (optionally gzip compressed) output files
+pub mod output;
+/// Contains the seeded random number generation used for approximate sampling
+pub mod sampling;
+/// Contains the logic for inserting the stats of a run into a SQLite database
+#[cfg(feature = "sqlite")]
+pub mod sqlite_output;
+/// Contains the logic for loading length information from the FASTX members of a tar archive
+pub mod tar_loader;
+/// Contains a t-digest sketch for estimating percentiles in a single streaming pass
+pub mod tdigest;
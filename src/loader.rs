@@ -0,0 +1,777 @@
+
+use log::{info, warn};
+use regex::bytes::Regex;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::bam_loader::BamLoadOptions;
+use crate::fastx_loader::{stream_multifastx_lengths, stream_multifastx_lengths_with_formats, stream_multifastx_lengths_with_summary};
+use crate::format::{FormatDetection, InputFormat};
+use crate::length_stats::merge_length_counts;
+use crate::sampling::SamplingConfig;
+use crate::tdigest::TDigest;
+
+/// The default number of records between progress messages while loading
+pub const DEFAULT_PROGRESS_INTERVAL: usize = 1_000_000;
+
+/// The default number of distinct lengths in the length counts above which a memory warning is logged
+pub const DEFAULT_DISTINCT_LENGTH_WARNING: usize = 1_000_000;
+
+/// Set once the distinct length warning has been logged, so loaders that build several sets of counts (e.g. one per file, then merged) only log it once
+static DISTINCT_LENGTH_WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Configuration for loading sequence lengths, shared by all of the supported file formats.
+/// The defaults load every record of every file with no filtering; use the builder methods to change them.
+/// # Examples
+/// ```
+/// use fastleng::loader::LoaderConfig;
+/// let config = LoaderConfig::new()
+///     .head(1000)
+///     .min_length(100)
+///     .include_hardclips(true);
+/// assert_eq!(config.head, Some(1000));
+/// assert_eq!(config.min_length, Some(100));
+/// assert!(config.bam_options.include_hardclips);
+/// ```
+#[derive(Clone, Debug)]
+pub struct LoaderConfig {
+    /// Controls how BAM/SAM record lengths are calculated
+    pub bam_options: BamLoadOptions,
+    /// If set, stop reading each file after this many records
+    pub head: Option<usize>,
+    /// If set, loading fails if any file contains more than this many records; unlike `head`, nothing is silently truncated
+    pub max_records: Option<usize>,
+    /// If set, each record is independently kept with the configured probability
+    pub sampling: Option<SamplingConfig>,
+    /// If set, sequences shorter than this are not counted
+    pub min_length: Option<usize>,
+    /// If set, sequences longer than this are not counted
+    pub max_length: Option<usize>,
+    /// How the format of each file is determined; by default, unambiguous FASTX extensions are trusted and everything else is sniffed
+    pub format_detection: FormatDetection,
+    /// If set, FASTQ records with a mean Phred score below this are not counted; any input without qualities is an error
+    pub min_mean_qual: Option<f64>,
+    /// If true, count the records whose name was already seen in any of the files.
+    /// Memory cost: every unique record name is retained in a `HashSet` until loading finishes.
+    pub check_duplicate_names: bool,
+    /// If true, a FASTQ record whose quality string is a different length from its sequence is counted instead of failing the load.
+    /// Such records are skipped before they reach the parser, so the records after them are still loaded.
+    pub validate_qual_length: bool,
+    /// The number of threads used to decompress each BGZF compressed FASTX file; 1 (the default) decompresses sequentially.
+    /// This is only used when built with the `parallel-bgzf` feature, and plain gzip files are always decompressed sequentially.
+    pub decompression_threads: usize,
+    /// The number of records between progress messages for each file; 0 disables them
+    pub progress_interval: usize,
+    /// A warning is logged once the length counts hold more than this many distinct lengths, since each one costs memory; 0 disables it
+    pub distinct_length_warning: usize
+}
+
+impl Default for LoaderConfig {
+    fn default() -> LoaderConfig {
+        LoaderConfig {
+            bam_options: Default::default(),
+            head: None,
+            max_records: None,
+            sampling: None,
+            min_length: None,
+            max_length: None,
+            format_detection: Default::default(),
+            min_mean_qual: None,
+            check_duplicate_names: false,
+            validate_qual_length: false,
+            decompression_threads: 1,
+            progress_interval: DEFAULT_PROGRESS_INTERVAL,
+            distinct_length_warning: DEFAULT_DISTINCT_LENGTH_WARNING
+        }
+    }
+}
+
+impl LoaderConfig {
+    /// Creates the default configuration, which loads every record with no filtering
+    pub fn new() -> LoaderConfig {
+        Default::default()
+    }
+
+    /// Sets whether hard-clipped bases are added back to BAM/SAM record lengths
+    pub fn include_hardclips(mut self, include_hardclips: bool) -> LoaderConfig {
+        self.bam_options.include_hardclips = include_hardclips;
+        self
+    }
+
+    /// Sets whether only the primary record of each BAM/SAM read name is counted
+    pub fn unique_reads(mut self, unique_reads: bool) -> LoaderConfig {
+        self.bam_options.unique_reads = unique_reads;
+        self
+    }
+
+    /// Only counts the BAM/CRAM records overlapping a samtools-style region, which requires indexed files
+    pub fn region(mut self, region: &str) -> LoaderConfig {
+        self.bam_options.region = Some(region.to_string());
+        self
+    }
+
+    /// Stops reading each file after `head` records
+    pub fn head(mut self, head: usize) -> LoaderConfig {
+        self.head = Some(head);
+        self
+    }
+
+    /// Fails the load if any file has more than `max_records` records
+    pub fn max_records(mut self, max_records: usize) -> LoaderConfig {
+        self.max_records = Some(max_records);
+        self
+    }
+
+    /// Keeps each record with the probability and seed given by `sampling`
+    pub fn sampling(mut self, sampling: SamplingConfig) -> LoaderConfig {
+        self.sampling = Some(sampling);
+        self
+    }
+
+    /// Excludes sequences shorter than `min_length`
+    pub fn min_length(mut self, min_length: usize) -> LoaderConfig {
+        self.min_length = Some(min_length);
+        self
+    }
+
+    /// Excludes sequences longer than `max_length`
+    pub fn max_length(mut self, max_length: usize) -> LoaderConfig {
+        self.max_length = Some(max_length);
+        self
+    }
+
+    /// Sets how the format of each file is determined, e.g. `FormatDetection::Extension` to never read the contents for detection
+    pub fn format_detection(mut self, format_detection: FormatDetection) -> LoaderConfig {
+        self.format_detection = format_detection;
+        self
+    }
+
+    /// Excludes FASTQ records with a mean Phred score below `min_mean_qual`
+    pub fn min_mean_qual(mut self, min_mean_qual: f64) -> LoaderConfig {
+        self.min_mean_qual = Some(min_mean_qual);
+        self
+    }
+
+    /// Sets whether records with a previously seen name are counted as duplicates
+    pub fn check_duplicate_names(mut self, check_duplicate_names: bool) -> LoaderConfig {
+        self.check_duplicate_names = check_duplicate_names;
+        self
+    }
+
+    /// Sets whether FASTQ records with mismatched sequence and quality lengths are counted instead of failing the load
+    pub fn validate_qual_length(mut self, validate_qual_length: bool) -> LoaderConfig {
+        self.validate_qual_length = validate_qual_length;
+        self
+    }
+
+    /// Sets the number of threads used to decompress each BGZF compressed FASTX file, see `decompression_threads`
+    pub fn decompression_threads(mut self, decompression_threads: usize) -> LoaderConfig {
+        self.decompression_threads = decompression_threads;
+        self
+    }
+
+    /// Sets the number of records between progress messages, or 0 to disable them
+    pub fn progress_interval(mut self, progress_interval: usize) -> LoaderConfig {
+        self.progress_interval = progress_interval;
+        self
+    }
+
+    /// Sets the number of distinct lengths that triggers the memory warning, or 0 to disable it
+    pub fn distinct_length_warning(mut self, distinct_length_warning: usize) -> LoaderConfig {
+        self.distinct_length_warning = distinct_length_warning;
+        self
+    }
+
+    /// This will log the memory warning if the number of distinct lengths went from `previous_count` (at or below `distinct_length_warning`) to `current_count` (above it).
+    /// Checking each time the counts grow means the warning is logged as soon as the threshold is crossed, and it is only logged the first time in a run.
+    /// Returns true if the threshold was crossed.
+    /// # Arguments
+    /// * `previous_count` - the number of distinct lengths before the latest addition
+    /// * `current_count` - the number of distinct lengths after it
+    pub fn check_distinct_lengths(&self, previous_count: usize, current_count: usize) -> bool {
+        let threshold: usize = self.distinct_length_warning;
+        if threshold == 0 || previous_count > threshold || current_count <= threshold {
+            return false;
+        }
+        if !DISTINCT_LENGTH_WARNED.swap(true, Ordering::Relaxed) {
+            warn!(
+                "The length counts have more than {} distinct lengths and memory use grows with each one; consider --approximate-percentiles or --count-only for very diverse inputs (see --distinct-length-warning)",
+                threshold
+            );
+        }
+        true
+    }
+
+    /// Adds one sequence of length `seq_len` to `length_counts`, checking `distinct_length_warning` when it is a new length.
+    /// The loaders build their length counts through this (or `merge_counts(...)`), so the warning applies however the inputs are loaded.
+    /// # Examples
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use fastleng::loader::LoaderConfig;
+    /// let config = LoaderConfig::new().distinct_length_warning(1);
+    /// let mut length_counts: BTreeMap<usize, u64> = BTreeMap::new();
+    /// config.count_length(&mut length_counts, 5);
+    /// config.count_length(&mut length_counts, 5);
+    /// config.count_length(&mut length_counts, 10); //crosses the threshold and warns
+    /// assert_eq!(length_counts, [(5, 2), (10, 1)].iter().cloned().collect());
+    /// ```
+    pub fn count_length(&self, length_counts: &mut BTreeMap<usize, u64>, seq_len: usize) {
+        //insert 0 if absent; then increment
+        let len_count: &mut u64 = length_counts.entry(seq_len).or_insert(0);
+        *len_count += 1;
+        if *len_count == 1 {
+            let distinct_lengths: usize = length_counts.len();
+            self.check_distinct_lengths(distinct_lengths - 1, distinct_lengths);
+        }
+    }
+
+    /// This is `count_length(...)` for length counts split across several keys (e.g. read groups), which are all held at once.
+    /// # Arguments
+    /// * `length_counts` - the counts of the key the sequence belongs to
+    /// * `seq_len` - the length of the sequence
+    /// * `distinct_lengths` - the total number of distinct lengths over the counts of every key, which is checked and updated
+    pub fn count_keyed_length(&self, length_counts: &mut BTreeMap<usize, u64>, seq_len: usize, distinct_lengths: &mut usize) {
+        //insert 0 if absent; then increment
+        let len_count: &mut u64 = length_counts.entry(seq_len).or_insert(0);
+        *len_count += 1;
+        if *len_count == 1 {
+            *distinct_lengths += 1;
+            self.check_distinct_lengths(*distinct_lengths - 1, *distinct_lengths);
+        }
+    }
+
+    /// Adds all of `other_counts` to `length_counts`, checking `distinct_length_warning` for the combined counts
+    pub fn merge_counts(&self, length_counts: &mut BTreeMap<usize, u64>, other_counts: &BTreeMap<usize, u64>) {
+        let previous_lengths: usize = length_counts.len();
+        merge_length_counts(length_counts, other_counts);
+        self.check_distinct_lengths(previous_lengths, length_counts.len());
+    }
+
+    /// Returns true if a progress message should be logged after `count` records have been processed
+    pub fn is_progress_point(&self, count: usize) -> bool {
+        self.progress_interval > 0 && count.is_multiple_of(self.progress_interval)
+    }
+
+    /// Returns true if a sequence of length `seq_len` passes the configured length filters
+    pub fn passes_length_filters(&self, seq_len: usize) -> bool {
+        self.min_length.is_none_or(|min_length| seq_len >= min_length) &&
+            self.max_length.is_none_or(|max_length| seq_len <= max_length)
+    }
+}
+
+/// This is the single entry point for gathering sequence lengths from a fastx or BAM/SAM file into a BTreeMap.
+/// The format is selected according to `config.format_detection`, and all options in `config` are applied while loading.
+/// # Arguments
+/// * `filename` - the filename to read sequences from
+/// * `config` - the loading options and filters to apply
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::loader::{gather_stats, LoaderConfig};
+/// let counts: BTreeMap<usize, u64> = gather_stats("./test_data/five_strings.sam", &LoaderConfig::new().min_length(3)).unwrap();
+/// assert_eq!(counts, [(3, 1), (4, 1), (5, 1)].iter().cloned().collect());
+/// ```
+pub fn gather_stats(filename: &str, config: &LoaderConfig) -> Result<BTreeMap<usize, u64>, Box<dyn std::error::Error>> {
+    gather_multi_stats(&[filename], config)
+}
+
+/// This will iterate through multiple fastx or BAM/SAM files and gather the lengths into a single BTreeMap, applying all options in `config`.
+/// When sampling, a single random number generator is used across all files, so the sample depends on the order of `filenames`.
+/// # Arguments
+/// * `filenames` - the filenames to read sequences from
+/// * `config` - the loading options and filters to apply
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::loader::{gather_multi_stats, LoaderConfig};
+/// let filenames = [
+///     "./test_data/single_string.fa",
+///     "./test_data/five_strings.sam"
+/// ];
+/// let counts: BTreeMap<usize, u64> = gather_multi_stats(&filenames, &LoaderConfig::new().head(2)).unwrap();
+/// assert_eq!(counts, [(1, 2), (2, 1)].iter().cloned().collect());
+/// ```
+pub fn gather_multi_stats<T: AsRef<str> + std::fmt::Debug>(filenames: &[T], config: &LoaderConfig) -> Result<BTreeMap<usize, u64>, Box<dyn std::error::Error>> {
+    gather_multi_stats_with_summary(filenames, config).map(|(hash_stats, _load_summary)| hash_stats)
+}
+
+/// Counts of how many records were read and kept while loading
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoadSummary {
+    /// The total number of records read from the files
+    pub total_records: u64,
+    /// The number of records that passed all filters and were counted
+    pub kept_records: u64,
+    /// The number of records removed by `min_mean_qual`
+    pub quality_filtered: u64,
+    /// If `check_duplicate_names` is set, the number of records whose name had already been seen; otherwise 0
+    pub duplicate_names: u64,
+    /// If `validate_qual_length` is set, the number of FASTQ records whose sequence and quality lengths differ; otherwise 0
+    pub qual_length_mismatches: u64,
+    /// The format each file was loaded as, in the order of the filenames
+    pub input_formats: Vec<InputFormat>
+}
+
+/// This is identical to `gather_multi_stats(...)`, but also returns a summary of how many records were read and filtered.
+/// # Arguments
+/// * `filenames` - the filenames to read sequences from
+/// * `config` - the loading options and filters to apply
+/// # Examples
+/// ```
+/// use fastleng::loader::{gather_multi_stats_with_summary, LoaderConfig};
+/// let (counts, load_summary) = gather_multi_stats_with_summary(&["./test_data/mixed_quality.fq"], &LoaderConfig::new().min_mean_qual(20.0)).unwrap();
+/// assert_eq!(load_summary.total_records, 4);
+/// assert_eq!(load_summary.quality_filtered, 1);
+/// assert_eq!(counts.values().sum::<u64>(), 3);
+/// ```
+pub fn gather_multi_stats_with_summary<T: AsRef<str> + std::fmt::Debug>(filenames: &[T], config: &LoaderConfig) -> Result<(BTreeMap<usize, u64>, LoadSummary), Box<dyn std::error::Error>> {
+    let (mut keyed_stats, load_summary) = gather_keyed_stats(filenames, config, |_seq_id| ())?;
+    Ok((keyed_stats.remove(&()).unwrap_or_default(), load_summary))
+}
+
+/// The totals gathered by `count_multi_totals(...)`, without any length counts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LengthTotals {
+    /// The total number of bases in the counted sequences
+    pub total_bases: u64,
+    /// The total number of counted sequences
+    pub total_sequences: u64
+}
+
+/// This will stream through multiple fastx or BAM/SAM files and only total up the bases and sequences, applying the same options as `gather_multi_stats(...)`.
+/// No length counts are built at all, making this the cheapest way to get the totals for very large inputs.
+/// The `check_duplicate_names` option is not supported here and is ignored.
+/// # Arguments
+/// * `filenames` - the filenames to read sequences from
+/// * `config` - the loading options and filters to apply
+/// # Examples
+/// ```
+/// use fastleng::loader::{count_multi_totals, LoaderConfig};
+/// let filenames = [
+///     "./test_data/single_string.fa",
+///     "./test_data/five_strings.sam"
+/// ];
+/// let totals = count_multi_totals(&filenames, &LoaderConfig::new()).unwrap();
+/// assert_eq!(totals.total_bases, 16);
+/// assert_eq!(totals.total_sequences, 6);
+/// ```
+pub fn count_multi_totals<T: AsRef<str> + std::fmt::Debug>(filenames: &[T], config: &LoaderConfig) -> Result<LengthTotals, Box<dyn std::error::Error>> {
+    let mut rng = config.sampling.as_ref().map(|sampling| sampling.build_rng());
+    let mut totals: LengthTotals = Default::default();
+    let total_count = stream_multifastx_lengths(filenames, config, |_seq_id, seq_len| {
+        //same draw order as gather_keyed_stats(...), so the sample matches the full load
+        let sampled: bool = match (rng.as_mut(), config.sampling.as_ref()) {
+            (Some(rng), Some(sampling)) => rng.next_f64() < sampling.fraction,
+            _ => true
+        };
+        if sampled && config.passes_length_filters(seq_len) {
+            totals.total_bases = totals.total_bases.saturating_add(seq_len as u64);
+            totals.total_sequences += 1;
+        }
+        Ok(())
+    })?;
+    if config.sampling.is_some() || config.min_length.is_some() || config.max_length.is_some() || config.min_mean_qual.is_some() {
+        info!("Kept {} of {} sequences.", totals.total_sequences, total_count);
+    }
+    Ok(totals)
+}
+
+/// The results of `gather_length_digest(...)`, a sketch of the lengths instead of the full length counts
+#[derive(Clone, Debug)]
+pub struct LengthDigest {
+    /// The t-digest of every counted sequence length
+    pub digest: TDigest,
+    /// The exact totals of the counted sequences
+    pub totals: LengthTotals,
+    /// The detected format of each input file, in order
+    pub input_formats: Vec<InputFormat>
+}
+
+/// This will stream through multiple fastx or BAM/SAM files and add each sequence length to a t-digest, applying the same options as `count_multi_totals(...)`.
+/// The memory used is fixed by `compression` regardless of the number of distinct lengths, so percentiles can be estimated for inputs of any size.
+/// # Arguments
+/// * `filenames` - the filenames to read sequences from
+/// * `config` - the loading options and filters to apply
+/// * `compression` - the compression of the t-digest, see `tdigest::DEFAULT_COMPRESSION`
+/// # Examples
+/// ```
+/// use fastleng::loader::{gather_length_digest, LoaderConfig};
+/// use fastleng::tdigest::DEFAULT_COMPRESSION;
+/// let filenames = ["./test_data/five_strings.fa"];
+/// let mut length_digest = gather_length_digest(&filenames, &LoaderConfig::new(), DEFAULT_COMPRESSION).unwrap();
+/// assert_eq!(length_digest.totals.total_sequences, 5);
+/// assert_eq!(length_digest.digest.quantile(0.5), 3.0);
+/// ```
+pub fn gather_length_digest<T: AsRef<str> + std::fmt::Debug>(filenames: &[T], config: &LoaderConfig, compression: f64) -> Result<LengthDigest, Box<dyn std::error::Error>> {
+    let mut rng = config.sampling.as_ref().map(|sampling| sampling.build_rng());
+    let mut digest: TDigest = TDigest::new(compression);
+    let mut totals: LengthTotals = Default::default();
+    let (total_count, input_formats) = stream_multifastx_lengths_with_formats(filenames, config, |_seq_id, seq_len| {
+        //same draw order as gather_keyed_stats(...), so the sample matches the full load
+        let sampled: bool = match (rng.as_mut(), config.sampling.as_ref()) {
+            (Some(rng), Some(sampling)) => rng.next_f64() < sampling.fraction,
+            _ => true
+        };
+        if sampled && config.passes_length_filters(seq_len) {
+            digest.add(seq_len as f64, 1);
+            totals.total_bases = totals.total_bases.saturating_add(seq_len as u64);
+            totals.total_sequences += 1;
+        }
+        Ok(())
+    })?;
+    if config.sampling.is_some() || config.min_length.is_some() || config.max_length.is_some() || config.min_mean_qual.is_some() {
+        info!("Kept {} of {} sequences.", totals.total_sequences, total_count);
+    }
+    Ok(LengthDigest {
+        digest,
+        totals,
+        input_formats
+    })
+}
+
+/// Length counts separated by a key, e.g. the group of each record name
+pub type KeyedLengthCounts<K> = BTreeMap<K, BTreeMap<usize, u64>>;
+
+/// The group that records are placed in when their name does not match the grouping regex
+pub const UNMATCHED_GROUP: &str = "unmatched";
+
+/// Returns the group of a record name, which is the first capture group of `group_regex` or `UNMATCHED_GROUP` if it does not match.
+/// # Arguments
+/// * `group_regex` - the pattern to apply to the record name, which should contain a capture group
+/// * `seq_id` - the record name
+/// # Examples
+/// ```
+/// use regex::bytes::Regex;
+/// use fastleng::loader::group_name;
+/// let group_regex = Regex::new("barcode=(\\w+)").unwrap();
+/// assert_eq!(group_name(&group_regex, b"read1 barcode=bc01"), "bc01");
+/// assert_eq!(group_name(&group_regex, b"read2"), "unmatched");
+/// ```
+pub fn group_name(group_regex: &Regex, seq_id: &[u8]) -> String {
+    match group_regex.captures(seq_id).and_then(|captures| captures.get(1)) {
+        Some(group_match) => String::from_utf8_lossy(group_match.as_bytes()).to_string(),
+        None => UNMATCHED_GROUP.to_string()
+    }
+}
+
+/// This will load the lengths like `gather_multi_stats_with_summary(...)`, but separated into groups by the record names.
+/// Each group is the first capture group of `group_regex` applied to the record name, and names that do not match are in `UNMATCHED_GROUP`.
+/// The per-group counts can be combined with `merge_length_counts(...)` to get the overall counts.
+/// # Arguments
+/// * `filenames` - the filenames to read sequences from
+/// * `config` - the loading options and filters to apply
+/// * `group_regex` - the pattern used to assign each record to a group
+/// # Examples
+/// ```
+/// use regex::bytes::Regex;
+/// use fastleng::loader::{gather_grouped_stats, LoaderConfig};
+/// let group_regex = Regex::new("^([1-2])$").unwrap();
+/// let (grouped_counts, _load_summary) = gather_grouped_stats(&["./test_data/five_strings.fa"], &LoaderConfig::new(), &group_regex).unwrap();
+/// assert_eq!(grouped_counts["1"][&1], 1);
+/// assert_eq!(grouped_counts["2"][&2], 1);
+/// assert_eq!(grouped_counts["unmatched"].len(), 3);
+/// ```
+pub fn gather_grouped_stats<T: AsRef<str> + std::fmt::Debug>(filenames: &[T], config: &LoaderConfig, group_regex: &Regex) -> Result<(KeyedLengthCounts<String>, LoadSummary), Box<dyn std::error::Error>> {
+    gather_keyed_stats(filenames, config, |seq_id| group_name(group_regex, seq_id))
+}
+
+/// This is the shared loading loop, which applies all of the options in `config` and counts each kept record under the key from `key_fn`.
+/// # Arguments
+/// * `filenames` - the filenames to read sequences from
+/// * `config` - the loading options and filters to apply
+/// * `key_fn` - converts a record name into the key it is counted under
+fn gather_keyed_stats<T, K, F>(filenames: &[T], config: &LoaderConfig, mut key_fn: F) -> Result<(KeyedLengthCounts<K>, LoadSummary), Box<dyn std::error::Error>>
+where
+    T: AsRef<str> + std::fmt::Debug,
+    K: Ord,
+    F: FnMut(&[u8]) -> K
+{
+    let mut rng = config.sampling.as_ref().map(|sampling| sampling.build_rng());
+    let mut kept_count: u64 = 0;
+    let mut quality_passed: u64 = 0;
+    let mut seen_names: HashSet<Vec<u8>> = HashSet::new();
+    let mut duplicate_names: u64 = 0;
+    let mut keyed_stats: KeyedLengthCounts<K> = BTreeMap::new();
+    let mut distinct_lengths: usize = 0;
+    let stream_summary = stream_multifastx_lengths_with_summary(filenames, config, |seq_id, seq_len| {
+        //only records passing the quality filter reach here
+        quality_passed += 1;
+        if config.check_duplicate_names && !seen_names.insert(seq_id.to_vec()) {
+            duplicate_names += 1;
+        }
+        //the sampling draw happens for every record so the sample does not depend on the filters
+        let sampled: bool = match (rng.as_mut(), config.sampling.as_ref()) {
+            (Some(rng), Some(sampling)) => rng.next_f64() < sampling.fraction,
+            _ => true
+        };
+        if sampled && config.passes_length_filters(seq_len) {
+            let hash_stats: &mut BTreeMap<usize, u64> = keyed_stats.entry(key_fn(seq_id)).or_default();
+            config.count_keyed_length(hash_stats, seq_len, &mut distinct_lengths);
+            kept_count += 1;
+        }
+        Ok(())
+    })?;
+    let total_count: usize = stream_summary.total_records;
+    if config.sampling.is_some() || config.min_length.is_some() || config.max_length.is_some() || config.min_mean_qual.is_some() {
+        info!("Kept {} of {} sequences.", kept_count, total_count);
+    }
+    let load_summary = LoadSummary {
+        total_records: total_count as u64,
+        kept_records: kept_count,
+        quality_filtered: total_count as u64 - quality_passed,
+        duplicate_names,
+        qual_length_mismatches: stream_summary.qual_length_mismatches,
+        input_formats: stream_summary.input_formats
+    };
+    if duplicate_names > 0 {
+        warn!("Found {} records with a duplicate name.", duplicate_names);
+    }
+    if load_summary.qual_length_mismatches > 0 {
+        warn!("Found {} FASTQ records with different sequence and quality lengths.", load_summary.qual_length_mismatches);
+    }
+    Ok((keyed_stats, load_summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fastx_loader::{gather_multifastx_stats, gather_multifastx_stats_sampled};
+
+    #[test]
+    fn test_default_config() {
+        let filenames = [
+            "./test_data/long_strings.fa",
+            "./test_data/five_strings.sam"
+        ];
+        let hash_stats = gather_multi_stats(&filenames, &LoaderConfig::new()).unwrap();
+        assert_eq!(hash_stats, gather_multifastx_stats(&filenames).unwrap());
+    }
+
+    #[test]
+    fn test_length_filters() {
+        let config = LoaderConfig::new().min_length(2).max_length(4);
+        let hash_stats = gather_stats("./test_data/five_strings.fa", &config).unwrap();
+        let expected: BTreeMap<usize, u64> = [(2, 1), (3, 1), (4, 1)].iter().cloned().collect();
+        assert_eq!(hash_stats, expected);
+
+        assert!(!config.passes_length_filters(1));
+        assert!(config.passes_length_filters(4));
+        assert!(!config.passes_length_filters(5));
+    }
+
+    #[test]
+    fn test_sampling_matches() {
+        //the config path should pick exactly the same records as the dedicated sampled loader
+        let filenames = [
+            "./test_data/long_strings.fa",
+            "./test_data/small_strings.fa"
+        ];
+        let sampling = SamplingConfig { fraction: 0.5, seed: 7 };
+        let config = LoaderConfig::new().sampling(sampling.clone());
+        let hash_stats = gather_multi_stats(&filenames, &config).unwrap();
+        assert_eq!(hash_stats, gather_multifastx_stats_sampled(&filenames, &BamLoadOptions::default(), &sampling).unwrap());
+    }
+
+    #[test]
+    fn test_format_detection() {
+        //a SAM file named like a FASTQ is only loaded correctly when the contents are sniffed
+        let misnamed_sam = std::env::temp_dir().join("fastleng_test_loader_sam.fq");
+        std::fs::copy("./test_data/five_strings.sam", &misnamed_sam).unwrap();
+        let misnamed_str: &str = misnamed_sam.to_str().unwrap();
+        let content_config = LoaderConfig::new().format_detection(FormatDetection::Content);
+        let content_stats = gather_stats(misnamed_str, &content_config);
+        let auto_stats = gather_stats(misnamed_str, &LoaderConfig::new());
+        std::fs::remove_file(&misnamed_sam).unwrap();
+        assert_eq!(content_stats.unwrap(), gather_stats("./test_data/five_strings.sam", &LoaderConfig::new()).unwrap());
+        assert!(auto_stats.is_err());
+
+        //strict extensions reject anything unrecognized
+        let strict_config = LoaderConfig::new().format_detection(FormatDetection::Extension);
+        assert!(gather_stats("./test_data/five_strings.cram", &strict_config).is_ok());
+        assert!(gather_stats("./test_data/panic_file.fa", &strict_config).is_err());
+    }
+
+    #[test]
+    fn test_min_mean_qual() {
+        //the mean qualities are 10, 20, 25, and 40
+        let config = LoaderConfig::new().min_mean_qual(20.0);
+        let (hash_stats, load_summary) = gather_multi_stats_with_summary(&["./test_data/mixed_quality.fq"], &config).unwrap();
+        let expected: BTreeMap<usize, u64> = [(1, 1), (2, 1), (4, 1)].iter().cloned().collect();
+        assert_eq!(hash_stats, expected);
+        assert_eq!(load_summary, LoadSummary { total_records: 4, kept_records: 3, quality_filtered: 1, duplicate_names: 0, qual_length_mismatches: 0, input_formats: vec![InputFormat::Fastq] });
+
+        //quality filtering happens before the length filters
+        let (_hash_stats, load_summary) = gather_multi_stats_with_summary(&["./test_data/mixed_quality.fq"], &config.clone().min_length(3)).unwrap();
+        assert_eq!(load_summary, LoadSummary { total_records: 4, kept_records: 1, quality_filtered: 1, duplicate_names: 0, qual_length_mismatches: 0, input_formats: vec![InputFormat::Fastq] });
+
+        //inputs without qualities are rejected
+        assert!(gather_stats("./test_data/five_strings.fa", &config).is_err());
+        assert!(gather_stats("./test_data/five_strings.sam", &config).is_err());
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        //a file listed twice is the classic concatenation mistake
+        let filenames = [
+            "./test_data/five_strings.fa",
+            "./test_data/five_strings.fa"
+        ];
+        let config = LoaderConfig::new().check_duplicate_names(true);
+        let (_hash_stats, load_summary) = gather_multi_stats_with_summary(&filenames, &config).unwrap();
+        assert_eq!(load_summary.duplicate_names, 5);
+
+        //names only repeat within the SAM for readA, readB, and readD
+        let (_hash_stats, load_summary) = gather_multi_stats_with_summary(&["./test_data/duplicate_names.sam"], &config).unwrap();
+        assert_eq!(load_summary.duplicate_names, 3);
+
+        //nothing is tracked by default
+        let (_hash_stats, load_summary) = gather_multi_stats_with_summary(&filenames, &LoaderConfig::new()).unwrap();
+        assert_eq!(load_summary.duplicate_names, 0);
+    }
+
+    #[test]
+    fn test_max_records() {
+        //exactly at the limit is fine, one over is an error instead of a truncation
+        for filename in ["./test_data/five_strings.fa", "./test_data/five_strings.sam"] {
+            assert_eq!(gather_stats(filename, &LoaderConfig::new().max_records(5)).unwrap().values().sum::<u64>(), 5);
+            assert!(gather_stats(filename, &LoaderConfig::new().max_records(4)).is_err());
+        }
+
+        //the limit applies to each file separately
+        let filenames = [
+            "./test_data/five_strings.fa",
+            "./test_data/five_strings.sam"
+        ];
+        assert!(gather_multi_stats(&filenames, &LoaderConfig::new().max_records(5)).is_ok());
+
+        //head stops reading before the limit is exceeded
+        assert!(gather_stats("./test_data/five_strings.fa", &LoaderConfig::new().head(3).max_records(4)).is_ok());
+    }
+
+    #[test]
+    fn test_grouped_stats() {
+        let filenames = [
+            "./test_data/five_strings.fa",
+            "./test_data/five_strings.sam"
+        ];
+        let group_regex = Regex::new("^(m64109)_").unwrap();
+        let config = LoaderConfig::new().min_length(2);
+        let (grouped_counts, load_summary) = gather_grouped_stats(&filenames, &config, &group_regex).unwrap();
+        assert_eq!(load_summary.kept_records, 8);
+        assert_eq!(grouped_counts.keys().collect::<Vec<_>>(), vec!["m64109", UNMATCHED_GROUP]);
+
+        //the groups always add back up to the ungrouped counts
+        let mut merged_counts: BTreeMap<usize, u64> = BTreeMap::new();
+        for group_counts in grouped_counts.values() {
+            crate::length_stats::merge_length_counts(&mut merged_counts, group_counts);
+        }
+        assert_eq!(merged_counts, gather_multi_stats(&filenames, &config).unwrap());
+
+        //a regex that never matches puts everything in one bucket
+        let no_match_regex = Regex::new("^(nothing)$").unwrap();
+        let (grouped_counts, _load_summary) = gather_grouped_stats(&filenames, &config, &no_match_regex).unwrap();
+        assert_eq!(grouped_counts.keys().collect::<Vec<_>>(), vec![UNMATCHED_GROUP]);
+    }
+
+    #[test]
+    fn test_count_multi_totals() {
+        use crate::length_stats::compute_total_counts;
+        use crate::sampling::SamplingConfig;
+
+        //the totals should always match the full length counts with the same options
+        let filenames = [
+            "./test_data/long_strings.fa",
+            "./test_data/five_strings.sam",
+            "./test_data/mixed_quality.fq"
+        ];
+        let configs = [
+            LoaderConfig::new(),
+            LoaderConfig::new().head(2).min_length(2),
+            LoaderConfig::new().max_length(3),
+            LoaderConfig::new().sampling(SamplingConfig { fraction: 0.5, seed: 7 })
+        ];
+        for config in configs.iter() {
+            let (expected_bases, expected_seqs) = compute_total_counts(&gather_multi_stats(&filenames, config).unwrap());
+            let totals = count_multi_totals(&filenames, config).unwrap();
+            assert_eq!(totals, LengthTotals { total_bases: expected_bases, total_sequences: expected_seqs });
+        }
+
+        let quality_config = LoaderConfig::new().min_mean_qual(20.0);
+        let totals = count_multi_totals(&["./test_data/mixed_quality.fq"], &quality_config).unwrap();
+        assert_eq!(totals, LengthTotals { total_bases: 7, total_sequences: 3 });
+        assert!(count_multi_totals(&["./test_data/five_strings.fa"], &quality_config).is_err());
+    }
+
+    #[test]
+    fn test_gather_length_digest() {
+        use crate::tdigest::DEFAULT_COMPRESSION;
+
+        //the totals are exact and match count_multi_totals(...) with the same options
+        let filenames = [
+            "./test_data/long_strings.fa",
+            "./test_data/five_strings.sam"
+        ];
+        for config in [LoaderConfig::new(), LoaderConfig::new().max_length(3)].iter() {
+            let mut length_digest = gather_length_digest(&filenames, config, DEFAULT_COMPRESSION).unwrap();
+            assert_eq!(length_digest.totals, count_multi_totals(&filenames, config).unwrap());
+            assert_eq!(length_digest.digest.count(), length_digest.totals.total_sequences);
+            assert_eq!(length_digest.input_formats, vec![InputFormat::Fasta, InputFormat::Sam]);
+            assert!(length_digest.digest.quantile(0.5) > 0.0);
+        }
+        assert!(gather_length_digest(&["./test_data/does_not_exist.fa"], &LoaderConfig::new(), DEFAULT_COMPRESSION).is_err());
+    }
+
+    #[test]
+    fn test_progress_interval() {
+        let default_config = LoaderConfig::new();
+        assert_eq!(default_config.progress_interval, DEFAULT_PROGRESS_INTERVAL);
+        assert!(default_config.is_progress_point(2_000_000));
+        assert!(!default_config.is_progress_point(1_500_000));
+
+        let small_config = LoaderConfig::new().progress_interval(2);
+        assert!(small_config.is_progress_point(4));
+        assert!(!small_config.is_progress_point(3));
+        assert_eq!(gather_multi_stats(&["./test_data/five_strings.fa"], &small_config).unwrap(), gather_multifastx_stats(&["./test_data/five_strings.fa"]).unwrap());
+
+        //0 never logs, instead of dividing by zero
+        let disabled_config = LoaderConfig::new().progress_interval(0);
+        assert!(!disabled_config.is_progress_point(0));
+        assert!(!disabled_config.is_progress_point(1_000_000));
+    }
+
+    #[test]
+    fn test_distinct_length_warning() {
+        let default_config = LoaderConfig::new();
+        assert_eq!(default_config.distinct_length_warning, DEFAULT_DISTINCT_LENGTH_WARNING);
+        assert!(!default_config.check_distinct_lengths(10, 11));
+
+        //only crossing the threshold warns, so it happens once
+        let small_config = LoaderConfig::new().distinct_length_warning(3);
+        assert!(!small_config.check_distinct_lengths(2, 3));
+        assert!(small_config.check_distinct_lengths(3, 4));
+        assert!(small_config.check_distinct_lengths(1, 10));
+        assert!(!small_config.check_distinct_lengths(4, 5));
+        assert!(!LoaderConfig::new().distinct_length_warning(0).check_distinct_lengths(0, 100));
+
+        //the warning does not change what is loaded
+        let filenames = ["./test_data/five_strings.fa"];
+        assert_eq!(gather_multi_stats(&filenames, &small_config).unwrap(), gather_multifastx_stats(&filenames).unwrap());
+
+        //the counting helpers only check crossings of the total, including across keys
+        let mut length_counts: BTreeMap<usize, u64> = BTreeMap::new();
+        for seq_len in [5, 5, 10, 15, 20] {
+            small_config.count_length(&mut length_counts, seq_len);
+        }
+        assert_eq!(length_counts, [(5, 2), (10, 1), (15, 1), (20, 1)].iter().cloned().collect());
+        let mut keyed_counts: KeyedLengthCounts<&str> = BTreeMap::new();
+        let mut distinct_lengths: usize = 0;
+        for (key, seq_len) in [("a", 5), ("b", 5), ("a", 5), ("b", 10)] {
+            small_config.count_keyed_length(keyed_counts.entry(key).or_default(), seq_len, &mut distinct_lengths);
+        }
+        assert_eq!(distinct_lengths, 3);
+        assert_eq!(keyed_counts["a"], [(5, 2)].iter().cloned().collect());
+        small_config.merge_counts(&mut length_counts, &keyed_counts["b"]);
+        assert_eq!(length_counts, [(5, 3), (10, 2), (15, 1), (20, 1)].iter().cloned().collect());
+    }
+}
@@ -5,19 +5,366 @@ extern crate exitcode;
 extern crate log;
 
 use clap::{App, Arg, value_t, values_t};
-use log::{error, info};
+use log::{error, info, warn};
+use regex::bytes::Regex;
+use serde::Serialize;
 use std::collections::BTreeMap;
 use std::fs::File;
+use std::io::Write;
+use std::time::{Duration, Instant};
 
-use fastleng::fastx_loader::gather_multifastx_stats;
-use fastleng::length_stats::{compute_length_stats, LengthStats};
+use fastleng::bam_loader::{gather_read_group_stats, BamLoadOptions};
+use fastleng::fastx_loader::{
+    gather_multifastx_ambiguous_stats, gather_multifastx_interleaved_stats, gather_multifastx_n_stats, gather_multifastx_quality_stats, gather_paired_stats, gather_multifastx_stats_keep_going, stream_multifastx_lengths_with_summary, FailedFile, GappedLengthCounts,
+    LengthQualityCounts
+};
+use fastleng::fastx_loader::gather_fastx_stats;
+use fastleng::length_stats::{
+    compare_length_stats, compute_approximate_stats, compute_base_histogram, compute_basic_stats, compute_detailed_histogram, compute_length_stats_with_interpolation, compute_log_histogram, compute_n_score_above, compute_ng_stats, compute_nx_curve, compute_selected_stats, compute_top_fraction_stats, compute_total_counts, sequences_to_cover_fraction,
+    detect_peaks, drop_longest_lengths, exclude_lengths, expand_lengths, filter_length_range, merge_length_counts, remove_zero_lengths, resolve_stat_name, round_to_decimals, ApproximateLengthStats, BasicLengthStats, FieldComparison, LengthRange, LengthStats, NgStats,
+    top_n_lengths, SelectedStats, TopFractionStats, DEFAULT_EXPAND_LENGTHS_CAP, LENGTH_STAT_NAMES
+};
+use fastleng::format::{describe_formats, detect_format, format_from_extension, is_remote_url, is_stream_input, resolve_format, InputFormat};
+use fastleng::json_case::{CasedJson, JsonCase};
+use fastleng::loader::{
+    count_multi_totals, gather_grouped_stats, gather_length_digest, gather_multi_stats_with_summary, gather_stats, LengthDigest, LengthTotals, LoaderConfig, DEFAULT_DISTINCT_LENGTH_WARNING, DEFAULT_PROGRESS_INTERVAL
+};
+use fastleng::output::{create_output_writer, format_assembly_stats, format_markdown_table_with_seq_type, format_summary_line, read_length_counts_json, read_length_counts_txt_with_check, OutputWriter, SeqType, DEFAULT_GZIP_LEVEL};
+use fastleng::sampling::{bootstrap_n50, estimate_sampled_totals, sample_files, LengthReservoir, SampledEstimates, SamplingConfig};
+#[cfg(feature = "sqlite")]
+use fastleng::sqlite_output::insert_length_stats;
+use fastleng::tar_loader::gather_tar_stats;
+use fastleng::tdigest::DEFAULT_COMPRESSION;
 
 const VERSION: Option<&'static str> = option_env!("CARGO_PKG_VERSION");
 
-fn main() {
-    //initialize logging for our benefit later
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+/// Exit code when `--keep-going` skipped at least one input file but still produced stats for the rest
+const EXIT_PARTIAL_SUCCESS: i32 = 3;
+
+/// The default minimum length for warning that every sequence has the same length; short reads below this are expected to be uniform
+const DEFAULT_SAME_LENGTH_WARNING: usize = 1000;
+
+/// The default ratio between consecutive bin bounds of `--log-histogram`
+const DEFAULT_LOG_HISTOGRAM_BASE: usize = 2;
+
+/// The summary statistics along with any metadata describing how they were generated
+#[derive(Serialize)]
+struct StatsOutput<'a> {
+    /// The computed summary statistics, serialized at the top level
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    stats: Option<&'a LengthStats>,
+    /// With `--fast`, the reduced statistics computed instead of the full set, serialized at the top level
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    basic_stats: Option<&'a BasicLengthStats>,
+    /// With `--stats`, only the chosen statistics, serialized at the top level
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    selected_stats: Option<&'a SelectedStats>,
+    /// The format of the inputs (e.g. "fastq" or "bam"), or "mixed" if they differ
+    input_format: &'a str,
+    /// The full command line that produced these results, for reproducibility
+    command_line: String,
+    /// If `--seq-type` was given, the kind of sequences measured; for proteins the "bases" fields count residues
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seq_type: Option<SeqType>,
+    /// True if the statistics are an approximation of the full input
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    approximate: bool,
+    /// If `--head` was given, the maximum number of records read from each file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    head: Option<usize>,
+    /// If sampling was requested, the fraction and seed that were used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sampling: Option<&'a SamplingConfig>,
+    /// If sampling was requested, the totals scaled up to estimates for the full input
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    sampled_estimates: Option<SampledEstimates>,
+    /// If N counting was requested, the total number of N bases across all sequences
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total_n_bases: Option<u64>,
+    /// If N counting was requested, the statistics computed after removing N bases from each sequence
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ungapped_stats: Option<&'a LengthStats>,
+    /// If `--count-ambiguous` was given, the number of each IUPAC ambiguity code across all of the sequences
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ambiguous_base_counts: Option<BTreeMap<char, u64>>,
+    /// If `--min-mean-qual` was given, the minimum mean Phred score for a FASTQ record to be counted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_mean_qual: Option<f64>,
+    /// If `--min-mean-qual` was given, the number of records removed for having a lower mean quality
+    #[serde(skip_serializing_if = "Option::is_none")]
+    quality_filtered_reads: Option<u64>,
+    /// If `--check-duplicate-names` was given, the number of records whose name was already seen
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duplicate_names: Option<u64>,
+    /// If `--validate-qual-length` was given, the number of FASTQ records whose sequence and quality lengths differ
+    #[serde(skip_serializing_if = "Option::is_none")]
+    qual_length_mismatches: Option<u64>,
+    /// The number of sequences with no bases at all, which are left out of the statistics
+    zero_length_sequences: u64,
+    /// Any exact sequence lengths that were removed before computing the statistics
+    #[serde(skip_serializing_if = "Option::is_none")]
+    excluded_lengths: Option<&'a [usize]>,
+    /// If `--drop-longest` was given, the lengths of the longest sequences that were removed before computing the statistics, longest first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dropped_longest_lengths: Option<Vec<usize>>,
+    /// If a length window was given, the inclusive range of lengths that were kept
+    #[serde(skip_serializing_if = "Option::is_none")]
+    length_range: Option<LengthRange>,
+    /// If `--group-by-regex` was given, the statistics of each group of record names
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group_stats: Option<BTreeMap<String, LengthStats>>,
+    /// If `--by-read-group` was given, the statistics of each read group in the BAM/SAM `RG` tags
+    #[serde(skip_serializing_if = "Option::is_none")]
+    read_group_stats: Option<BTreeMap<String, LengthStats>>,
+    /// If `--tar` was given, the statistics of each FASTX member of the archive
+    #[serde(skip_serializing_if = "Option::is_none")]
+    member_stats: Option<BTreeMap<String, LengthStats>>,
+    /// If `--n50-min-length` was given, the shortest sequence length included in `n50_above_min_length`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n50_min_length: Option<usize>,
+    /// If `--n50-min-length` was given, the N50 of only the sequences at least that long
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n50_above_min_length: Option<usize>,
+    /// If `--top-fractions` was given, the basic stats of the longest sequences for each fraction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_fractions: Option<Vec<TopFractionStats>>,
+    /// If `--cover-fractions` was given, the number and total length of the longest sequences needed to cover each fraction of the bases
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cover_fractions: Option<Vec<CoverFraction>>,
+    /// If `--bootstrap` was given, the bootstrap confidence interval of the N50
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n50_bootstrap: Option<N50Bootstrap>,
+    /// If `--top` was given, the longest individual sequence lengths, longest first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_lengths: Option<Vec<usize>>,
+    /// If `--reservoir` was given, a uniform random sample of that many individual sequence lengths, in no particular order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reservoir_lengths: Option<Vec<usize>>,
+    /// If `--detect-peaks` was given, the local maxima of the length histogram with at least that prominence
+    #[serde(skip_serializing_if = "Option::is_none")]
+    length_peaks: Option<Vec<LengthPeak>>,
+    /// If a genome size was provided, the NG-scores relative to it
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    ng_stats: Option<NgStats>,
+    /// With `--keep-going`, any input files that failed to load and were skipped
+    #[serde(skip_serializing_if = "<[FailedFile]>::is_empty")]
+    failed_files: &'a [FailedFile]
+}
+
+/// The statistics from `--approximate-percentiles` along with the metadata describing how they were generated
+#[derive(Serialize)]
+struct ApproximateStatsOutput<'a> {
+    /// The computed statistics, serialized at the top level
+    #[serde(flatten)]
+    stats: &'a ApproximateLengthStats,
+    /// The format of the inputs (e.g. "fastq" or "bam"), or "mixed" if they differ
+    input_format: &'a str,
+    /// The full command line that produced these results, for reproducibility
+    command_line: String,
+    /// Always true, marking the percentile lengths as estimates from a t-digest rather than exact values
+    approximate_percentiles: bool,
+    /// If `--head` was given, the maximum number of records read from each file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    head: Option<usize>,
+    /// If sampling was requested, the fraction and seed that were used
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sampling: Option<&'a SamplingConfig>
+}
+
+/// Wrapper for saving the summary statistics and the raw length counts in a single JSON document
+#[derive(Serialize)]
+struct CombinedOutput<'a> {
+    /// The computed summary statistics
+    stats: &'a StatsOutput<'a>,
+    /// The raw length counts the statistics were computed from
+    histogram: &'a BTreeMap<usize, u64>
+}
+
+/// The statistics of two datasets along with the change in each statistic, for `--compare`
+#[derive(Serialize)]
+struct ComparisonOutput<'a> {
+    /// The file the changes are relative to
+    baseline_file: &'a str,
+    /// The file compared against the baseline
+    comparison_file: &'a str,
+    /// The statistics of the baseline file
+    baseline: &'a LengthStats,
+    /// The statistics of the comparison file
+    comparison: &'a LengthStats,
+    /// The change in each statistic, in field order
+    changes: &'a [FieldComparison]
+}
+
+/// A single line of the per-record lengths output
+#[derive(Serialize)]
+struct RecordLength<'a> {
+    /// The record identifier
+    name: &'a str,
+    /// The sequence length of the record
+    length: usize
+}
+
+/// The bootstrap confidence interval of the N50 from `--bootstrap`
+#[derive(Serialize)]
+struct N50Bootstrap {
+    /// The number of bootstrap replicates
+    replicates: usize,
+    /// The seed used to draw the replicates
+    seed: u64,
+    /// The mean N50 of the replicates
+    mean_n50: f64,
+    /// The 2.5th percentile of the replicate N50s, the lower end of the 95% confidence interval
+    n50_lower_95: f64,
+    /// The 97.5th percentile of the replicate N50s, the upper end of the 95% confidence interval
+    n50_upper_95: f64
+}
+
+/// The build metadata printed by `--version-json`, embedded by `build.rs`
+#[derive(Serialize)]
+struct BuildInfo {
+    /// The crate version, the same as `--version`
+    version: &'static str,
+    /// The git commit that was built, or "unknown" if it was not built from a git checkout
+    git_commit: &'static str,
+    /// When the binary was built, as an RFC 3339 UTC timestamp (`SOURCE_DATE_EPOCH` is used if set)
+    build_timestamp: &'static str,
+    /// The output of `rustc --version` for the compiler that built the binary
+    rustc_version: &'static str
+}
+
+/// A peak of the length histogram from `--detect-peaks`
+#[derive(Serialize)]
+struct LengthPeak {
+    /// The sequence length at the top of the peak
+    length: usize,
+    /// The number of sequences with that length
+    count: u64
+}
 
+/// The longest sequences covering a fraction of the bases, from `--cover-fractions`
+#[derive(Serialize)]
+struct CoverFraction {
+    /// The fraction of all bases to cover
+    fraction: f64,
+    /// The fewest sequences, longest first, whose combined length reaches the fraction
+    sequence_count: u64,
+    /// The combined length of those sequences
+    cumulative_bases: u64
+}
+
+/// A single point of the Nx curve output
+#[derive(Serialize)]
+struct NxCurvePoint {
+    /// The sequence length
+    length: usize,
+    /// The fraction of all bases in sequences of at least this length
+    cumulative_base_fraction: f64
+}
+
+/// Creates an output file (gzip compressed if it ends with `.gz`), exiting the program with `CANTCREAT` if it cannot be created.
+/// # Arguments
+/// * `filename` - the path of the output file to create
+/// * `gzip_level` - the compression level for `.gz` outputs
+fn create_output_file(filename: &str, gzip_level: u32) -> OutputWriter {
+    match create_output_writer(filename, gzip_level) {
+        Ok(writer) => writer,
+        Err(e) => {
+            error!("Failed to create output JSON file: {:?}", filename);
+            error!("Error: {:?}", e);
+            std::process::exit(exitcode::CANTCREAT);
+        }
+    }
+}
+
+/// Saves a value as JSON to an output file, exiting the program with `IOERR` if it cannot be written.
+/// # Arguments
+/// * `filename` - the path of the output file to create
+/// * `gzip_level` - the compression level for `.gz` outputs
+/// * `compact` - if true, write the JSON on a single line instead of pretty-printing it
+/// * `value` - the value to serialize
+fn save_json_output<T: Serialize>(filename: &str, gzip_level: u32, compact: bool, value: &T) {
+    let mut out_file = create_output_file(filename, gzip_level);
+    let write_result = if compact { serde_json::to_writer(&mut out_file, value) } else { serde_json::to_writer_pretty(&mut out_file, value) }
+        .map_err(std::io::Error::from)
+        .and_then(|_| out_file.finish());
+    if let Err(e) = write_result {
+        error!("Failed to write output JSON file: {:?}", filename);
+        error!("Error: {:?}", e);
+        std::process::exit(exitcode::IOERR);
+    }
+}
+
+/// Exits the program with `DATAERR` if an input was detected as something other than the `--expect` format.
+/// # Arguments
+/// * `filename` - the input that was checked
+/// * `expected_format` - the format given to `--expect`
+/// * `detected_format` - the format found from the contents
+fn check_expected_format(filename: &str, expected_format: InputFormat, detected_format: InputFormat) {
+    if detected_format != expected_format {
+        error!("Expected {} input but detected {}: {:?}", expected_format.name(), detected_format.name(), filename);
+        std::process::exit(exitcode::DATAERR);
+    }
+}
+
+/// Formats a value as JSON for standard output, on a single line if `compact` or pretty-printed otherwise
+fn format_json<T: Serialize>(value: &T, compact: bool) -> String {
+    if compact { serde_json::to_string(value).unwrap() } else { serde_json::to_string_pretty(value).unwrap() }
+}
+
+/// Saves text to an output file, exiting the program with `IOERR` if it cannot be written.
+/// # Arguments
+/// * `filename` - the path of the output file to create
+/// * `gzip_level` - the compression level for `.gz` outputs
+/// * `text` - the text to write
+fn save_text_output(filename: &str, gzip_level: u32, text: &str) {
+    let mut out_file = create_output_file(filename, gzip_level);
+    let write_result = out_file.write_all(text.as_bytes())
+        .and_then(|_| out_file.finish());
+    if let Err(e) = write_result {
+        error!("Failed to write output file: {:?}", filename);
+        error!("Error: {:?}", e);
+        std::process::exit(exitcode::IOERR);
+    }
+}
+
+/// Returns the path of an output artifact named by the `--output-dir` convention, i.e. `<output_dir>/<prefix>.<suffix>`.
+/// # Arguments
+/// * `output_dir` - the directory all of the artifacts are written to
+/// * `prefix` - the shared beginning of each artifact filename
+/// * `suffix` - the artifact-specific ending, e.g. "stats.json"
+fn output_dir_path(output_dir: &str, prefix: &str, suffix: &str) -> String {
+    std::path::Path::new(output_dir).join(format!("{prefix}.{suffix}")).to_string_lossy().to_string()
+}
+
+/// Initializes the binary's logger; this is the only place a logger is set up, the library only uses the `log` facade.
+/// The `RUST_LOG` environment variable still takes precedence over `default_level` if it is set.
+/// # Arguments
+/// * `default_level` - the log filter to use when `RUST_LOG` is not set, e.g. "info" or "warn"
+fn init_logging(default_level: &str) {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_level)).init();
+}
+
+/// Logs how long each phase of the run took, along with the loading throughput.
+/// # Arguments
+/// * `load_time` - the time spent loading the input files
+/// * `compute_time` - the time spent computing the statistics
+/// * `output_time` - the time spent writing the outputs
+/// * `total_records` - the number of records loaded
+/// * `total_bases` - the number of bases in the loaded records
+/// * `seq_type` - the kind of sequences loaded, for labeling the bases
+fn log_timing_summary(load_time: Duration, compute_time: Duration, output_time: Duration, total_records: u64, total_bases: u64, seq_type: SeqType) {
+    let load_seconds: f64 = load_time.as_secs_f64();
+    info!("Timing summary:");
+    info!("\tload: {:.3} s", load_seconds);
+    info!("\tcompute: {:.3} s", compute_time.as_secs_f64());
+    info!("\toutput: {:.3} s", output_time.as_secs_f64());
+    if load_seconds > 0.0 {
+        info!("\trecords/sec: {:.1}", total_records as f64 / load_seconds);
+        info!("\t{}/sec: {:.1}", seq_type.unit_label(), total_bases as f64 / load_seconds);
+    }
+}
+
+fn main() {
     let matches = App::new("fastleng")
         .version(VERSION.unwrap_or("?"))
         .author("J. Matthew Holt <mholt@pacificbiosciences.com>")
@@ -36,61 +383,1191 @@ fn main() {
             .takes_value(true)
             .help("Saves the length counts to a specified json")
         )
+        .arg(
+            Arg::with_name("length_json_detailed")
+            .long("--length-json-detailed")
+            .requires("length_json")
+            .help("Saves each length count with its sequence and base fractions in the length JSON")
+        )
+        .arg(
+            Arg::with_name("append")
+            .long("--append")
+            .requires("length_json")
+            .conflicts_with_all(&["length_json_detailed", "compare"])
+            .help("If the length JSON already exists, adds its counts to the new inputs before computing stats and saves the combined counts back")
+        )
+        .arg(
+            Arg::with_name("combined_output")
+            .long("--combined-output")
+            .takes_value(true)
+            .help("Saves the statistics and length counts together in a single JSON")
+        )
+        .arg(
+            Arg::with_name("expand_lengths")
+            .long("--expand-lengths")
+            .takes_value(true)
+            .help("Saves the sorted list of every individual sequence length to a JSON; intended for small inputs")
+        )
+        .arg(
+            Arg::with_name("expand_lengths_cap")
+            .long("--expand-lengths-cap")
+            .takes_value(true)
+            .requires("expand_lengths")
+            .help("The maximum number of sequences allowed for --expand-lengths (default: 10000000)")
+        )
+        .arg(
+            Arg::with_name("base_histogram")
+            .long("--base-histogram")
+            .takes_value(true)
+            .help("Saves the total number of bases at each length (i.e. length * count) to a JSON")
+        )
+        .arg(
+            Arg::with_name("log_histogram")
+            .long("--log-histogram")
+            .takes_value(true)
+            .help("Saves the number of sequences in logarithmic length bins, keyed by the lower bound of each bin, to a JSON; zero-length sequences are in bin 0")
+        )
+        .arg(
+            Arg::with_name("log_histogram_base")
+            .long("--log-histogram-base")
+            .takes_value(true)
+            .requires("log_histogram")
+            .help("The ratio between consecutive bin bounds for --log-histogram, at least 2 (default: 2)")
+        )
+        .arg(
+            Arg::with_name("nx_curve")
+            .long("--nx-curve")
+            .takes_value(true)
+            .help("Saves the cumulative base fraction for each length (longest first) for plotting Nx curves; CSV if the name ends with .csv, otherwise JSON")
+        )
+        .arg(
+            Arg::with_name("quality_by_length")
+            .long("--quality-by-length")
+            .takes_value(true)
+            .conflicts_with_all(&[
+                "per_record_lengths", "sample", "count_n", "interleaved", "keep_going", "group_by_regex", "by_read_group", "paired", "tar", "lengths_txt",
+                "validate_qual_length", "check_duplicate_names", "compare", "count_only", "approximate_percentiles"
+            ])
+            .help("Saves the mean Phred quality of the reads at each length as JSON, to show length-dependent quality; FASTQ inputs only")
+        )
+        .arg(
+            Arg::with_name("output_dir")
+            .long("--output-dir")
+            .takes_value(true)
+            .help("Writes the stats, length counts, base histogram, and Nx curve into this directory (created if needed) as <prefix>.stats.json, <prefix>.lengths.json, <prefix>.base_histogram.json, and <prefix>.nx_curve.json; any explicit output path takes precedence")
+        )
+        .arg(
+            Arg::with_name("prefix")
+            .long("--prefix")
+            .takes_value(true)
+            .requires("output_dir")
+            .help("The filename prefix for the files written to --output-dir (default: fastleng)")
+        )
+        .arg(
+            Arg::with_name("per_record_lengths")
+            .long("--per-record-lengths")
+            .takes_value(true)
+            .help("Saves the name and length of every record as JSON Lines")
+        )
+        .arg(
+            Arg::with_name("null_delimited")
+            .long("--null-delimited")
+            .requires("per_record_lengths")
+            .help("Ends each per-record output line with a NUL byte instead of a newline, for piping into xargs -0")
+        )
+        .arg(
+            Arg::with_name("gzip_level")
+            .long("--gzip-level")
+            .takes_value(true)
+            .help("The compression level (0-9) for output files ending in .gz (default: 6)")
+        )
+        .arg(
+            Arg::with_name("compact")
+            .long("--compact")
+            .help("Writes every JSON output on a single line instead of pretty-printing it, which makes large length counts much smaller")
+        )
+        .arg(
+            Arg::with_name("sample")
+            .long("--sample")
+            .takes_value(true)
+            .conflicts_with("per_record_lengths")
+            .help("Approximates the stats by including each record with this probability (0.0-1.0)")
+        )
+        .arg(
+            Arg::with_name("seed")
+            .long("--seed")
+            .takes_value(true)
+            .help("The random seed used for sampling, bootstrapping, and --reservoir (default: 0)")
+        )
+        .arg(
+            Arg::with_name("sample_files")
+            .long("--sample-files")
+            .takes_value(true)
+            .conflicts_with_all(&["compare", "paired", "tar", "lengths_txt"])
+            .help("Only loads a random subset of the input files, this fraction of them (0.0-1.0), selected with --seed; the selected files are logged")
+        )
+        .arg(
+            Arg::with_name("count_n")
+            .long("--count-n")
+            .conflicts_with_all(&["per_record_lengths", "sample"])
+            .help("Counts N bases and reports ungapped (N-removed) stats for FASTX files; slower")
+        )
+        .arg(
+            Arg::with_name("count_ambiguous")
+            .long("--count-ambiguous")
+            .conflicts_with_all(&[
+                "per_record_lengths", "sample", "count_n", "interleaved", "keep_going", "group_by_regex", "by_read_group", "quality_by_length", "paired", "tar",
+                "lengths_txt", "validate_qual_length", "check_duplicate_names", "exclude_length", "length_range", "compare", "count_only", "approximate_percentiles"
+            ])
+            .help("Counts each IUPAC ambiguity code (B, D, H, K, M, N, R, S, V, W, Y; either case) across all FASTX sequences; slower")
+        )
+        .arg(
+            Arg::with_name("interleaved")
+            .long("--interleaved")
+            .conflicts_with_all(&["per_record_lengths", "sample", "count_n"])
+            .help("Treats each file as interleaved pairs and counts the combined length of each pair")
+        )
+        .arg(
+            Arg::with_name("min_mean_qual")
+            .long("--min-mean-qual")
+            .takes_value(true)
+            .conflicts_with_all(&["count_n", "interleaved", "keep_going"])
+            .help("Skips FASTQ records with a mean Phred score below this value; inputs without qualities are an error")
+        )
+        .arg(
+            Arg::with_name("check_duplicate_names")
+            .long("--check-duplicate-names")
+            .conflicts_with_all(&["per_record_lengths", "count_n", "interleaved", "keep_going"])
+            .help("Counts the records whose name was already seen in any input; keeps every unique name in memory")
+        )
+        .arg(
+            Arg::with_name("validate_qual_length")
+            .long("--validate-qual-length")
+            .conflicts_with_all(&["count_n", "interleaved", "keep_going", "paired", "tar", "compare", "count_only", "approximate_percentiles"])
+            .help("Counts FASTQ records whose sequence and quality lengths differ and skips them instead of failing; the records after them are still loaded")
+        )
+        .arg(
+            Arg::with_name("group_by_regex")
+            .long("--group-by-regex")
+            .takes_value(true)
+            .conflicts_with_all(&["per_record_lengths", "count_n", "interleaved", "keep_going", "fast"])
+            .help("Also reports the stats of each group of records, where the group is the first capture group of this regex applied to the record name")
+        )
+        .arg(
+            Arg::with_name("by_read_group")
+            .long("--by-read-group")
+            .conflicts_with_all(&[
+                "group_by_regex", "per_record_lengths", "count_n", "interleaved", "keep_going", "fast", "paired", "tar", "lengths_txt",
+                "min_mean_qual", "check_duplicate_names", "validate_qual_length", "compare", "count_only", "approximate_percentiles"
+            ])
+            .help("Also reports the stats of each read group, from the RG tag of each BAM/SAM record; all inputs must be alignment files")
+        )
+        .arg(
+            Arg::with_name("exclude_length")
+            .long("--exclude-length")
+            .takes_value(true)
+            .multiple(true)
+            .use_delimiter(true)
+            .number_of_values(1)
+            .help("Removes sequences with these exact lengths (comma-separated) before computing stats")
+        )
+        .arg(
+            Arg::with_name("length_range")
+            .long("--length-range")
+            .takes_value(true)
+            .help("Only computes stats over sequences with lengths in this inclusive window, given as MIN:MAX")
+        )
+        .arg(
+            Arg::with_name("drop_longest")
+            .long("--drop-longest")
+            .takes_value(true)
+            .value_name("K")
+            .conflicts_with_all(&["count_n", "group_by_regex", "by_read_group", "tar", "reservoir", "quality_by_length", "compare", "count_only", "approximate_percentiles"])
+            .help("Removes the K longest sequences (e.g. a chimeric mega-read) before computing the stats and reports their lengths, so the stats describe the bulk of the distribution")
+        )
+        .arg(
+            Arg::with_name("format")
+            .long("--format")
+            .takes_value(true)
+            .possible_values(&["json", "markdown", "assembly-stats"])
+            .help("The format of the main summary output; markdown renders only the summary statistics as a table, and assembly-stats mimics the text output of that tool (default: json)")
+        )
+        .arg(
+            Arg::with_name("json_case")
+            .long("--json-case")
+            .takes_value(true)
+            .possible_values(&["snake", "camel"])
+            .help("The naming convention for the field names of the summary JSON outputs, e.g. total_bases or totalBases (default: snake)")
+        )
+        .arg(
+            Arg::with_name("seq_type")
+            .long("--seq-type")
+            .takes_value(true)
+            .possible_values(&["dna", "rna", "protein"])
+            .help("The kind of sequences in the inputs, which labels the units (e.g. residues for protein) in the logs and text outputs and is recorded in the stats JSON; the numbers are unchanged (default: dna)")
+        )
+        .arg(
+            Arg::with_name("expect")
+            .long("--expect")
+            .takes_value(true)
+            .possible_values(&["fasta", "fastq", "sam", "bam", "cram"])
+            .conflicts_with_all(&["lengths_txt", "tar"])
+            .help("Fails unless every input's contents are in this format, e.g. to stop a FASTA pipeline that was given FASTQ; files are checked before loading and pipes once they are read")
+        )
+        .arg(
+            Arg::with_name("fast")
+            .long("--fast")
+            .conflicts_with_all(&["count_n", "genome_size", "reference"])
+            .help("Only computes the totals, mean, median, min, and max lengths, skipping the N-scores and other metrics")
+        )
+        .arg(
+            Arg::with_name("interpolate_percentiles")
+            .long("--interpolate-percentiles")
+            .conflicts_with("fast")
+            .help("Linearly interpolates the median between the two middle lengths (like numpy) instead of reporting an observed length")
+        )
+        .arg(
+            Arg::with_name("round_decimals")
+            .long("--round-decimals")
+            .takes_value(true)
+            .help("Rounds all floating point statistics in the JSON outputs to this many decimal places (default: full precision)")
+        )
+        .arg(
+            Arg::with_name("genome_size")
+            .long("--genome-size")
+            .takes_value(true)
+            .conflicts_with("reference")
+            .help("The expected genome size in bases, enables NG-score (e.g. NG50) outputs")
+        )
+        .arg(
+            Arg::with_name("reference")
+            .long("--reference")
+            .takes_value(true)
+            .help("A reference FASTX file whose total length is used as the expected genome size for NG-score outputs")
+        )
+        .arg(
+            Arg::with_name("n50_min_length")
+            .long("--n50-min-length")
+            .takes_value(true)
+            .conflicts_with_all(&["compare", "count_only", "approximate_percentiles"])
+            .help("Also reports the N50 of only the sequences at least this long, e.g. 10000 for the N50 of contigs >= 10kb")
+        )
+        .arg(
+            Arg::with_name("stats")
+            .long("--stats")
+            .takes_value(true)
+            .multiple(true)
+            .use_delimiter(true)
+            .number_of_values(1)
+            .conflicts_with_all(&["fast", "compare", "count_only", "approximate_percentiles"])
+            .help("Only computes and reports these statistics (comma-separated), e.g. n50,median,mean; the _length suffix is optional (default: all)")
+        )
+        .arg(
+            Arg::with_name("top_fractions")
+            .long("--top-fractions")
+            .takes_value(true)
+            .multiple(true)
+            .use_delimiter(true)
+            .number_of_values(1)
+            .conflicts_with_all(&["compare", "count_only", "approximate_percentiles"])
+            .help("Also reports the totals and mean length of the longest fraction of sequences for each of these fractions (comma-separated), e.g. 0.1,0.25 for the longest 10% and 25%")
+        )
+        .arg(
+            Arg::with_name("cover_fractions")
+            .long("--cover-fractions")
+            .takes_value(true)
+            .multiple(true)
+            .use_delimiter(true)
+            .number_of_values(1)
+            .conflicts_with_all(&["compare", "count_only", "approximate_percentiles"])
+            .help("Also reports how many of the longest sequences, and their combined length, are needed to cover each of these fractions of the bases (comma-separated), e.g. 0.5,0.9")
+        )
+        .arg(
+            Arg::with_name("bootstrap")
+            .long("--bootstrap")
+            .takes_value(true)
+            .conflicts_with_all(&["compare", "count_only", "approximate_percentiles"])
+            .help("Also reports a 95% confidence interval of the N50 from this many bootstrap replicates (e.g. 1000), using --seed; each replicate redraws every sequence, so this is meant for assemblies")
+        )
+        .arg(
+            Arg::with_name("top")
+            .long("--top")
+            .takes_value(true)
+            .value_name("N")
+            .conflicts_with_all(&["compare", "count_only", "approximate_percentiles"])
+            .help("Also reports the N longest individual sequence lengths, longest first, e.g. to check that the longest reads are plausible")
+        )
+        .arg(
+            Arg::with_name("reservoir")
+            .long("--reservoir")
+            .takes_value(true)
+            .value_name("N")
+            .conflicts_with_all(&[
+                "per_record_lengths", "sample", "count_n", "interleaved", "keep_going", "group_by_regex", "by_read_group", "quality_by_length", "count_ambiguous",
+                "paired", "tar", "lengths_txt", "check_duplicate_names", "exclude_length", "length_range", "compare", "count_only", "approximate_percentiles"
+            ])
+            .help("Also reports a random sample of N individual sequence lengths (reproducible with --seed) kept while streaming, e.g. for density plots of huge inputs")
+        )
+        .arg(
+            Arg::with_name("detect_peaks")
+            .long("--detect-peaks")
+            .takes_value(true)
+            .value_name("MIN_PROMINENCE")
+            .conflicts_with_all(&["compare", "count_only", "approximate_percentiles"])
+            .help("Also reports the peaks of the length histogram that stand at least this many sequences above the dip to any higher peak, e.g. to flag a short contaminant or adapter-dimer mode")
+        )
+        .arg(
+            Arg::with_name("head")
+            .long("--head")
+            .takes_value(true)
+            .conflicts_with_all(&["sample", "count_n", "interleaved", "keep_going"])
+            .help("Only reads the first N records of each file; the stats are marked as approximate")
+        )
+        .arg(
+            Arg::with_name("max_records")
+            .long("--max-records")
+            .takes_value(true)
+            .conflicts_with_all(&["count_n", "interleaved", "keep_going"])
+            .help("Fails with an error if any input file contains more than N records, instead of silently truncating like --head")
+        )
+        .arg(
+            Arg::with_name("keep_going")
+            .long("--keep-going")
+            .conflicts_with_all(&["per_record_lengths", "sample", "count_n", "interleaved"])
+            .help("Skips input files that fail to load instead of aborting, exiting with code 3 if any were skipped")
+        )
+        .arg(
+            Arg::with_name("compare")
+            .long("--compare")
+            .takes_value(true)
+            .number_of_values(2)
+            .value_names(&["BASELINE", "COMPARISON"])
+            .conflicts_with_all(&[
+                "FASTX", "length_json", "combined_output", "expand_lengths", "base_histogram", "log_histogram", "nx_curve", "per_record_lengths",
+                "count_n", "interleaved", "exclude_length", "length_range", "format", "fast", "genome_size", "reference", "keep_going"
+            ])
+            .help("Computes the stats of two files and reports the absolute and percent change of each stat from the first to the second")
+        )
+        .arg(
+            Arg::with_name("paired")
+            .long("--paired")
+            .takes_value(true)
+            .number_of_values(2)
+            .value_names(&["R1", "R2"])
+            .conflicts_with_all(&[
+                "FASTX", "compare", "tar", "per_record_lengths", "sample", "count_n", "interleaved", "keep_going", "group_by_regex",
+                "check_duplicate_names", "min_mean_qual", "max_records", "count_only"
+            ])
+            .help("Reads a pair of R1/R2 FASTX files in lockstep and counts the combined length of each read pair; the files must have the same number of records")
+        )
+        .arg(
+            Arg::with_name("tar")
+            .long("--tar")
+            .takes_value(true)
+            .conflicts_with_all(&[
+                "FASTX", "compare", "per_record_lengths", "sample", "count_n", "interleaved", "keep_going", "group_by_regex", "check_duplicate_names"
+            ])
+            .help("Loads the FASTX members of a tar archive (gzip accepted) instead of the positional inputs, and also reports the stats of each member")
+        )
+        .arg(
+            Arg::with_name("lengths_txt")
+            .long("--lengths-txt")
+            .takes_value(true)
+            .conflicts_with_all(&[
+                "FASTX", "compare", "tar", "paired", "per_record_lengths", "sample", "count_n", "interleaved", "keep_going", "group_by_regex",
+                "check_duplicate_names", "min_mean_qual", "validate_qual_length", "head", "max_records", "region", "count_only", "approximate_percentiles"
+            ])
+            .help("Reads the lengths from a text file with one integer length per line (gzip accepted) instead of parsing sequences from the positional inputs")
+        )
+        .arg(
+            Arg::with_name("assert_sorted")
+            .long("--assert-sorted")
+            .requires("lengths_txt")
+            .help("Fails if the --lengths-txt lengths are not in ascending order, naming the first out-of-order line; a guard for pre-sorted upstream output")
+        )
+        .arg(
+            Arg::with_name("count_only")
+            .long("--count-only")
+            .conflicts_with_all(&[
+                "length_json", "combined_output", "expand_lengths", "base_histogram", "log_histogram", "nx_curve", "per_record_lengths", "count_n", "interleaved",
+                "check_duplicate_names", "group_by_regex", "exclude_length", "length_range", "format", "fast", "interpolate_percentiles",
+                "genome_size", "reference", "keep_going", "compare", "tar"
+            ])
+            .help("Only outputs the total bases and total sequences as two tab-separated numbers, skipping the length counts entirely")
+        )
+        .arg(
+            Arg::with_name("approximate_percentiles")
+            .long("--approximate-percentiles")
+            .conflicts_with_all(&[
+                "length_json", "combined_output", "expand_lengths", "base_histogram", "log_histogram", "nx_curve", "per_record_lengths", "count_n", "interleaved",
+                "check_duplicate_names", "group_by_regex", "exclude_length", "length_range", "format", "fast", "interpolate_percentiles",
+                "genome_size", "reference", "keep_going", "compare", "tar", "paired", "append", "count_only"
+            ])
+            .help("Estimates the median and other percentiles from a fixed-size t-digest sketch instead of storing every length, for inputs too large to count exactly; the totals, mean, min, and max are still exact")
+        )
+        .arg(
+            Arg::with_name("quiet")
+            .short("q")
+            .long("--quiet")
+            .help("Only logs warnings and errors, and skips the one-line summary printed to stderr at the end")
+        )
+        .arg(
+            Arg::with_name("version_json")
+            .long("--version-json")
+            .help("Prints the version, git commit, build timestamp, and rustc version of this build as JSON and exits")
+        )
+        .arg(
+            Arg::with_name("fail_on_empty")
+            .long("--fail-on-empty")
+            .help("Exits with an error instead of reporting empty stats if no sequences are left after loading and filtering, e.g. to halt a pipeline after an upstream failure")
+        )
+        .arg(
+            Arg::with_name("same_length_warning")
+            .long("--same-length-warning")
+            .takes_value(true)
+            .help("Warns if every sequence has the same length and that length is at least this long, which is suspicious for long reads or assemblies; 0 disables the warning (default: 1000)")
+        )
+        .arg(
+            Arg::with_name("distinct_length_warning")
+            .long("--distinct-length-warning")
+            .takes_value(true)
+            .value_name("N")
+            .help("Warns once the length counts hold more than this many distinct lengths, since memory grows with each one; 0 disables the warning (default: 1000000)")
+        )
+        .arg(
+            Arg::with_name("progress_interval")
+            .long("--progress-interval")
+            .takes_value(true)
+            .conflicts_with_all(&["count_n", "interleaved", "keep_going"])
+            .help("The number of records between progress messages while loading each file; 0 disables them (default: 1000000)")
+        )
+        .arg(
+            Arg::with_name("decompression_threads")
+            .long("--decompression-threads")
+            .takes_value(true)
+            .help("The number of threads used to decompress each BGZF compressed FASTX file; requires building with the parallel-bgzf feature (default: 1)")
+        )
+        .arg(
+            Arg::with_name("sqlite")
+            .long("--sqlite")
+            .takes_value(true)
+            .value_name("DB")
+            .conflicts_with_all(&["fast", "stats", "compare", "count_only", "approximate_percentiles"])
+            .help("Inserts the stats as a new row of the fastleng_stats table in this SQLite database, creating the table and any missing columns as needed; requires building with the sqlite feature")
+        )
+        .arg(
+            Arg::with_name("timing")
+            .long("--timing")
+            .help("Logs the time spent loading, computing stats, and writing outputs, along with the loading throughput")
+        )
+        .arg(
+            Arg::with_name("include_hardclips")
+            .long("--include-hardclips")
+            .help("For aligned BAM/SAM files, adds hard-clipped bases back to the read length")
+        )
+        .arg(
+            Arg::with_name("unique_reads")
+            .long("--unique-reads")
+            .help("For BAM/SAM files, counts only the primary record of each read name")
+        )
+        .arg(
+            Arg::with_name("region")
+            .long("--region")
+            .takes_value(true)
+            .conflicts_with_all(&["paired", "tar", "interleaved", "count_n"])
+            .help("Only counts the records overlapping this samtools-style region (e.g. chr1:1000-2000); all inputs must be coordinate-sorted and indexed BAM/CRAM")
+        )
         .arg(
             Arg::with_name("FASTX")
-                .help("The FASTQ/A or uBAM file(s) to gather stats on, gzip accepted")
-                .required(true)
+                .help("The FASTQ/A or unaligned SAM/BAM/CRAM file(s) to gather stats on, gzip accepted; FASTQ/A can also be read from \"-\" (stdin) or a pipe")
+                .required_unless_one(&["compare", "tar", "paired", "lengths_txt", "version_json"])
                 .multiple(true)
                 .index(1)
         )
         .get_matches();
 
-    let fastx_fns: Vec<String> = values_t!(matches.values_of("FASTX"), String).unwrap_or_else(|_| vec![]);
-    let out_fn: String = value_t!(matches.value_of("out_json"), String).unwrap_or_else(|_| "stdout".to_string());
-    let length_fn: String = value_t!(matches.value_of("length_json"), String).unwrap_or_else(|_| "".to_string());
+    //this replaces any other work, like --version does
+    if matches.is_present("version_json") {
+        let build_info = BuildInfo {
+            version: VERSION.unwrap_or("?"),
+            git_commit: env!("FASTLENG_GIT_COMMIT"),
+            build_timestamp: env!("FASTLENG_BUILD_TIMESTAMP"),
+            rustc_version: env!("FASTLENG_RUSTC_VERSION")
+        };
+        println!("{}", format_json(&build_info, matches.is_present("compact")));
+        std::process::exit(exitcode::OK);
+    }
+
+    //initialize logging for our benefit later
+    let quiet: bool = matches.is_present("quiet");
+    init_logging(if quiet { "warn" } else { "info" });
+
+    let compare_fns: Vec<String> = values_t!(matches.values_of("compare"), String).unwrap_or_else(|_| vec![]);
+    let paired_fns: Vec<String> = values_t!(matches.values_of("paired"), String).unwrap_or_else(|_| vec![]);
+    let tar_fn: String = value_t!(matches.value_of("tar"), String).unwrap_or_else(|_| "".to_string());
+    let lengths_txt_fn: String = value_t!(matches.value_of("lengths_txt"), String).unwrap_or_else(|_| "".to_string());
+    let assert_sorted: bool = matches.is_present("assert_sorted");
+    //in compare, paired, tar, and lengths-txt modes, those files are the inputs
+    let mut fastx_fns: Vec<String> = if !compare_fns.is_empty() {
+        compare_fns.clone()
+    } else if !paired_fns.is_empty() {
+        paired_fns.clone()
+    } else if !tar_fn.is_empty() {
+        vec![tar_fn.clone()]
+    } else if !lengths_txt_fn.is_empty() {
+        vec![lengths_txt_fn.clone()]
+    } else {
+        values_t!(matches.values_of("FASTX"), String).unwrap_or_else(|_| vec![])
+    };
+    let output_dir: String = value_t!(matches.value_of("output_dir"), String).unwrap_or_else(|_| "".to_string());
+    let prefix: String = value_t!(matches.value_of("prefix"), String).unwrap_or_else(|_| "fastleng".to_string());
+    //with an output directory, every artifact without an explicit path gets the conventional name instead of being skipped
+    let default_fn = |suffix: &str, fallback: &str| -> String {
+        if output_dir.is_empty() { fallback.to_string() } else { output_dir_path(&output_dir, &prefix, suffix) }
+    };
+    let out_fn: String = value_t!(matches.value_of("out_json"), String).unwrap_or_else(|_| default_fn("stats.json", "stdout"));
+    let length_fn: String = value_t!(matches.value_of("length_json"), String).unwrap_or_else(|_| default_fn("lengths.json", ""));
+    let length_detailed: bool = matches.is_present("length_json_detailed");
+    let append: bool = matches.is_present("append");
+    let combined_fn: String = value_t!(matches.value_of("combined_output"), String).unwrap_or_else(|_| "".to_string());
+    let expand_lengths_fn: String = value_t!(matches.value_of("expand_lengths"), String).unwrap_or_else(|_| "".to_string());
+    let expand_lengths_cap: u64 = if matches.is_present("expand_lengths_cap") { value_t!(matches, "expand_lengths_cap", u64).unwrap_or_else(|e| e.exit()) } else { DEFAULT_EXPAND_LENGTHS_CAP };
+    let base_histogram_fn: String = value_t!(matches.value_of("base_histogram"), String).unwrap_or_else(|_| default_fn("base_histogram.json", ""));
+    let log_histogram_fn: String = value_t!(matches.value_of("log_histogram"), String).unwrap_or_else(|_| "".to_string());
+    let log_histogram_base: usize = if matches.is_present("log_histogram_base") { value_t!(matches, "log_histogram_base", usize).unwrap_or_else(|e| e.exit()) } else { DEFAULT_LOG_HISTOGRAM_BASE };
+    let nx_curve_fn: String = value_t!(matches.value_of("nx_curve"), String).unwrap_or_else(|_| default_fn("nx_curve.json", ""));
+    let quality_by_length_fn: String = value_t!(matches.value_of("quality_by_length"), String).unwrap_or_else(|_| "".to_string());
+    let per_record_fn: String = value_t!(matches.value_of("per_record_lengths"), String).unwrap_or_else(|_| "".to_string());
+    let null_delimited: bool = matches.is_present("null_delimited");
+    let compact: bool = matches.is_present("compact");
+    let gzip_level: u32 = if matches.is_present("gzip_level") { value_t!(matches, "gzip_level", u32).unwrap_or_else(|e| e.exit()) } else { DEFAULT_GZIP_LEVEL };
+    let sample_fraction: Option<f64> = if matches.is_present("sample") { Some(value_t!(matches, "sample", f64).unwrap_or_else(|e| e.exit())) } else { None };
+    let seed: u64 = if matches.is_present("seed") { value_t!(matches, "seed", u64).unwrap_or_else(|e| e.exit()) } else { 0 };
+    let sample_files_fraction: Option<f64> = if matches.is_present("sample_files") { Some(value_t!(matches, "sample_files", f64).unwrap_or_else(|e| e.exit())) } else { None };
+    if let Some(fraction) = sample_files_fraction {
+        if !(0.0..=1.0).contains(&fraction) {
+            error!("--sample-files must be in the range 0.0-1.0, got {}", fraction);
+            std::process::exit(exitcode::USAGE);
+        }
+        let all_fns_count: usize = fastx_fns.len();
+        fastx_fns = sample_files(&fastx_fns, fraction, seed);
+        info!("Selected {} of {} input files with --sample-files (seed {}): {:?}", fastx_fns.len(), all_fns_count, seed, fastx_fns);
+    }
+    let count_n: bool = matches.is_present("count_n");
+    let count_ambiguous: bool = matches.is_present("count_ambiguous");
+    let interleaved: bool = matches.is_present("interleaved");
+    let min_mean_qual: Option<f64> = if matches.is_present("min_mean_qual") { Some(value_t!(matches, "min_mean_qual", f64).unwrap_or_else(|e| e.exit())) } else { None };
+    let check_duplicate_names: bool = matches.is_present("check_duplicate_names");
+    let validate_qual_length: bool = matches.is_present("validate_qual_length");
+    let group_pattern: String = value_t!(matches.value_of("group_by_regex"), String).unwrap_or_else(|_| "".to_string());
+    let by_read_group: bool = matches.is_present("by_read_group");
+    let excluded_lengths: Vec<usize> = if matches.is_present("exclude_length") { values_t!(matches, "exclude_length", usize).unwrap_or_else(|e| e.exit()) } else { vec![] };
+    let length_range: Option<LengthRange> = matches.value_of("length_range").map(|range_str| {
+        range_str.parse().unwrap_or_else(|e| {
+            error!("Invalid --length-range: {}", e);
+            std::process::exit(exitcode::USAGE);
+        })
+    });
+    let drop_longest: Option<usize> = if matches.is_present("drop_longest") { Some(value_t!(matches, "drop_longest", usize).unwrap_or_else(|e| e.exit())) } else { None };
+    let output_format: String = matches.value_of("format").unwrap_or("json").to_string();
+    let json_case: JsonCase = if matches.value_of("json_case") == Some("camel") { JsonCase::Camel } else { JsonCase::Snake };
+    let seq_type_arg: Option<SeqType> = matches.value_of("seq_type").and_then(SeqType::from_name);
+    let expected_format: Option<InputFormat> = matches.value_of("expect").and_then(InputFormat::from_name);
+    let seq_type: SeqType = seq_type_arg.unwrap_or_default();
+    let fast: bool = matches.is_present("fast");
+    let interpolate_percentiles: bool = matches.is_present("interpolate_percentiles");
+    let round_decimals: Option<u32> = if matches.is_present("round_decimals") { Some(value_t!(matches, "round_decimals", u32).unwrap_or_else(|e| e.exit())) } else { None };
+    let genome_size_arg: Option<u64> = if matches.is_present("genome_size") { Some(value_t!(matches, "genome_size", u64).unwrap_or_else(|e| e.exit())) } else { None };
+    let n50_min_length: Option<usize> = if matches.is_present("n50_min_length") { Some(value_t!(matches, "n50_min_length", usize).unwrap_or_else(|e| e.exit())) } else { None };
+    let stat_names: Vec<String> = if matches.is_present("stats") { values_t!(matches, "stats", String).unwrap_or_else(|e| e.exit()) } else { vec![] };
+    if let Some(bad_name) = stat_names.iter().find(|stat_name| resolve_stat_name(stat_name).is_none()) {
+        error!("Unknown --stats value {:?}, expected one of: {}", bad_name, LENGTH_STAT_NAMES.join(", "));
+        std::process::exit(exitcode::USAGE);
+    }
+    let top_fractions: Vec<f64> = if matches.is_present("top_fractions") { values_t!(matches, "top_fractions", f64).unwrap_or_else(|e| e.exit()) } else { vec![] };
+    if let Some(bad_fraction) = top_fractions.iter().find(|&&fraction| fraction <= 0.0 || fraction > 1.0) {
+        error!("Invalid --top-fractions value {}, each must be in the range (0.0, 1.0]", bad_fraction);
+        std::process::exit(exitcode::USAGE);
+    }
+    let cover_fractions: Vec<f64> = if matches.is_present("cover_fractions") { values_t!(matches, "cover_fractions", f64).unwrap_or_else(|e| e.exit()) } else { vec![] };
+    if let Some(bad_fraction) = cover_fractions.iter().find(|&&fraction| fraction.is_nan() || fraction <= 0.0 || fraction > 1.0) {
+        error!("Invalid --cover-fractions value {}, each must be in the range (0.0, 1.0]", bad_fraction);
+        std::process::exit(exitcode::USAGE);
+    }
+    let reservoir_size: Option<usize> = if matches.is_present("reservoir") { Some(value_t!(matches, "reservoir", usize).unwrap_or_else(|e| e.exit())) } else { None };
+    if reservoir_size == Some(0) {
+        error!("--reservoir must be at least 1");
+        std::process::exit(exitcode::USAGE);
+    }
+    let top_count: Option<usize> = if matches.is_present("top") { Some(value_t!(matches, "top", usize).unwrap_or_else(|e| e.exit())) } else { None };
+    let peak_prominence: Option<u64> = if matches.is_present("detect_peaks") { Some(value_t!(matches, "detect_peaks", u64).unwrap_or_else(|e| e.exit())) } else { None };
+    let bootstrap_replicates: Option<usize> = if matches.is_present("bootstrap") { Some(value_t!(matches, "bootstrap", usize).unwrap_or_else(|e| e.exit())) } else { None };
+    if bootstrap_replicates == Some(0) {
+        error!("--bootstrap must have at least 1 replicate");
+        std::process::exit(exitcode::USAGE);
+    }
+    let reference_fn: String = value_t!(matches.value_of("reference"), String).unwrap_or_else(|_| "".to_string());
+    let head: Option<usize> = if matches.is_present("head") { Some(value_t!(matches, "head", usize).unwrap_or_else(|e| e.exit())) } else { None };
+    let max_records: Option<usize> = if matches.is_present("max_records") { Some(value_t!(matches, "max_records", usize).unwrap_or_else(|e| e.exit())) } else { None };
+    let keep_going: bool = matches.is_present("keep_going");
+    let count_only: bool = matches.is_present("count_only");
+    let approximate_percentiles: bool = matches.is_present("approximate_percentiles");
+    let distinct_length_warning: usize = if matches.is_present("distinct_length_warning") { value_t!(matches, "distinct_length_warning", usize).unwrap_or_else(|e| e.exit()) } else { DEFAULT_DISTINCT_LENGTH_WARNING };
+    let progress_interval: usize = if matches.is_present("progress_interval") { value_t!(matches, "progress_interval", usize).unwrap_or_else(|e| e.exit()) } else { DEFAULT_PROGRESS_INTERVAL };
+    let decompression_threads: usize = if matches.is_present("decompression_threads") { value_t!(matches, "decompression_threads", usize).unwrap_or_else(|e| e.exit()) } else { 1 };
+    let sqlite_fn: String = value_t!(matches.value_of("sqlite"), String).unwrap_or_else(|_| "".to_string());
+    let timing: bool = matches.is_present("timing");
+    let fail_on_empty: bool = matches.is_present("fail_on_empty");
+    let same_length_warning: usize = if matches.is_present("same_length_warning") { value_t!(matches, "same_length_warning", usize).unwrap_or_else(|e| e.exit()) } else { DEFAULT_SAME_LENGTH_WARNING };
+    let include_hardclips: bool = matches.is_present("include_hardclips");
+    let unique_reads: bool = matches.is_present("unique_reads");
+    let region: Option<String> = matches.value_of("region").map(|region| region.to_string());
 
     info!("Input parameters (required):");
     info!("\tFASTX: {:?}", fastx_fns);
     info!("Optional Parameters:");
+    info!("\toutput_dir: {:?}", output_dir);
+    info!("\tprefix: {:?}", prefix);
     info!("\tout_json: {:?}", out_fn);
     info!("\tlength_json: {:?}", length_fn);
+    info!("\tlength_json_detailed: {:?}", length_detailed);
+    info!("\tappend: {:?}", append);
+    info!("\tcombined_output: {:?}", combined_fn);
+    info!("\texpand_lengths: {:?}", expand_lengths_fn);
+    info!("\texpand_lengths_cap: {:?}", expand_lengths_cap);
+    info!("\tbase_histogram: {:?}", base_histogram_fn);
+    info!("\tlog_histogram: {:?}", log_histogram_fn);
+    info!("\tlog_histogram_base: {:?}", log_histogram_base);
+    info!("\tnx_curve: {:?}", nx_curve_fn);
+    info!("\tquality_by_length: {:?}", quality_by_length_fn);
+    info!("\tper_record_lengths: {:?}", per_record_fn);
+    info!("\tnull_delimited: {:?}", null_delimited);
+    info!("\tgzip_level: {:?}", gzip_level);
+    info!("\tcompact: {:?}", compact);
+    info!("\tsample: {:?}", sample_fraction);
+    info!("\tseed: {:?}", seed);
+    info!("\tsample_files: {:?}", sample_files_fraction);
+    info!("\tcount_n: {:?}", count_n);
+    info!("\tcount_ambiguous: {:?}", count_ambiguous);
+    info!("\tinterleaved: {:?}", interleaved);
+    info!("\tmin_mean_qual: {:?}", min_mean_qual);
+    info!("\tcheck_duplicate_names: {:?}", check_duplicate_names);
+    info!("\tvalidate_qual_length: {:?}", validate_qual_length);
+    info!("\tgroup_by_regex: {:?}", group_pattern);
+    info!("\tby_read_group: {:?}", by_read_group);
+    info!("\texclude_length: {:?}", excluded_lengths);
+    info!("\tlength_range: {:?}", length_range);
+    info!("\tdrop_longest: {:?}", drop_longest);
+    info!("\tformat: {:?}", output_format);
+    info!("\tjson_case: {:?}", json_case);
+    info!("\tseq_type: {:?}", seq_type);
+    info!("\texpect: {:?}", expected_format);
+    info!("\tfast: {:?}", fast);
+    info!("\tinterpolate_percentiles: {:?}", interpolate_percentiles);
+    info!("\tround_decimals: {:?}", round_decimals);
+    info!("\tgenome_size: {:?}", genome_size_arg);
+    info!("\treference: {:?}", reference_fn);
+    info!("\tn50_min_length: {:?}", n50_min_length);
+    info!("\tstats: {:?}", stat_names);
+    info!("\ttop_fractions: {:?}", top_fractions);
+    info!("\tcover_fractions: {:?}", cover_fractions);
+    info!("\tbootstrap: {:?}", bootstrap_replicates);
+    info!("\ttop: {:?}", top_count);
+    info!("\treservoir: {:?}", reservoir_size);
+    info!("\tdetect_peaks: {:?}", peak_prominence);
+    info!("\thead: {:?}", head);
+    info!("\tmax_records: {:?}", max_records);
+    info!("\tkeep_going: {:?}", keep_going);
+    info!("\tfail_on_empty: {:?}", fail_on_empty);
+    info!("\tcompare: {:?}", compare_fns);
+    info!("\tpaired: {:?}", paired_fns);
+    info!("\ttar: {:?}", tar_fn);
+    info!("\tlengths_txt: {:?}", lengths_txt_fn);
+    info!("\tassert_sorted: {:?}", assert_sorted);
+    info!("\tcount_only: {:?}", count_only);
+    info!("\tapproximate_percentiles: {:?}", approximate_percentiles);
+    info!("\tdistinct_length_warning: {:?}", distinct_length_warning);
+    info!("\tprogress_interval: {:?}", progress_interval);
+    info!("\tdecompression_threads: {:?}", decompression_threads);
+    info!("\tsqlite: {:?}", sqlite_fn);
+    info!("\ttiming: {:?}", timing);
+    info!("\tsame_length_warning: {:?}", same_length_warning);
+    info!("\tinclude_hardclips: {:?}", include_hardclips);
+    info!("\tunique_reads: {:?}", unique_reads);
+    info!("\tregion: {:?}", region);
+
+    //check parameters
+    if gzip_level > 9 {
+        error!("--gzip-level must be in the range 0-9, got {}", gzip_level);
+        std::process::exit(exitcode::USAGE);
+    }
+    if decompression_threads == 0 {
+        error!("--decompression-threads must be at least 1");
+        std::process::exit(exitcode::USAGE);
+    }
+    if decompression_threads > 1 && !cfg!(feature = "parallel-bgzf") {
+        warn!("--decompression-threads has no effect unless fastleng is built with the parallel-bgzf feature");
+    }
+    //unlike the threads, silently skipping the database would lose the results
+    if !sqlite_fn.is_empty() && !cfg!(feature = "sqlite") {
+        error!("--sqlite requires fastleng to be built with the sqlite feature");
+        std::process::exit(exitcode::USAGE);
+    }
+    if log_histogram_base < 2 {
+        error!("--log-histogram-base must be at least 2, got {}", log_histogram_base);
+        std::process::exit(exitcode::USAGE);
+    }
+    let sampling: Option<SamplingConfig> = sample_fraction.map(|fraction| SamplingConfig { fraction, seed });
+    if let Some(sample_fraction) = sample_fraction {
+        if !(0.0..=1.0).contains(&sample_fraction) {
+            error!("--sample must be in the range 0.0-1.0, got {}", sample_fraction);
+            std::process::exit(exitcode::USAGE);
+        }
+    }
+
+    if fast && output_format == "markdown" {
+        error!("--fast is not compatible with --format markdown");
+        std::process::exit(exitcode::USAGE);
+    }
+    if !stat_names.is_empty() && output_format == "markdown" {
+        error!("--stats is not compatible with --format markdown");
+        std::process::exit(exitcode::USAGE);
+    }
+    if let Some(min_mean_qual) = min_mean_qual {
+        if min_mean_qual.is_nan() || min_mean_qual < 0.0 {
+            error!("--min-mean-qual must be a non-negative number, got {}", min_mean_qual);
+            std::process::exit(exitcode::USAGE);
+        }
+    }
+    let group_regex: Option<Regex> = if group_pattern.is_empty() {
+        None
+    } else {
+        match Regex::new(&group_pattern) {
+            Ok(group_regex) if group_regex.captures_len() > 1 => Some(group_regex),
+            Ok(_) => {
+                error!("--group-by-regex must contain a capture group, got {:?}", group_pattern);
+                std::process::exit(exitcode::USAGE);
+            }
+            Err(e) => {
+                error!("Invalid --group-by-regex: {}", e);
+                std::process::exit(exitcode::USAGE);
+            }
+        }
+    };
+    if let Some(decimals) = round_decimals {
+        if decimals > 15 {
+            error!("--round-decimals must be in the range 0-15, got {}", decimals);
+            std::process::exit(exitcode::USAGE);
+        }
+    }
+    if head == Some(0) {
+        error!("--head must be greater than 0");
+        std::process::exit(exitcode::USAGE);
+    }
+    if genome_size_arg == Some(0) {
+        error!("--genome-size must be greater than 0");
+        std::process::exit(exitcode::USAGE);
+    }
+    if !output_dir.is_empty() {
+        if let Err(e) = std::fs::create_dir_all(&output_dir) {
+            error!("Failed to create output directory: {:?}", output_dir);
+            error!("Error: {:?}", e);
+            std::process::exit(exitcode::CANTCREAT);
+        }
+    }
 
-    //check inputs
-    for fastx_fn in fastx_fns.iter() {
-        match File::open(fastx_fn) {
+    //check every input before loading any of them, so a mistyped last file fails right away; with --keep-going, unopenable FASTX files are reported as failures after loading instead
+    let other_input_fns = paired_fns.iter().chain([&tar_fn, &lengths_txt_fn, &reference_fn]).filter(|input_fn| !input_fn.is_empty());
+    for (input_fn, can_skip) in fastx_fns.iter().map(|fastx_fn| (fastx_fn, keep_going)).chain(other_input_fns.map(|input_fn| (input_fn, false))) {
+        if is_stream_input(input_fn) || is_remote_url(input_fn) {
+            //opening a pipe here could lose data (or block) and URLs are not local files, so these are only opened once during loading
+            continue;
+        }
+        match File::open(input_fn) {
             Ok(_) => {}
+            Err(e) if can_skip => {
+                warn!("Failed to open file, it will be skipped: {:?}", input_fn);
+                warn!("Error: {:?}", e);
+            }
             Err(e) => {
-                error!("Failed to open file: {:?}", fastx_fn);
+                error!("Failed to open file: {:?}", input_fn);
                 error!("Error: {:?}", e);
                 std::process::exit(exitcode::NOINPUT);
             }
         };
     }
 
+    //the reference total only needs to be computed once, so get it out of the way before the main inputs
+    let genome_size: Option<u64> = if reference_fn.is_empty() {
+        genome_size_arg
+    } else {
+        info!("Computing genome size from reference: {:?}", reference_fn);
+        let reference_counts: BTreeMap<usize, u64> = match gather_fastx_stats(&reference_fn) {
+            Ok(result) => result,
+            Err(e) => {
+                error!("Error while parsing reference file: {:?}", reference_fn);
+                error!("Error: {:?}", e);
+                std::process::exit(exitcode::NOINPUT);
+            }
+        };
+        let (reference_bases, _reference_seqs) = compute_total_counts(&reference_counts);
+        if reference_bases == 0 {
+            error!("Reference file contains no bases: {:?}", reference_fn);
+            std::process::exit(exitcode::DATAERR);
+        }
+        info!("Reference genome size: {}", reference_bases);
+        Some(reference_bases)
+    };
+
+    //the previous counts have to be read before the output checks overwrite them
+    let appended_counts: Option<BTreeMap<usize, u64>> = if append && std::path::Path::new(&length_fn).exists() {
+        info!("Appending to existing length counts: {:?}", length_fn);
+        match read_length_counts_json(&length_fn) {
+            Ok(previous_counts) => Some(previous_counts),
+            Err(e) => {
+                error!("Failed to read existing length JSON for --append: {:?}", length_fn);
+                error!("Error: {:?}", e);
+                std::process::exit(exitcode::DATAERR);
+            }
+        }
+    } else {
+        if append {
+            info!("No existing length counts, starting a new file: {:?}", length_fn);
+        }
+        None
+    };
+
+    //make sure the inputs are what the caller promised before doing any work; pipes cannot be sniffed without consuming them, so they wait for the load
+    let mut failed_files: Vec<FailedFile> = vec![];
+    if let Some(expected_format) = expected_format {
+        for fastx_fn in fastx_fns.iter().filter(|fastx_fn| !is_stream_input(fastx_fn)) {
+            let detected_format: InputFormat = if is_remote_url(fastx_fn) {
+                format_from_extension(fastx_fn, true).unwrap_or(InputFormat::Unknown)
+            } else {
+                match detect_format(fastx_fn) {
+                    Ok(detected_format) => detected_format,
+                    Err(e) if keep_going => {
+                        //an unreadable file is just one more failure to tolerate
+                        warn!("Skipping file that failed to load: {:?}", fastx_fn);
+                        failed_files.push(FailedFile {
+                            filename: fastx_fn.clone(),
+                            error: e.to_string()
+                        });
+                        continue;
+                    },
+                    Err(e) => {
+                        error!("Failed to detect the format of input file: {:?}", fastx_fn);
+                        error!("Error: {:?}", e);
+                        std::process::exit(exitcode::NOINPUT);
+                    }
+                }
+            };
+            check_expected_format(fastx_fn, expected_format, detected_format);
+        }
+    }
+
     //check outputs
     if out_fn != "stdout" {
-        match File::create(&out_fn) {
-            Ok(file) => file,
+        create_output_file(&out_fn, gzip_level);
+    }
+    if !length_fn.is_empty() {
+        create_output_file(&length_fn, gzip_level);
+    }
+    if !combined_fn.is_empty() {
+        create_output_file(&combined_fn, gzip_level);
+    }
+    if !expand_lengths_fn.is_empty() {
+        create_output_file(&expand_lengths_fn, gzip_level);
+    }
+    if !base_histogram_fn.is_empty() {
+        create_output_file(&base_histogram_fn, gzip_level);
+    }
+    if !log_histogram_fn.is_empty() {
+        create_output_file(&log_histogram_fn, gzip_level);
+    }
+    if !nx_curve_fn.is_empty() {
+        create_output_file(&nx_curve_fn, gzip_level);
+    }
+    if !quality_by_length_fn.is_empty() {
+        create_output_file(&quality_by_length_fn, gzip_level);
+    }
+    if !per_record_fn.is_empty() {
+        create_output_file(&per_record_fn, gzip_level);
+    }
+
+    //load the fastx file lengths
+    let bam_options = BamLoadOptions {
+        include_hardclips,
+        unique_reads,
+        region
+    };
+    let loader_config = LoaderConfig {
+        bam_options: bam_options.clone(),
+        head,
+        max_records,
+        sampling: sampling.clone(),
+        min_mean_qual,
+        check_duplicate_names,
+        validate_qual_length,
+        decompression_threads,
+        progress_interval,
+        distinct_length_warning,
+        ..Default::default()
+    };
+
+    if !compare_fns.is_empty() {
+        //compare mode loads each file separately and only produces the comparison
+        let mut compared_stats: Vec<LengthStats> = vec![];
+        for compare_fn in compare_fns.iter() {
+            let length_counts: BTreeMap<usize, u64> = match gather_stats(compare_fn, &loader_config) {
+                Ok(result) => result,
+                Err(e) => {
+                    error!("Error while parsing input file: {:?}", compare_fn);
+                    error!("Error: {:?}", e);
+                    std::process::exit(exitcode::IOERR);
+                }
+            };
+            compared_stats.push(compute_length_stats_with_interpolation(&length_counts, interpolate_percentiles));
+        }
+        let mut changes: Vec<FieldComparison> = compare_length_stats(&compared_stats[0], &compared_stats[1]);
+        if let Some(decimals) = round_decimals {
+            compared_stats.iter_mut().for_each(|stats| stats.round_floats(decimals));
+            changes.iter_mut().for_each(|change| change.round_floats(decimals));
+        }
+        let comparison_output = ComparisonOutput {
+            baseline_file: &compare_fns[0],
+            comparison_file: &compare_fns[1],
+            baseline: &compared_stats[0],
+            comparison: &compared_stats[1],
+            changes: &changes
+        };
+        let cased_output = CasedJson::new(&comparison_output, json_case);
+        if out_fn == "stdout" {
+            println!("{}", format_json(&cased_output, compact));
+        } else {
+            info!("Saving comparison to file: {:?}", out_fn);
+            save_json_output(&out_fn, gzip_level, compact, &cased_output);
+        }
+        info!("Processes successfully finished.");
+        return;
+    }
+
+    if count_only {
+        //count-only mode never builds the length counts, so it just reports the totals and stops
+        let load_start: Instant = Instant::now();
+        let totals: LengthTotals = match count_multi_totals(&fastx_fns, &loader_config) {
+            Ok(result) => result,
             Err(e) => {
-                error!("Failed to create output JSON file: {:?}", out_fn);
+                error!("Error while parsing input files: {:?}", fastx_fns);
                 error!("Error: {:?}", e);
-                std::process::exit(exitcode::CANTCREAT);
+                std::process::exit(exitcode::IOERR);
             }
         };
+        let load_time: Duration = load_start.elapsed();
+        if fail_on_empty && totals.total_sequences == 0 {
+            error!("No sequences were found in the input files: {:?}", fastx_fns);
+            std::process::exit(exitcode::DATAERR);
+        }
+        let output_start: Instant = Instant::now();
+        let totals_text: String = format!("{}\t{}\n", totals.total_bases, totals.total_sequences);
+        if out_fn == "stdout" {
+            print!("{totals_text}");
+        } else {
+            info!("Saving totals to file: {:?}", out_fn);
+            save_text_output(&out_fn, gzip_level, &totals_text);
+        }
+        if timing {
+            log_timing_summary(load_time, Duration::ZERO, output_start.elapsed(), totals.total_sequences, totals.total_bases, seq_type);
+        }
+        info!("Processes successfully finished.");
+        return;
     }
-    if !length_fn.is_empty() {
-        match File::create(&length_fn) {
-            Ok(file) => file,
+
+    if approximate_percentiles {
+        //the digest replaces the length counts, so none of the outputs that need them are available here
+        let load_start: Instant = Instant::now();
+        let mut length_digest: LengthDigest = match gather_length_digest(&fastx_fns, &loader_config, DEFAULT_COMPRESSION) {
+            Ok(result) => result,
             Err(e) => {
-                error!("Failed to create output JSON file: {:?}", length_fn);
+                error!("Error while parsing input files: {:?}", fastx_fns);
                 error!("Error: {:?}", e);
-                std::process::exit(exitcode::CANTCREAT);
+                std::process::exit(exitcode::IOERR);
             }
         };
+        let load_time: Duration = load_start.elapsed();
+        if fail_on_empty && length_digest.totals.total_sequences == 0 {
+            error!("No sequences were found in the input files: {:?}", fastx_fns);
+            std::process::exit(exitcode::DATAERR);
+        }
+        let compute_start: Instant = Instant::now();
+        let mut approximate_metrics: ApproximateLengthStats = compute_approximate_stats(&mut length_digest.digest, length_digest.totals.total_bases);
+        if let Some(decimals) = round_decimals {
+            approximate_metrics.round_floats(decimals);
+        }
+        let approximate_output = ApproximateStatsOutput {
+            stats: &approximate_metrics,
+            input_format: describe_formats(&length_digest.input_formats),
+            command_line: std::env::args_os().map(|arg| arg.to_string_lossy().to_string()).collect::<Vec<String>>().join(" "),
+            approximate_percentiles: true,
+            head,
+            sampling: sampling.as_ref()
+        };
+        let compute_time: Duration = compute_start.elapsed();
+        let output_start: Instant = Instant::now();
+        let cased_output = CasedJson::new(&approximate_output, json_case);
+        if out_fn == "stdout" {
+            println!("{}", format_json(&cased_output, compact));
+        } else {
+            info!("Saving results to file: {:?}", out_fn);
+            save_json_output(&out_fn, gzip_level, compact, &cased_output);
+        }
+        if timing {
+            log_timing_summary(load_time, compute_time, output_start.elapsed(), approximate_metrics.total_sequences, approximate_metrics.total_bases, seq_type);
+        }
+        info!("Processes successfully finished.");
+        return;
     }
 
-    //load the fastx file lengths
-    let length_counts: BTreeMap<usize, u64> = match gather_multifastx_stats(&fastx_fns) {
+    let mut gapped_counts: Option<GappedLengthCounts> = None;
+    let mut quality_filtered: u64 = 0;
+    let mut duplicate_names: u64 = 0;
+    let mut qual_length_mismatches: u64 = 0;
+    let mut grouped_counts: Option<BTreeMap<String, BTreeMap<usize, u64>>> = None;
+    let mut quality_counts: Option<LengthQualityCounts> = None;
+    let mut ambiguous_base_counts: Option<BTreeMap<char, u64>> = None;
+    let mut reservoir_lengths: Option<Vec<usize>> = None;
+    let mut loaded_formats: Option<Vec<InputFormat>> = None;
+    let load_start: Instant = Instant::now();
+    let load_result = if keep_going {
+        //any files that already failed the format check are not tried again
+        let loadable_fns: Vec<&String> = fastx_fns.iter()
+            .filter(|fastx_fn| !failed_files.iter().any(|failed_file| &failed_file.filename == *fastx_fn))
+            .collect();
+        let (length_counts, load_failures) = gather_multifastx_stats_keep_going(&loadable_fns, &loader_config.bam_options, distinct_length_warning);
+        failed_files.extend(load_failures);
+        if failed_files.len() == fastx_fns.len() {
+            Err("All input files failed to load".into())
+        } else {
+            Ok(length_counts)
+        }
+    } else if count_n {
+        gather_multifastx_n_stats(&fastx_fns, distinct_length_warning).map(|loaded_counts| {
+            let length_counts: BTreeMap<usize, u64> = loaded_counts.length_counts.clone();
+            gapped_counts = Some(loaded_counts);
+            length_counts
+        })
+    } else if interleaved {
+        gather_multifastx_interleaved_stats(&fastx_fns, &loader_config.bam_options, distinct_length_warning)
+    } else if !paired_fns.is_empty() {
+        gather_paired_stats(&paired_fns[0], &paired_fns[1], &loader_config)
+    } else if !lengths_txt_fn.is_empty() {
+        //the lengths are already known, so there is nothing to parse
+        info!("Loading lengths from file: {:?}", lengths_txt_fn);
+        read_length_counts_txt_with_check(&lengths_txt_fn, assert_sorted, &loader_config)
+    } else if !tar_fn.is_empty() {
+        gather_tar_stats(&tar_fn, &loader_config).map(|member_counts| {
+            let mut length_counts: BTreeMap<usize, u64> = BTreeMap::new();
+            for counts in member_counts.values() {
+                merge_length_counts(&mut length_counts, counts);
+            }
+            grouped_counts = Some(member_counts);
+            length_counts
+        })
+    } else if let Some(group_regex) = group_regex.as_ref() {
+        gather_grouped_stats(&fastx_fns, &loader_config, group_regex).map(|(group_counts, load_summary)| {
+            quality_filtered = load_summary.quality_filtered;
+            duplicate_names = load_summary.duplicate_names;
+            qual_length_mismatches = load_summary.qual_length_mismatches;
+            loaded_formats = Some(load_summary.input_formats);
+            //the overall counts are just all of the groups together
+            let mut length_counts: BTreeMap<usize, u64> = BTreeMap::new();
+            for counts in group_counts.values() {
+                merge_length_counts(&mut length_counts, counts);
+            }
+            grouped_counts = Some(group_counts);
+            length_counts
+        })
+    } else if by_read_group {
+        gather_read_group_stats(&fastx_fns, &loader_config).map(|read_group_counts| {
+            //the overall counts are just all of the read groups together
+            let mut length_counts: BTreeMap<usize, u64> = BTreeMap::new();
+            for counts in read_group_counts.values() {
+                merge_length_counts(&mut length_counts, counts);
+            }
+            grouped_counts = Some(read_group_counts);
+            length_counts
+        })
+    } else if count_ambiguous {
+        gather_multifastx_ambiguous_stats(&fastx_fns, &loader_config).map(|ambiguous_length_counts| {
+            ambiguous_base_counts = Some(ambiguous_length_counts.ambiguous_counts);
+            ambiguous_length_counts.length_counts
+        })
+    } else if !quality_by_length_fn.is_empty() {
+        gather_multifastx_quality_stats(&fastx_fns, &loader_config).map(|length_quality_counts| {
+            let length_counts: BTreeMap<usize, u64> = length_quality_counts.length_counts.clone();
+            quality_counts = Some(length_quality_counts);
+            length_counts
+        })
+    } else if let Some(reservoir_size) = reservoir_size {
+        //sample the individual lengths as they stream past, building the length counts along the way
+        let mut reservoir = LengthReservoir::new(reservoir_size, seed);
+        let mut length_counts: BTreeMap<usize, u64> = BTreeMap::new();
+        let mut record_count: u64 = 0;
+        stream_multifastx_lengths_with_summary(&fastx_fns, &loader_config, |_seq_id, seq_len| {
+            record_count += 1;
+            if loader_config.passes_length_filters(seq_len) {
+                //zero-length sequences are removed from the stats, so they are not sampled either
+                if seq_len > 0 {
+                    reservoir.add(seq_len);
+                }
+                loader_config.count_length(&mut length_counts, seq_len);
+            }
+            Ok(())
+        }).map(|stream_summary| {
+            loaded_formats = Some(stream_summary.input_formats);
+            qual_length_mismatches = stream_summary.qual_length_mismatches;
+            //anything read but not passed along failed the quality filter
+            quality_filtered = stream_summary.total_records as u64 - record_count;
+            info!("Sampled {} of {} sequence lengths.", reservoir.sample().len(), reservoir.seen());
+            reservoir_lengths = Some(reservoir.into_sample());
+            length_counts
+        })
+    } else if per_record_fn.is_empty() {
+        gather_multi_stats_with_summary(&fastx_fns, &loader_config).map(|(length_counts, load_summary)| {
+            quality_filtered = load_summary.quality_filtered;
+            duplicate_names = load_summary.duplicate_names;
+            qual_length_mismatches = load_summary.qual_length_mismatches;
+            loaded_formats = Some(load_summary.input_formats);
+            length_counts
+        })
+    } else {
+        //stream each record out as it is loaded, building the length counts along the way
+        info!("Saving per-record lengths to file: {:?}", per_record_fn);
+        let mut record_writer = create_output_file(&per_record_fn, gzip_level);
+        let record_delimiter: &[u8] = if null_delimited { b"\0" } else { b"\n" };
+        let mut length_counts: BTreeMap<usize, u64> = BTreeMap::new();
+        let mut record_count: u64 = 0;
+        stream_multifastx_lengths_with_summary(&fastx_fns, &loader_config, |seq_id, seq_len| {
+            let record_length = RecordLength {
+                name: &String::from_utf8_lossy(seq_id),
+                length: seq_len
+            };
+            serde_json::to_writer(&mut record_writer, &record_length)?;
+            record_writer.write_all(record_delimiter)?;
+
+            loader_config.count_length(&mut length_counts, seq_len);
+            record_count += 1;
+            Ok(())
+        }).and_then(|stream_summary| {
+            loaded_formats = Some(stream_summary.input_formats);
+            qual_length_mismatches = stream_summary.qual_length_mismatches;
+            //anything read but not passed along failed the quality filter
+            quality_filtered = stream_summary.total_records as u64 - record_count;
+            record_writer.finish()?;
+            Ok(length_counts)
+        })
+    };
+    let length_counts: BTreeMap<usize, u64> = match load_result {
         Ok(result) => result,
         Err(e) => {
             error!("Error while parsing input files: {:?}", fastx_fns);
@@ -98,42 +1575,349 @@ fn main() {
             std::process::exit(exitcode::IOERR);
         }
     };
+    let load_time: Duration = load_start.elapsed();
+    let (loaded_bases, loaded_records) = compute_total_counts(&length_counts);
+
+    //add in the counts from previous runs
+    let length_counts: BTreeMap<usize, u64> = match appended_counts {
+        Some(mut previous_counts) => {
+            merge_length_counts(&mut previous_counts, &length_counts);
+            previous_counts
+        },
+        None => length_counts
+    };
+    let compute_start: Instant = Instant::now();
+
+    //zero-length records (e.g. a FASTA header with no sequence) are only reported as a count
+    let mut length_counts: BTreeMap<usize, u64> = length_counts;
+    let zero_length_sequences: u64 = remove_zero_lengths(&mut length_counts);
+    if zero_length_sequences > 0 {
+        warn!("Found {} zero-length sequences, which are excluded from the statistics.", zero_length_sequences);
+    }
+
+    //remove any excluded lengths before computing anything
+    let length_counts: BTreeMap<usize, u64> = if excluded_lengths.is_empty() { length_counts } else { exclude_lengths(&length_counts, &excluded_lengths) };
+    if let Some(group_counts) = grouped_counts.as_mut() {
+        group_counts.values_mut().for_each(|counts| {
+            remove_zero_lengths(counts);
+            *counts = exclude_lengths(counts, &excluded_lengths);
+            if let Some(window) = length_range.as_ref() {
+                *counts = filter_length_range(counts, window);
+            }
+        });
+    }
+
+    //restrict to the length window if one was given
+    let length_counts: BTreeMap<usize, u64> = match length_range.as_ref() {
+        Some(window) => filter_length_range(&length_counts, window),
+        None => length_counts
+    };
+
+    //the ungapped stats must describe the same records, so they are filtered by each record's full length rather than its ungapped length
+    let gapped_counts: Option<GappedLengthCounts> = gapped_counts.map(|counts| counts.retain_lengths(|seq_len| length_counts.contains_key(&seq_len)));
+
+    //drop the longest outliers last, so they are the longest of what would otherwise be reported
+    let mut length_counts: BTreeMap<usize, u64> = length_counts;
+    let dropped_longest_lengths: Option<Vec<usize>> = drop_longest.map(|count| {
+        let dropped_lengths: Vec<usize> = drop_longest_lengths(&mut length_counts, count);
+        //the full list is in the output, which could be far too long for the log
+        match (dropped_lengths.first(), dropped_lengths.last()) {
+            (Some(longest), Some(shortest)) => info!("Dropped the {} longest sequences, with lengths from {} to {}", dropped_lengths.len(), shortest, longest),
+            _ => info!("No sequences to drop for --drop-longest")
+        }
+        dropped_lengths
+    });
+
+    //a single distinct length is normal for short reads, but for longer data it usually means the input is not what it seems
+    if let (1, Some((&seq_len, &seq_count))) = (length_counts.len(), length_counts.iter().next()) {
+        if same_length_warning > 0 && seq_len >= same_length_warning && seq_count > 1 {
+            warn!("All {} sequences have the same length ({} {}), which may indicate truncated or unexpected input; see --same-length-warning", seq_count, seq_len, seq_type.unit_label());
+        }
+    }
+
+    //an empty result in a pipeline usually means something upstream failed
+    if fail_on_empty && length_counts.is_empty() {
+        error!("No sequences were left after loading and filtering the input files: {:?}", fastx_fns);
+        std::process::exit(exitcode::DATAERR);
+    }
+
+    //compute the stats; fast mode skips everything beyond the basic metrics, and a selection only computes what was chosen
+    let mut length_metrics: Option<LengthStats> = if fast || !stat_names.is_empty() { None } else { Some(compute_length_stats_with_interpolation(&length_counts, interpolate_percentiles)) };
+    let mut basic_metrics: Option<BasicLengthStats> = if fast { Some(compute_basic_stats(&length_counts)) } else { None };
+    let mut selected_metrics: Option<SelectedStats> = if stat_names.is_empty() {
+        None
+    } else {
+        Some(compute_selected_stats(&length_counts, &stat_names, interpolate_percentiles).expect("the --stats names were already checked"))
+    };
+    let mut ungapped_metrics: Option<LengthStats> = gapped_counts.as_ref().map(|counts| compute_length_stats_with_interpolation(&counts.ungapped_counts, interpolate_percentiles));
+    let mut group_metrics: Option<BTreeMap<String, LengthStats>> = grouped_counts.as_ref().map(|group_counts| {
+        group_counts.iter()
+            .map(|(group, counts)| (group.clone(), compute_length_stats_with_interpolation(counts, interpolate_percentiles)))
+            .collect()
+    });
+    let mut top_fraction_stats: Option<Vec<TopFractionStats>> = if top_fractions.is_empty() {
+        None
+    } else {
+        Some(top_fractions.iter().map(|&fraction| compute_top_fraction_stats(&length_counts, fraction)).collect())
+    };
+    let mut n50_bootstrap: Option<N50Bootstrap> = bootstrap_replicates.map(|replicates| {
+        let (_total_bases, total_seqs) = compute_total_counts(&length_counts);
+        let (mean_n50, n50_lower_95, n50_upper_95) = bootstrap_n50(&length_counts, total_seqs, replicates, seed);
+        N50Bootstrap { replicates, seed, mean_n50, n50_lower_95, n50_upper_95 }
+    });
+    let mut ng_metrics: Option<NgStats> = genome_size.map(|genome_size| compute_ng_stats(&length_counts, genome_size));
+    let mut sampled_estimates: Option<SampledEstimates> = sampling.as_ref().and_then(|sampling_config| estimate_sampled_totals(&length_counts, sampling_config.fraction));
+    if let Some(decimals) = round_decimals {
+        if let Some(metrics) = length_metrics.as_mut() {
+            metrics.round_floats(decimals);
+        }
+        if let Some(metrics) = basic_metrics.as_mut() {
+            metrics.round_floats(decimals);
+        }
+        if let Some(metrics) = selected_metrics.as_mut() {
+            metrics.round_floats(decimals);
+        }
+        if let Some(ungapped) = ungapped_metrics.as_mut() {
+            ungapped.round_floats(decimals);
+        }
+        if let Some(estimates) = sampled_estimates.as_mut() {
+            estimates.round_floats(decimals);
+        }
+        if let Some(ng) = ng_metrics.as_mut() {
+            ng.round_floats(decimals);
+        }
+        if let Some(groups) = group_metrics.as_mut() {
+            groups.values_mut().for_each(|metrics| metrics.round_floats(decimals));
+        }
+        if let Some(top_stats) = top_fraction_stats.as_mut() {
+            top_stats.iter_mut().for_each(|metrics| metrics.round_floats(decimals));
+        }
+        if let Some(bootstrap) = n50_bootstrap.as_mut() {
+            bootstrap.mean_n50 = round_to_decimals(bootstrap.mean_n50, decimals);
+            bootstrap.n50_lower_95 = round_to_decimals(bootstrap.n50_lower_95, decimals);
+            bootstrap.n50_upper_95 = round_to_decimals(bootstrap.n50_upper_95, decimals);
+        }
+    }
+
+    //the other load modes do not report formats, so repeat the same decisions the loaders made; pipes can only be judged by their extension here
+    let formats_sniffed: bool = loaded_formats.is_some();
+    let input_formats: Vec<InputFormat> = loaded_formats.unwrap_or_else(|| {
+        let loaded_fns: Vec<&str> = if tar_fn.is_empty() {
+            fastx_fns.iter()
+                .filter(|fastx_fn| !failed_files.iter().any(|failed_file| &failed_file.filename == *fastx_fn))
+                .map(|fastx_fn| fastx_fn.as_str())
+                .collect()
+        } else {
+            grouped_counts.as_ref().map(|member_counts| member_counts.keys().map(|member| member.as_str()).collect()).unwrap_or_default()
+        };
+        loaded_fns.iter()
+            .map(|loaded_fn| if is_stream_input(loaded_fn) || !tar_fn.is_empty() {
+                format_from_extension(loaded_fn, true).unwrap_or(InputFormat::Unknown)
+            } else {
+                resolve_format(loaded_fn, loader_config.format_detection).unwrap_or(InputFormat::Unknown)
+            })
+            .collect()
+    });
+    //only the loaders that report formats have sniffed the pipes, the guesses above are not enough to reject an input
+    if let (Some(expected_format), true) = (expected_format, formats_sniffed) {
+        for (fastx_fn, detected_format) in fastx_fns.iter().zip(input_formats.iter()).filter(|(fastx_fn, _detected_format)| is_stream_input(fastx_fn)) {
+            check_expected_format(fastx_fn, expected_format, *detected_format);
+        }
+    }
+
+    //the tar members and read groups are grouped the same way as the name groups
+    let (group_stats, member_stats, read_group_stats) = if !tar_fn.is_empty() {
+        (None, group_metrics, None)
+    } else if by_read_group {
+        (None, None, group_metrics)
+    } else {
+        (group_metrics, None, None)
+    };
+    let (total_bases, _total_seqs) = compute_total_counts(&length_counts);
+    let stats_output = StatsOutput {
+        stats: length_metrics.as_ref(),
+        basic_stats: basic_metrics.as_ref(),
+        selected_stats: selected_metrics.as_ref(),
+        input_format: if lengths_txt_fn.is_empty() { describe_formats(&input_formats) } else { "lengths-txt" },
+        command_line: std::env::args_os().map(|arg| arg.to_string_lossy().to_string()).collect::<Vec<String>>().join(" "),
+        seq_type: seq_type_arg,
+        approximate: sampling.is_some() || head.is_some() || sample_files_fraction.is_some(),
+        head,
+        sampling: sampling.as_ref(),
+        sampled_estimates,
+        total_n_bases: gapped_counts.as_ref().map(|counts| counts.total_n_bases()),
+        ungapped_stats: ungapped_metrics.as_ref(),
+        ambiguous_base_counts,
+        min_mean_qual,
+        quality_filtered_reads: min_mean_qual.map(|_| quality_filtered),
+        duplicate_names: if check_duplicate_names { Some(duplicate_names) } else { None },
+        qual_length_mismatches: if validate_qual_length { Some(qual_length_mismatches) } else { None },
+        zero_length_sequences,
+        excluded_lengths: if excluded_lengths.is_empty() { None } else { Some(&excluded_lengths) },
+        length_range,
+        dropped_longest_lengths,
+        group_stats,
+        read_group_stats,
+        member_stats,
+        n50_min_length,
+        n50_above_min_length: n50_min_length.map(|min_length| compute_n_score_above(&length_counts, 50, min_length)),
+        top_fractions: top_fraction_stats,
+        cover_fractions: if cover_fractions.is_empty() {
+            None
+        } else {
+            Some(cover_fractions.iter().map(|&fraction| {
+                let (sequence_count, cumulative_bases) = sequences_to_cover_fraction(&length_counts, total_bases, fraction);
+                CoverFraction { fraction, sequence_count, cumulative_bases }
+            }).collect())
+        },
+        n50_bootstrap,
+        top_lengths: top_count.map(|n| top_n_lengths(&length_counts, n)),
+        reservoir_lengths,
+        length_peaks: peak_prominence.map(|min_prominence| {
+            detect_peaks(&length_counts, min_prominence).into_iter()
+                .map(|(length, count)| LengthPeak { length, count })
+                .collect()
+        }),
+        ng_stats: ng_metrics,
+        failed_files: &failed_files
+    };
+    let compute_time: Duration = compute_start.elapsed();
+    let output_start: Instant = Instant::now();
 
-    //compute the stats
-    let length_metrics: LengthStats = compute_length_stats(&length_counts);
     // Serialize it to a JSON string.
-    let json_format: String = serde_json::to_string(&length_metrics).unwrap();
+    let json_format: String = serde_json::to_string(&stats_output).unwrap();
     info!("Length metrics: {}", json_format);
 
     //this is what we should put in the file
-    if out_fn == "stdout" {
-        let pretty_json: String = serde_json::to_string_pretty(&length_metrics).unwrap();
-        println!("{pretty_json}");
+    if output_format == "assembly-stats" {
+        let assembly_stats: String = format_assembly_stats(&length_counts, &fastx_fns.join(", "));
+        if out_fn == "stdout" {
+            print!("{assembly_stats}");
+        } else {
+            info!("Saving results to file: {:?}", out_fn);
+            save_text_output(&out_fn, gzip_level, &assembly_stats);
+        }
+    }
+    else if let (Some(metrics), "markdown") = (length_metrics.as_ref(), output_format.as_str()) {
+        let markdown_table: String = format_markdown_table_with_seq_type(metrics, seq_type);
+        if out_fn == "stdout" {
+            print!("{markdown_table}");
+        } else {
+            info!("Saving results to file: {:?}", out_fn);
+            save_text_output(&out_fn, gzip_level, &markdown_table);
+        }
+    }
+    else if out_fn == "stdout" {
+        println!("{}", format_json(&CasedJson::new(&stats_output, json_case), compact));
     }
     else {
         info!("Saving results to file: {:?}", out_fn);
-        let out_file = match File::create(&out_fn) {
-            Ok(file) => file,
-            Err(e) => {
-                error!("Failed to create output JSON file: {:?}", out_fn);
-                error!("Error: {:?}", e);
-                std::process::exit(exitcode::CANTCREAT);
-            }
-        };
-        serde_json::to_writer_pretty(out_file, &length_metrics).unwrap();
+        save_json_output(&out_fn, gzip_level, compact, &CasedJson::new(&stats_output, json_case));
     }
 
     if !length_fn.is_empty() {
         info!("Saving length counts to file: {:?}", length_fn);
-        let out_file = match File::create(&length_fn) {
-            Ok(file) => file,
+        if length_detailed {
+            let mut detailed_counts = compute_detailed_histogram(&length_counts);
+            if let Some(decimals) = round_decimals {
+                detailed_counts.values_mut().for_each(|entry| entry.round_floats(decimals));
+            }
+            save_json_output(&length_fn, gzip_level, compact, &detailed_counts);
+        } else {
+            save_json_output(&length_fn, gzip_level, compact, &length_counts);
+        }
+    }
+
+    if !combined_fn.is_empty() {
+        info!("Saving combined stats and length counts to file: {:?}", combined_fn);
+        let combined_output = CombinedOutput {
+            stats: &stats_output,
+            histogram: &length_counts
+        };
+        save_json_output(&combined_fn, gzip_level, compact, &CasedJson::new(&combined_output, json_case));
+    }
+
+    if !expand_lengths_fn.is_empty() {
+        info!("Saving expanded lengths to file: {:?}", expand_lengths_fn);
+        match expand_lengths(&length_counts, expand_lengths_cap) {
+            Ok(expanded) => save_json_output(&expand_lengths_fn, gzip_level, compact, &expanded),
             Err(e) => {
-                error!("Failed to create output JSON file: {:?}", length_fn);
-                error!("Error: {:?}", e);
-                std::process::exit(exitcode::CANTCREAT);
+                error!("Failed to expand lengths, see --expand-lengths-cap: {}", e);
+                std::process::exit(exitcode::DATAERR);
             }
         };
-        serde_json::to_writer_pretty(out_file, &length_counts).unwrap();
+    }
+
+    if !base_histogram_fn.is_empty() {
+        info!("Saving base histogram to file: {:?}", base_histogram_fn);
+        save_json_output(&base_histogram_fn, gzip_level, compact, &compute_base_histogram(&length_counts));
+    }
+
+    if !log_histogram_fn.is_empty() {
+        info!("Saving log-binned histogram to file: {:?}", log_histogram_fn);
+        save_json_output(&log_histogram_fn, gzip_level, compact, &compute_log_histogram(&length_counts, log_histogram_base));
+    }
+    if let Some(quality_counts) = quality_counts.as_ref() {
+        info!("Saving quality by length to file: {:?}", quality_by_length_fn);
+        //only the lengths that survived any exclusions are reported
+        let mean_qualities: BTreeMap<usize, f64> = quality_counts.mean_quality_by_length().into_iter()
+            .filter(|(seq_len, _mean_quality)| length_counts.contains_key(seq_len))
+            .collect();
+        save_json_output(&quality_by_length_fn, gzip_level, compact, &mean_qualities);
+    }
+
+    if !nx_curve_fn.is_empty() {
+        info!("Saving Nx curve to file: {:?}", nx_curve_fn);
+        let nx_curve: Vec<(usize, f64)> = compute_nx_curve(&length_counts, total_bases);
+        if nx_curve_fn.trim_end_matches(".gz").ends_with(".csv") {
+            let mut csv_text: String = String::from("length,cumulative_base_fraction\n");
+            for (seq_len, fraction) in nx_curve.iter() {
+                csv_text.push_str(&format!("{seq_len},{fraction}\n"));
+            }
+            save_text_output(&nx_curve_fn, gzip_level, &csv_text);
+        } else {
+            let nx_points: Vec<NxCurvePoint> = nx_curve.into_iter()
+                .map(|(length, cumulative_base_fraction)| NxCurvePoint { length, cumulative_base_fraction })
+                .collect();
+            save_json_output(&nx_curve_fn, gzip_level, compact, &nx_points);
+        }
+    }
+
+    #[cfg(feature = "sqlite")]
+    if let (false, Some(metrics)) = (sqlite_fn.is_empty(), length_metrics.as_ref()) {
+        info!("Saving results to SQLite database: {:?}", sqlite_fn);
+        let input_label: String = if !tar_fn.is_empty() {
+            tar_fn.clone()
+        } else if !paired_fns.is_empty() {
+            paired_fns.join(", ")
+        } else if !lengths_txt_fn.is_empty() {
+            lengths_txt_fn.clone()
+        } else {
+            fastx_fns.join(", ")
+        };
+        if let Err(e) = insert_length_stats(&sqlite_fn, &input_label, metrics) {
+            error!("Failed to save results to SQLite database {:?}: {}", sqlite_fn, e);
+            std::process::exit(exitcode::IOERR);
+        }
+    }
+
+    if timing {
+        log_timing_summary(load_time, compute_time, output_start.elapsed(), loaded_records, loaded_bases, seq_type);
+    }
+
+    //a quick at-a-glance result without reading the JSON
+    if !quiet {
+        let (total_bases, total_seqs) = compute_total_counts(&length_counts);
+        let mean_length: f64 = if total_seqs == 0 { 0.0 } else { total_bases as f64 / total_seqs as f64 };
+        eprintln!("{}", format_summary_line(total_seqs, total_bases, mean_length, length_metrics.as_ref().map(|metrics| metrics.n50), seq_type));
+    }
+
+    if !failed_files.is_empty() {
+        error!("Finished with {} of {} input files skipped due to errors:", failed_files.len(), fastx_fns.len());
+        for failed_file in failed_files.iter() {
+            error!("\t{:?}: {}", failed_file.filename, failed_file.error);
+        }
+        std::process::exit(EXIT_PARTIAL_SUCCESS);
     }
 
     info!("Processes successfully finished.")
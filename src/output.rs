@@ -0,0 +1,527 @@
+
+use flate2::Compression;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+
+use crate::length_stats::{compute_total_counts, LengthStats};
+use crate::loader::LoaderConfig;
+
+/// The default gzip compression level, a balance between speed and size
+pub const DEFAULT_GZIP_LEVEL: u32 = 6;
+
+/// The kind of sequences being measured, which only changes how the units are labeled (e.g. "residues" instead of "bases")
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SeqType {
+    /// DNA nucleotides, the default
+    #[default]
+    Dna,
+    /// RNA nucleotides
+    Rna,
+    /// Amino acids
+    Protein
+}
+
+impl SeqType {
+    /// This will parse a sequence type name as given on the command line, i.e. "dna", "rna", or "protein".
+    /// # Arguments
+    /// * `name` - the name to parse, case insensitive
+    /// # Examples
+    /// ```
+    /// use fastleng::output::SeqType;
+    /// assert_eq!(SeqType::from_name("protein"), Some(SeqType::Protein));
+    /// assert_eq!(SeqType::from_name("DNA"), Some(SeqType::Dna));
+    /// assert_eq!(SeqType::from_name("peptide"), None);
+    /// ```
+    pub fn from_name(name: &str) -> Option<SeqType> {
+        match name.to_ascii_lowercase().as_str() {
+            "dna" => Some(SeqType::Dna),
+            "rna" => Some(SeqType::Rna),
+            "protein" => Some(SeqType::Protein),
+            _ => None
+        }
+    }
+
+    /// Returns the plural name of a single sequence unit, "bases" for nucleotides and "residues" for proteins
+    pub fn unit_label(&self) -> &'static str {
+        match self {
+            SeqType::Dna | SeqType::Rna => "bases",
+            SeqType::Protein => "residues"
+        }
+    }
+
+    /// This will replace "bases" in a label (e.g. a statistic name such as `total_bases`) with the unit label of this sequence type.
+    /// # Arguments
+    /// * `label` - the label to adjust
+    /// # Examples
+    /// ```
+    /// use fastleng::output::SeqType;
+    /// assert_eq!(SeqType::Protein.relabel("total_gigabases"), "total_gigaresidues");
+    /// assert_eq!(SeqType::Rna.relabel("total_bases"), "total_bases");
+    /// ```
+    pub fn relabel(&self, label: &str) -> String {
+        label.replace("bases", self.unit_label())
+    }
+}
+
+/// A buffered output file that is transparently gzip compressed when the filename ends with `.gz`
+pub enum OutputWriter {
+    /// An uncompressed output file
+    Plain(BufWriter<File>),
+    /// A gzip compressed output file
+    Gzip(GzEncoder<BufWriter<File>>)
+}
+
+impl OutputWriter {
+    /// Flushes all buffered data, writing the gzip trailer if the output is compressed.
+    /// This should always be called once writing is done so that errors are not silently dropped.
+    pub fn finish(self) -> std::io::Result<()> {
+        match self {
+            OutputWriter::Plain(mut writer) => writer.flush(),
+            OutputWriter::Gzip(encoder) => encoder.finish()?.flush()
+        }
+    }
+}
+
+impl Write for OutputWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            OutputWriter::Plain(writer) => writer.write(buf),
+            OutputWriter::Gzip(encoder) => encoder.write(buf)
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            OutputWriter::Plain(writer) => writer.flush(),
+            OutputWriter::Gzip(encoder) => encoder.flush()
+        }
+    }
+}
+
+/// This will create an output file, compressing it with gzip at `gzip_level` if `filename` ends with `.gz`.
+/// # Arguments
+/// * `filename` - the path of the output file to create
+/// * `gzip_level` - the gzip compression level from 0 (none) to 9 (best); ignored for uncompressed outputs
+/// # Examples
+/// ```
+/// use std::io::Write;
+/// use fastleng::output::{create_output_writer, DEFAULT_GZIP_LEVEL};
+/// let filename = std::env::temp_dir().join("fastleng_doc_example.json.gz");
+/// let mut writer = create_output_writer(filename.to_str().unwrap(), DEFAULT_GZIP_LEVEL).unwrap();
+/// writer.write_all(b"{}").unwrap();
+/// writer.finish().unwrap();
+/// ```
+pub fn create_output_writer(filename: &str, gzip_level: u32) -> std::io::Result<OutputWriter> {
+    let file_writer = BufWriter::new(File::create(filename)?);
+    if filename.ends_with(".gz") {
+        Ok(OutputWriter::Gzip(GzEncoder::new(file_writer, Compression::new(gzip_level))))
+    } else {
+        Ok(OutputWriter::Plain(file_writer))
+    }
+}
+
+/// This will read back the length counts from a JSON file previously written with `--length-json`, decompressing it if `filename` ends with `.gz`.
+/// # Arguments
+/// * `filename` - the path of the length counts JSON
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::output::{create_output_writer, read_length_counts_json, DEFAULT_GZIP_LEVEL};
+/// let filename = std::env::temp_dir().join("fastleng_doc_lengths.json.gz");
+/// let filename_str: &str = filename.to_str().unwrap();
+/// let length_counts: BTreeMap<usize, u64> = [(5, 10), (10, 3)].iter().cloned().collect();
+/// let mut writer = create_output_writer(filename_str, DEFAULT_GZIP_LEVEL).unwrap();
+/// serde_json::to_writer(&mut writer, &length_counts).unwrap();
+/// writer.finish().unwrap();
+/// assert_eq!(read_length_counts_json(filename_str).unwrap(), length_counts);
+/// ```
+pub fn read_length_counts_json(filename: &str) -> Result<BTreeMap<usize, u64>, Box<dyn std::error::Error>> {
+    let file_reader = BufReader::new(File::open(filename)?);
+    let reader: Box<dyn Read> = if filename.ends_with(".gz") {
+        Box::new(MultiGzDecoder::new(file_reader))
+    } else {
+        Box::new(file_reader)
+    };
+    Ok(serde_json::from_reader(reader)?)
+}
+
+/// This will read a plain text file with one integer sequence length per line (gzip accepted) into length counts, e.g. lengths from another tool.
+/// Surrounding whitespace and blank lines are ignored, and anything else that is not a non-negative integer is an error naming the line.
+/// # Arguments
+/// * `filename` - the path of the lengths file
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::output::read_length_counts_txt;
+/// let filename = std::env::temp_dir().join("fastleng_doc_lengths.txt");
+/// std::fs::write(&filename, "5\n10\n5\n").unwrap();
+/// let length_counts: BTreeMap<usize, u64> = read_length_counts_txt(filename.to_str().unwrap()).unwrap();
+/// assert_eq!(length_counts, [(5, 2), (10, 1)].iter().cloned().collect());
+/// ```
+pub fn read_length_counts_txt(filename: &str) -> Result<BTreeMap<usize, u64>, Box<dyn std::error::Error>> {
+    read_length_counts_txt_with_check(filename, false, &LoaderConfig::default())
+}
+
+/// This is identical to `read_length_counts_txt(...)`, except it can also check that the lengths are in ascending order, e.g. for pre-sorted upstream output.
+/// Repeated lengths are allowed, but a length shorter than the one before it is an error naming its line.
+/// Only the `distinct_length_warning` of `config` is used.
+/// # Arguments
+/// * `filename` - the path of the lengths file
+/// * `assert_sorted` - if true, fail on the first length that is shorter than the previous one
+/// * `config` - the loading options to apply
+/// # Examples
+/// ```
+/// use fastleng::loader::LoaderConfig;
+/// use fastleng::output::read_length_counts_txt_with_check;
+/// let filename = std::env::temp_dir().join("fastleng_doc_unsorted_lengths.txt");
+/// std::fs::write(&filename, "5\n10\n5\n").unwrap();
+/// assert!(read_length_counts_txt_with_check(filename.to_str().unwrap(), false, &LoaderConfig::new()).is_ok());
+/// let error = read_length_counts_txt_with_check(filename.to_str().unwrap(), true, &LoaderConfig::new()).unwrap_err();
+/// assert!(error.to_string().contains("line 3"));
+/// ```
+pub fn read_length_counts_txt_with_check(filename: &str, assert_sorted: bool, config: &LoaderConfig) -> Result<BTreeMap<usize, u64>, Box<dyn std::error::Error>> {
+    let file_reader = BufReader::new(File::open(filename)?);
+    let reader: Box<dyn BufRead> = if filename.ends_with(".gz") {
+        Box::new(BufReader::new(MultiGzDecoder::new(file_reader)))
+    } else {
+        Box::new(file_reader)
+    };
+    let mut length_counts: BTreeMap<usize, u64> = BTreeMap::new();
+    let mut previous_len: Option<usize> = None;
+    for (line_index, line) in reader.lines().enumerate() {
+        let line: String = line?;
+        let trimmed: &str = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let seq_len: usize = trimmed.parse().map_err(|_| {
+            format!("Expected a non-negative integer length on line {} of {:?}, got {:?}", line_index + 1, filename, trimmed)
+        })?;
+        if let Some(previous_len) = previous_len.filter(|&previous_len| assert_sorted && seq_len < previous_len) {
+            return Err(format!("Lengths are not in ascending order on line {} of {:?}, {} follows {}", line_index + 1, filename, seq_len, previous_len).into());
+        }
+        previous_len = Some(seq_len);
+        config.count_length(&mut length_counts, seq_len);
+    }
+    Ok(length_counts)
+}
+
+/// This will render the summary statistics as a two-column Markdown table (Metric | Value).
+/// Integer metrics are printed as-is while floating point metrics are rounded to two decimals.
+/// # Arguments
+/// * `stats` - the summary statistics to render
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::compute_length_stats;
+/// use fastleng::output::format_markdown_table;
+/// let length_counts: BTreeMap<usize, u64> = [(5, 10), (10, 3)].iter().cloned().collect();
+/// let table: String = format_markdown_table(&compute_length_stats(&length_counts));
+/// assert!(table.starts_with("| Metric | Value |\n| --- | --- |\n| total_bases | 80 |\n"));
+/// ```
+pub fn format_markdown_table(stats: &LengthStats) -> String {
+    format_markdown_table_with_seq_type(stats, SeqType::Dna)
+}
+
+/// This is identical to `format_markdown_table(...)`, except the metric names are labeled with the units of `seq_type` (e.g. `total_residues` for proteins).
+/// # Arguments
+/// * `stats` - the summary statistics to render
+/// * `seq_type` - the kind of sequences the statistics describe
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::length_stats::compute_length_stats;
+/// use fastleng::output::{format_markdown_table_with_seq_type, SeqType};
+/// let length_counts: BTreeMap<usize, u64> = [(5, 10), (10, 3)].iter().cloned().collect();
+/// let table: String = format_markdown_table_with_seq_type(&compute_length_stats(&length_counts), SeqType::Protein);
+/// assert!(table.contains("| total_residues | 80 |\n"));
+/// ```
+pub fn format_markdown_table_with_seq_type(stats: &LengthStats, seq_type: SeqType) -> String {
+    //the serialized form tells us which fields are floating point, so we don't have to track it by name
+    let json_value = serde_json::to_value(stats).unwrap();
+    let mut table: String = String::from("| Metric | Value |\n| --- | --- |\n");
+    for (name, value) in stats.iter_fields() {
+        let label: String = seq_type.relabel(name);
+        if json_value[name].is_f64() {
+            table.push_str(&format!("| {label} | {value:.2} |\n"));
+        } else {
+            table.push_str(&format!("| {label} | {value} |\n"));
+        }
+    }
+    table
+}
+
+/// This will render the length counts in the text layout of the `assembly-stats` tool, for pipelines that already parse its output.
+/// The `N_count` and `Gaps` lines are omitted since they need the sequences themselves; the N-scores are over the lengths like `assembly-stats`,
+/// with `n` being the number of sequences (longest first) needed to reach each N-score.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `label` - the name used in the `stats for` header line, typically the input filename(s)
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::output::format_assembly_stats;
+/// let length_counts: BTreeMap<usize, u64> = [(5, 10), (10, 3)].iter().cloned().collect();
+/// let text: String = format_assembly_stats(&length_counts, "contigs.fa");
+/// assert!(text.starts_with("stats for contigs.fa\nsum = 80, n = 13, ave = 6.15, largest = 10\nN50 = 5, n = 5\n"));
+/// ```
+pub fn format_assembly_stats(length_counts: &BTreeMap<usize, u64>, label: &str) -> String {
+    let (total_bases, total_seqs) = compute_total_counts(length_counts);
+    let mean_length: f64 = if total_seqs == 0 { 0.0 } else { total_bases as f64 / total_seqs as f64 };
+    let largest: usize = length_counts.keys().next_back().copied().unwrap_or(0);
+    let mut text: String = format!("stats for {label}\nsum = {total_bases}, n = {total_seqs}, ave = {mean_length:.2}, largest = {largest}\n");
+
+    //walk from the longest sequence, tracking how many sequences it takes to reach each target
+    let mut targets = (50..=100_u64).step_by(10).peekable();
+    let mut current_bases: u64 = 0;
+    let mut current_seqs: u64 = 0;
+    for (seq_len, seq_count) in length_counts.iter().rev() {
+        let bin_bases: u64 = (*seq_len as u64).saturating_mul(*seq_count);
+        while let Some(&target) = targets.peek() {
+            let target_bases: f64 = (target as u128 * total_bases as u128) as f64 / 100.0;
+            if (current_bases.saturating_add(bin_bases) as f64) < target_bases || *seq_len == 0 {
+                break;
+            }
+            //only part of this length may be needed to cross the target
+            let needed_seqs: u64 = ((target_bases - current_bases as f64) / *seq_len as f64).ceil().max(1.0) as u64;
+            text.push_str(&format!("N{target} = {seq_len}, n = {}\n", current_seqs.saturating_add(needed_seqs.min(*seq_count))));
+            targets.next();
+        }
+        current_bases = current_bases.saturating_add(bin_bases);
+        current_seqs = current_seqs.saturating_add(*seq_count);
+    }
+
+    //empty inputs never reach any of the targets
+    for target in targets {
+        text.push_str(&format!("N{target} = 0, n = 0\n"));
+    }
+    text
+}
+
+/// This will format an integer with commas between each group of three digits, e.g. `12,345`.
+/// # Arguments
+/// * `value` - the number to format
+/// # Examples
+/// ```
+/// use fastleng::output::format_thousands;
+/// assert_eq!(format_thousands(1234567), "1,234,567");
+/// assert_eq!(format_thousands(999), "999");
+/// ```
+pub fn format_thousands(value: u64) -> String {
+    let digits: String = value.to_string();
+    let mut formatted: String = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// This will format the headline statistics as a single human-readable line, e.g. `12,345 seqs, 1.2 Gb, mean 98.5, N50 15,000`.
+/// Nucleotide totals are scaled to the largest of bp/kb/Mb/Gb that fits, while protein totals are given in residues.
+/// # Arguments
+/// * `total_sequences` - the number of sequences
+/// * `total_bases` - the number of bases (or residues) in the sequences
+/// * `mean_length` - the mean sequence length
+/// * `n50` - the N50 if it was computed; it is left out otherwise (e.g. with `--fast`)
+/// * `seq_type` - the kind of sequences, which selects the units of the total
+/// # Examples
+/// ```
+/// use fastleng::output::{format_summary_line, SeqType};
+/// assert_eq!(format_summary_line(12345, 1_216_000_000, 98.5, Some(15000), SeqType::Dna), "12,345 seqs, 1.2 Gb, mean 98.5, N50 15,000");
+/// assert_eq!(format_summary_line(3, 750, 250.0, None, SeqType::Protein), "3 seqs, 750 residues, mean 250.0");
+/// ```
+pub fn format_summary_line(total_sequences: u64, total_bases: u64, mean_length: f64, n50: Option<usize>, seq_type: SeqType) -> String {
+    let total_label: String = if seq_type == SeqType::Protein {
+        format!("{} residues", format_thousands(total_bases))
+    } else {
+        match total_bases {
+            0..=999 => format!("{total_bases} bp"),
+            1_000..=999_999 => format!("{:.1} kb", total_bases as f64 / 1e3),
+            1_000_000..=999_999_999 => format!("{:.1} Mb", total_bases as f64 / 1e6),
+            _ => format!("{:.1} Gb", total_bases as f64 / 1e9)
+        }
+    };
+    let mut line: String = format!("{} seqs, {total_label}, mean {mean_length:.1}", format_thousands(total_sequences));
+    if let Some(n50) = n50 {
+        line.push_str(&format!(", N50 {}", format_thousands(n50 as u64)));
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use flate2::read::GzDecoder;
+
+    /// writes the data to a temporary file and reads it back in
+    fn round_trip(filename: &str, gzip_level: u32, data: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let path = std::env::temp_dir().join(filename);
+        let path_str = path.to_str().unwrap();
+        let mut writer = create_output_writer(path_str, gzip_level).unwrap();
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap();
+
+        let raw_bytes: Vec<u8> = std::fs::read(&path).unwrap();
+        let mut decoded: Vec<u8> = vec![];
+        if path_str.ends_with(".gz") {
+            GzDecoder::new(&raw_bytes[..]).read_to_end(&mut decoded).unwrap();
+        } else {
+            decoded = raw_bytes.clone();
+        }
+        std::fs::remove_file(&path).unwrap();
+        (raw_bytes, decoded)
+    }
+
+    #[test]
+    fn test_gzip_levels() {
+        let data: Vec<u8> = b"{\"1\": 100, \"2\": 200}\n".repeat(1000);
+
+        let (raw_fast, decoded_fast) = round_trip("fastleng_test_level0.json.gz", 0, &data);
+        let (raw_best, decoded_best) = round_trip("fastleng_test_level9.json.gz", 9, &data);
+        assert_eq!(decoded_fast, data);
+        assert_eq!(decoded_best, data);
+
+        //level 0 is stored uncompressed, so it should be much larger
+        assert!(raw_best.len() < raw_fast.len());
+    }
+
+    #[test]
+    fn test_markdown_table() {
+        let length_counts: std::collections::BTreeMap<usize, u64> = [(1, 1), (2, 2)].iter().cloned().collect();
+        let stats = crate::length_stats::compute_length_stats(&length_counts);
+        let table: String = format_markdown_table(&stats);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 2 + stats.iter_fields().count());
+        assert_eq!(lines[0], "| Metric | Value |");
+        assert_eq!(lines[1], "| --- | --- |");
+        assert!(lines.contains(&"| total_bases | 5 |"));
+        assert!(lines.contains(&"| total_sequences | 3 |"));
+        assert!(lines.contains(&"| mean_length | 1.67 |"));
+        assert!(lines.contains(&"| median_length | 2.00 |"));
+        assert!(lines.contains(&"| n50 | 2 |"));
+
+        //only the labels change for proteins
+        let protein_table: String = format_markdown_table_with_seq_type(&stats, SeqType::Protein);
+        assert_eq!(protein_table.lines().count(), lines.len());
+        assert!(protein_table.contains("| total_residues | 5 |"));
+        assert!(protein_table.contains("| total_gigaresidues | 0.00 |"));
+        assert!(!protein_table.contains("bases"));
+        assert_eq!(format_markdown_table_with_seq_type(&stats, SeqType::Rna), table);
+    }
+
+    #[test]
+    fn test_plain_output() {
+        let data: Vec<u8> = b"{\"1\": 100}".to_vec();
+        let (raw_bytes, decoded) = round_trip("fastleng_test_plain.json", 9, &data);
+        assert_eq!(raw_bytes, data);
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn test_read_length_counts_json() {
+        let path = std::env::temp_dir().join("fastleng_test_read_lengths.json");
+        let path_str = path.to_str().unwrap();
+        std::fs::write(&path, b"{\"1\": 100, \"25\": 2}").unwrap();
+        let length_counts = read_length_counts_json(path_str);
+        std::fs::write(&path, b"[1, 2]").unwrap();
+        let bad_counts = read_length_counts_json(path_str);
+        std::fs::remove_file(&path).unwrap();
+
+        let expected: BTreeMap<usize, u64> = [(1, 100), (25, 2)].iter().cloned().collect();
+        assert_eq!(length_counts.unwrap(), expected);
+        assert!(bad_counts.is_err());
+        assert!(read_length_counts_json("./test_data/does_not_exist.json").is_err());
+    }
+
+    #[test]
+    fn test_assembly_stats() {
+        //10 x 100bp + 5 x 50bp + 5 x 10bp = 1300 bases
+        let length_counts: BTreeMap<usize, u64> = [(10, 5), (50, 5), (100, 10)].iter().cloned().collect();
+        let text: String = format_assembly_stats(&length_counts, "a.fa, b.fa");
+        let expected: String = [
+            "stats for a.fa, b.fa",
+            "sum = 1300, n = 20, ave = 65.00, largest = 100",
+            //650 bases needs 7 of the 100bp, 780 and 910 need 8 and 10, 1040 needs all 10 + 1 of the 50bp
+            "N50 = 100, n = 7",
+            "N60 = 100, n = 8",
+            "N70 = 100, n = 10",
+            "N80 = 50, n = 11",
+            //1170 bases is 10 x 100bp + 4 x 50bp (1200), and 1300 needs all of them
+            "N90 = 50, n = 14",
+            "N100 = 10, n = 20",
+            ""
+        ].join("\n");
+        assert_eq!(text, expected);
+
+        let empty_text: String = format_assembly_stats(&BTreeMap::new(), "empty.fa");
+        assert!(empty_text.starts_with("stats for empty.fa\nsum = 0, n = 0, ave = 0.00, largest = 0\nN50 = 0, n = 0\n"));
+        assert_eq!(empty_text.lines().count(), 8);
+    }
+
+    #[test]
+    fn test_format_summary_line() {
+        assert_eq!(format_thousands(0), "0");
+        assert_eq!(format_thousands(1000), "1,000");
+        assert_eq!(format_thousands(u64::MAX), "18,446,744,073,709,551,615");
+
+        //each unit boundary
+        assert_eq!(format_summary_line(1, 999, 999.0, Some(999), SeqType::Dna), "1 seqs, 999 bp, mean 999.0, N50 999");
+        assert_eq!(format_summary_line(2, 1500, 750.0, Some(1000), SeqType::Rna), "2 seqs, 1.5 kb, mean 750.0, N50 1,000");
+        assert_eq!(format_summary_line(1000, 2_340_000, 2340.0, Some(2340), SeqType::Dna), "1,000 seqs, 2.3 Mb, mean 2340.0, N50 2,340");
+        assert_eq!(format_summary_line(0, 0, 0.0, Some(0), SeqType::Dna), "0 seqs, 0 bp, mean 0.0, N50 0");
+        assert_eq!(format_summary_line(10, 12345, 1234.5, None, SeqType::Protein), "10 seqs, 12,345 residues, mean 1234.5");
+    }
+
+    #[test]
+    fn test_read_length_counts_txt() {
+        let path = std::env::temp_dir().join("fastleng_test_lengths.txt");
+        let path_str: &str = path.to_str().unwrap();
+        std::fs::write(&path, "3\n 10 \n\n3\r\n0\n").unwrap();
+        let expected: BTreeMap<usize, u64> = [(0, 1), (3, 2), (10, 1)].iter().cloned().collect();
+        assert_eq!(read_length_counts_txt(path_str).unwrap(), expected);
+
+        //the error names the offending line
+        std::fs::write(&path, "3\n4\n-5\n").unwrap();
+        let error = read_length_counts_txt(path_str).unwrap_err();
+        assert!(error.to_string().contains("line 3"), "{}", error);
+        std::fs::write(&path, "3\n4.5\n").unwrap();
+        assert!(read_length_counts_txt(path_str).unwrap_err().to_string().contains("line 2"));
+        std::fs::remove_file(&path).unwrap();
+
+        //compressed lists work the same way
+        let gz_path = std::env::temp_dir().join("fastleng_test_lengths.txt.gz");
+        let gz_path_str: &str = gz_path.to_str().unwrap();
+        let mut writer = create_output_writer(gz_path_str, DEFAULT_GZIP_LEVEL).unwrap();
+        writer.write_all(b"7\n7\n").unwrap();
+        writer.finish().unwrap();
+        assert_eq!(read_length_counts_txt(gz_path_str).unwrap(), [(7, 2)].iter().cloned().collect());
+        std::fs::remove_file(&gz_path).unwrap();
+        assert!(read_length_counts_txt("./test_data/does_not_exist.txt").is_err());
+    }
+
+    #[test]
+    fn test_read_length_counts_txt_sorted() {
+        let path = std::env::temp_dir().join("fastleng_test_sorted_lengths.txt");
+        let path_str: &str = path.to_str().unwrap();
+
+        //repeats and blank lines are fine, and the counts are unchanged by the check
+        std::fs::write(&path, "0\n3\n3\n\n10\n").unwrap();
+        assert_eq!(read_length_counts_txt_with_check(path_str, true, &LoaderConfig::new()).unwrap(), read_length_counts_txt(path_str).unwrap());
+
+        //the first out-of-order line is named, even past a blank line
+        std::fs::write(&path, "3\n10\n\n4\n2\n").unwrap();
+        let error = read_length_counts_txt_with_check(path_str, true, &LoaderConfig::new()).unwrap_err();
+        assert!(error.to_string().contains("line 4") && error.to_string().contains("4 follows 10"), "{}", error);
+        assert!(read_length_counts_txt_with_check(path_str, false, &LoaderConfig::new()).is_ok());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
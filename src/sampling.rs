@@ -0,0 +1,420 @@
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::length_stats::{compute_n_score, round_to_decimals};
+
+/// A small, seeded pseudo-random number generator (SplitMix64).
+/// This is not cryptographically secure, but it is fast and fully reproducible from the seed across platforms and releases.
+#[derive(Clone, Debug)]
+pub struct SeededRng {
+    state: u64
+}
+
+impl SeededRng {
+    /// Creates a new generator from a seed; the same seed always produces the same sequence.
+    /// # Arguments
+    /// * `seed` - the seed value
+    /// # Examples
+    /// ```
+    /// use fastleng::sampling::SeededRng;
+    /// let mut rng_a = SeededRng::new(42);
+    /// let mut rng_b = SeededRng::new(42);
+    /// assert_eq!(rng_a.next_u64(), rng_b.next_u64());
+    /// ```
+    pub fn new(seed: u64) -> SeededRng {
+        SeededRng {
+            state: seed
+        }
+    }
+
+    /// Returns the next pseudo-random 64-bit value.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z: u64 = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a pseudo-random value uniformly distributed in `[0.0, 1.0)`.
+    pub fn next_f64(&mut self) -> f64 {
+        //use the upper 53 bits, which is the full precision of an f64 mantissa
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Configuration for per-record sampling; each record is independently kept with probability `fraction`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SamplingConfig {
+    /// The probability that any single record is included, in the range `[0.0, 1.0]`
+    pub fraction: f64,
+    /// The seed for the random number generator, allowing the sample to be reproduced
+    pub seed: u64
+}
+
+impl SamplingConfig {
+    /// Creates a new random number generator seeded from this configuration
+    pub fn build_rng(&self) -> SeededRng {
+        SeededRng::new(self.seed)
+    }
+}
+
+/// Estimates of the full-input totals derived from a sample, each with a standard error
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct SampledEstimates {
+    /// The estimated total number of bases in the full input
+    pub estimated_total_bases: f64,
+    /// The standard error of `estimated_total_bases`
+    pub estimated_total_bases_stderr: f64,
+    /// The estimated total number of sequences in the full input
+    pub estimated_total_sequences: f64,
+    /// The standard error of `estimated_total_sequences`
+    pub estimated_total_sequences_stderr: f64
+}
+
+impl SampledEstimates {
+    /// Rounds all of the estimates in place to `decimals` places
+    /// # Arguments
+    /// * `decimals` - the number of decimal places to keep
+    pub fn round_floats(&mut self, decimals: u32) {
+        self.estimated_total_bases = round_to_decimals(self.estimated_total_bases, decimals);
+        self.estimated_total_bases_stderr = round_to_decimals(self.estimated_total_bases_stderr, decimals);
+        self.estimated_total_sequences = round_to_decimals(self.estimated_total_sequences, decimals);
+        self.estimated_total_sequences_stderr = round_to_decimals(self.estimated_total_sequences_stderr, decimals);
+    }
+}
+
+/// This will scale the totals of a sample back up to estimates for the full input.
+/// Since each record is kept independently with probability `fraction`, each observed record stands in for `1 / fraction` records.
+/// The standard errors follow from the variance of this (Horvitz-Thompson) estimator, e.g. `sqrt(n * (1 - fraction)) / fraction` for the sequence count.
+/// Returns `None` if `fraction` is not positive, since nothing can be inferred from an empty sample.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sampled sequence length as the key, and the value the number of sampled sequences with that length
+/// * `fraction` - the sampling fraction that was used to build `length_counts`
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::sampling::estimate_sampled_totals;
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3)
+/// ].iter().cloned().collect();
+/// let estimates = estimate_sampled_totals(&length_counts, 0.5).unwrap();
+/// assert_eq!(estimates.estimated_total_bases, 160.0);
+/// assert_eq!(estimates.estimated_total_sequences, 26.0);
+/// ```
+pub fn estimate_sampled_totals(length_counts: &BTreeMap<usize, u64>, fraction: f64) -> Option<SampledEstimates> {
+    if fraction <= 0.0 {
+        return None;
+    }
+
+    let mut sampled_bases: f64 = 0.0;
+    let mut sampled_squared_bases: f64 = 0.0;
+    let mut sampled_seqs: f64 = 0.0;
+    for (&seq_len, &seq_count) in length_counts.iter() {
+        sampled_bases += (seq_len as f64) * (seq_count as f64);
+        sampled_squared_bases += (seq_len as f64) * (seq_len as f64) * (seq_count as f64);
+        sampled_seqs += seq_count as f64;
+    }
+
+    let keep_complement: f64 = 1.0 - fraction;
+    Some(SampledEstimates {
+        estimated_total_bases: sampled_bases / fraction,
+        estimated_total_bases_stderr: (keep_complement * sampled_squared_bases).sqrt() / fraction,
+        estimated_total_sequences: sampled_seqs / fraction,
+        estimated_total_sequences_stderr: (keep_complement * sampled_seqs).sqrt() / fraction
+    })
+}
+
+/// This will estimate how stable the N50 is by bootstrap resampling, drawing `total_seqs` sequences with replacement from the length distribution `replicates` times and computing the N50 of each draw.
+/// Returns the mean N50 of the replicates along with the 2.5th and 97.5th percentiles, which form a 95% confidence interval; an empty input returns all zeros.
+/// Each replicate draws every sequence again, so the run time is proportional to `replicates * total_seqs`; this is intended for assemblies and other small inputs.
+/// # Arguments
+/// * `length_counts` - a BTreeMap with the sequence length as the key, and the value the total number of sequences with that length
+/// * `total_seqs` - the total number of sequences represented by `length_counts`, this can be computed by `compute_total_counts(...)`
+/// * `replicates` - the number of bootstrap replicates, must be at least 1
+/// * `seed` - the seed for the random number generator, so the same inputs always give the same interval
+/// # Examples
+/// ```
+/// use std::collections::BTreeMap;
+/// use fastleng::sampling::bootstrap_n50;
+/// let length_counts: BTreeMap<usize, u64> = [
+///     (5, 10),
+///     (10, 3)
+/// ].iter().cloned().collect();
+/// let (mean_n50, lower_n50, upper_n50) = bootstrap_n50(&length_counts, 13, 100, 0);
+/// assert!(lower_n50 <= mean_n50 && mean_n50 <= upper_n50);
+/// assert!(lower_n50 >= 5.0 && upper_n50 <= 10.0);
+/// ```
+pub fn bootstrap_n50(length_counts: &BTreeMap<usize, u64>, total_seqs: u64, replicates: usize, seed: u64) -> (f64, f64, f64) {
+    assert!(replicates >= 1);
+    if total_seqs == 0 {
+        return (0.0, 0.0, 0.0);
+    }
+
+    //each draw picks a sequence index and looks up its length from the cumulative counts
+    let mut cumulative_counts: Vec<(u64, usize)> = Vec::with_capacity(length_counts.len());
+    let mut current_count: u64 = 0;
+    for (&seq_len, &seq_count) in length_counts.iter() {
+        current_count += seq_count;
+        cumulative_counts.push((current_count, seq_len));
+    }
+    assert_eq!(current_count, total_seqs);
+
+    let mut rng: SeededRng = SeededRng::new(seed);
+    let mut replicate_n50s: Vec<f64> = Vec::with_capacity(replicates);
+    for _ in 0..replicates {
+        let mut resampled_counts: BTreeMap<usize, u64> = BTreeMap::new();
+        let mut resampled_bases: u64 = 0;
+        for _ in 0..total_seqs {
+            let seq_index: u64 = rng.next_u64() % total_seqs;
+            let bin: usize = cumulative_counts.partition_point(|&(cumulative, _seq_len)| cumulative <= seq_index);
+            let seq_len: usize = cumulative_counts[bin].1;
+            *resampled_counts.entry(seq_len).or_insert(0) += 1;
+            resampled_bases += seq_len as u64;
+        }
+        replicate_n50s.push(compute_n_score(&resampled_counts, resampled_bases, 50) as f64);
+    }
+
+    //the interval comes from the order statistics of the replicates, interpolating between neighbors
+    replicate_n50s.sort_by(|a, b| a.total_cmp(b));
+    let percentile = |fraction: f64| -> f64 {
+        let position: f64 = fraction * (replicates - 1) as f64;
+        let lower: usize = position.floor() as usize;
+        let upper: usize = position.ceil() as usize;
+        replicate_n50s[lower] + (replicate_n50s[upper] - replicate_n50s[lower]) * (position - lower as f64)
+    };
+    let mean_n50: f64 = replicate_n50s.iter().sum::<f64>() / replicates as f64;
+    (mean_n50, percentile(0.025), percentile(0.975))
+}
+
+/// This will randomly select a fraction of the input files (rather than records), e.g. to profile a representative subset of a large cohort.
+/// Exactly `fraction * filenames.len()` files are selected (rounded to the nearest file, but at least one if `fraction > 0.0`), and they are returned in their original order.
+/// The same seed and file list always select the same files.
+/// # Arguments
+/// * `filenames` - the full list of input files
+/// * `fraction` - the fraction of the files to select, in the range `[0.0, 1.0]`
+/// * `seed` - the seed for the random number generator
+/// # Examples
+/// ```
+/// use fastleng::sampling::sample_files;
+/// let filenames = ["a.fq", "b.fq", "c.fq", "d.fq"];
+/// let selected = sample_files(&filenames, 0.5, 0);
+/// assert_eq!(selected.len(), 2);
+/// assert_eq!(selected, sample_files(&filenames, 0.5, 0));
+/// ```
+pub fn sample_files<T: Clone>(filenames: &[T], fraction: f64, seed: u64) -> Vec<T> {
+    assert!((0.0..=1.0).contains(&fraction));
+    let mut select_count: usize = (fraction * filenames.len() as f64).round() as usize;
+    if fraction > 0.0 && !filenames.is_empty() {
+        select_count = select_count.max(1);
+    }
+
+    //a partial Fisher-Yates shuffle of the indices picks the files, then the original order is restored
+    let mut rng = SeededRng::new(seed);
+    let mut indices: Vec<usize> = (0..filenames.len()).collect();
+    for i in 0..select_count {
+        let j: usize = i + (rng.next_u64() % (indices.len() - i) as u64) as usize;
+        indices.swap(i, j);
+    }
+    let mut selected_indices: Vec<usize> = indices[..select_count].to_vec();
+    selected_indices.sort_unstable();
+    selected_indices.into_iter().map(|i| filenames[i].clone()).collect()
+}
+
+/// A fixed-size uniform random sample of the lengths streamed through it, kept with reservoir sampling (Vitter's Algorithm R).
+/// Every length added has the same chance of being in the sample, and the memory used depends only on the capacity, not on the number of lengths added.
+/// # Examples
+/// ```
+/// use fastleng::sampling::LengthReservoir;
+/// let mut reservoir = LengthReservoir::new(3, 0);
+/// for seq_len in 1..=1000 {
+///     reservoir.add(seq_len);
+/// }
+/// assert_eq!(reservoir.seen(), 1000);
+/// assert_eq!(reservoir.sample().len(), 3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct LengthReservoir {
+    /// The maximum number of lengths kept
+    capacity: usize,
+    /// The number of lengths added so far
+    seen: u64,
+    /// The current sample, in no particular order
+    sample: Vec<usize>,
+    /// Decides which lengths replace the sampled ones
+    rng: SeededRng
+}
+
+impl LengthReservoir {
+    /// Creates an empty reservoir that keeps up to `capacity` lengths; the same seed and input always produce the same sample
+    pub fn new(capacity: usize, seed: u64) -> LengthReservoir {
+        LengthReservoir {
+            capacity,
+            seen: 0,
+            sample: Vec::with_capacity(capacity.min(1 << 20)),
+            rng: SeededRng::new(seed)
+        }
+    }
+
+    /// Offers a single length to the sample
+    pub fn add(&mut self, seq_len: usize) {
+        self.seen += 1;
+        if self.sample.len() < self.capacity {
+            self.sample.push(seq_len);
+        } else {
+            //the i-th length replaces a random slot with probability capacity / i
+            let slot: u64 = self.rng.next_u64() % self.seen;
+            if slot < self.capacity as u64 {
+                self.sample[slot as usize] = seq_len;
+            }
+        }
+    }
+
+    /// Returns the number of lengths added so far
+    pub fn seen(&self) -> u64 {
+        self.seen
+    }
+
+    /// Returns the sampled lengths, which are every length added if there were no more than the capacity
+    pub fn sample(&self) -> &[usize] {
+        &self.sample
+    }
+
+    /// Consumes the reservoir, returning the sampled lengths
+    pub fn into_sample(self) -> Vec<usize> {
+        self.sample
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_rng_reproducible() {
+        let mut rng_a = SeededRng::new(1234);
+        let mut rng_b = SeededRng::new(1234);
+        let mut rng_c = SeededRng::new(4321);
+        let values_a: Vec<u64> = (0..100).map(|_| rng_a.next_u64()).collect();
+        let values_b: Vec<u64> = (0..100).map(|_| rng_b.next_u64()).collect();
+        let values_c: Vec<u64> = (0..100).map(|_| rng_c.next_u64()).collect();
+        assert_eq!(values_a, values_b);
+        assert_ne!(values_a, values_c);
+    }
+
+    #[test]
+    fn test_seeded_rng_f64_range() {
+        let mut rng = SeededRng::new(0);
+        let num_samples = 100000;
+        let mut total: f64 = 0.0;
+        for _ in 0..num_samples {
+            let value = rng.next_f64();
+            assert!((0.0..1.0).contains(&value));
+            total += value;
+        }
+
+        //the mean of a uniform distribution should be very close to 0.5
+        let mean = total / num_samples as f64;
+        assert!((mean - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_estimate_sampled_totals() {
+        let length_counts: BTreeMap<usize, u64> = [
+            (2, 4),
+            (4, 1)
+        ].iter().cloned().collect();
+
+        //a quarter sample scales everything by 4
+        let estimates = estimate_sampled_totals(&length_counts, 0.25).unwrap();
+        assert_eq!(estimates, SampledEstimates {
+            estimated_total_bases: 48.0,
+            estimated_total_bases_stderr: (0.75_f64 * 32.0).sqrt() / 0.25,
+            estimated_total_sequences: 20.0,
+            estimated_total_sequences_stderr: (0.75_f64 * 5.0).sqrt() / 0.25
+        });
+
+        //a full sample is exact
+        let estimates = estimate_sampled_totals(&length_counts, 1.0).unwrap();
+        assert_eq!(estimates.estimated_total_bases, 12.0);
+        assert_eq!(estimates.estimated_total_bases_stderr, 0.0);
+        assert_eq!(estimates.estimated_total_sequences, 5.0);
+        assert_eq!(estimates.estimated_total_sequences_stderr, 0.0);
+
+        //nothing can be estimated without sampling anything
+        assert_eq!(estimate_sampled_totals(&length_counts, 0.0), None);
+    }
+
+    #[test]
+    fn test_bootstrap_n50() {
+        let length_counts: BTreeMap<usize, u64> = [
+            (1, 50),
+            (10, 5),
+            (100, 1)
+        ].iter().cloned().collect();
+
+        //the same seed gives the same interval, and every replicate N50 is one of the lengths
+        let bootstrap = bootstrap_n50(&length_counts, 56, 200, 42);
+        assert_eq!(bootstrap, bootstrap_n50(&length_counts, 56, 200, 42));
+        let (mean_n50, lower_n50, upper_n50) = bootstrap;
+        assert!(lower_n50 <= mean_n50 && mean_n50 <= upper_n50);
+        assert!(lower_n50 >= 1.0 && upper_n50 <= 100.0);
+
+        //a single contig dominates the N50 here, so the interval should be wide
+        assert!(upper_n50 - lower_n50 >= 9.0, "{:?}", bootstrap);
+
+        //identical lengths always give the same N50
+        let same_counts: BTreeMap<usize, u64> = [(7, 20)].iter().cloned().collect();
+        assert_eq!(bootstrap_n50(&same_counts, 20, 10, 0), (7.0, 7.0, 7.0));
+        assert_eq!(bootstrap_n50(&BTreeMap::new(), 0, 10, 0), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_sample_files() {
+        let filenames: Vec<String> = (0..10).map(|i| format!("sample_{i}.fq")).collect();
+
+        //the count is exact, the order is kept, and the seed controls which files are picked
+        let selected = sample_files(&filenames, 0.3, 1);
+        assert_eq!(selected.len(), 3);
+        assert!(selected.windows(2).all(|pair| filenames.iter().position(|f| f == &pair[0]) < filenames.iter().position(|f| f == &pair[1])));
+        assert_eq!(selected, sample_files(&filenames, 0.3, 1));
+        assert!((0..20).any(|seed| sample_files(&filenames, 0.3, seed) != selected));
+
+        //the extremes, and a tiny fraction still picks one file
+        assert_eq!(sample_files(&filenames, 1.0, 5), filenames);
+        assert!(sample_files(&filenames, 0.0, 5).is_empty());
+        assert_eq!(sample_files(&filenames, 0.01, 5).len(), 1);
+        assert!(sample_files::<String>(&[], 0.5, 0).is_empty());
+    }
+
+    #[test]
+    fn test_length_reservoir() {
+        //small inputs are kept entirely, in order
+        let mut small_reservoir = LengthReservoir::new(10, 0);
+        [5, 1, 7].iter().for_each(|&seq_len| small_reservoir.add(seq_len));
+        assert_eq!(small_reservoir.sample(), &[5, 1, 7]);
+
+        //large inputs are sampled uniformly and reproducibly
+        let sample_reservoir = |seed: u64| -> Vec<usize> {
+            let mut reservoir = LengthReservoir::new(1000, seed);
+            (0..100000).for_each(|seq_len| reservoir.add(seq_len));
+            assert_eq!(reservoir.seen(), 100000);
+            reservoir.into_sample()
+        };
+        let sample: Vec<usize> = sample_reservoir(42);
+        assert_eq!(sample.len(), 1000);
+        assert_eq!(sample, sample_reservoir(42));
+        assert_ne!(sample, sample_reservoir(43));
+        let mean: f64 = sample.iter().sum::<usize>() as f64 / sample.len() as f64;
+        assert!((mean - 50000.0).abs() < 3000.0, "{mean}");
+        let late_count: usize = sample.iter().filter(|&&seq_len| seq_len >= 50000).count();
+        assert!((400..600).contains(&late_count), "{late_count}");
+
+        //a zero capacity keeps nothing
+        let mut empty_reservoir = LengthReservoir::new(0, 0);
+        empty_reservoir.add(5);
+        assert!(empty_reservoir.sample().is_empty());
+    }
+}
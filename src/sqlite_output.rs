@@ -0,0 +1,106 @@
+
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::length_stats::LengthStats;
+
+/// The table that `insert_length_stats(...)` adds a row to
+pub const SQLITE_TABLE: &str = "fastleng_stats";
+
+/// This will insert the `LengthStats` of one run as a new row of the `SQLITE_TABLE` table, creating the database and the table if they are absent.
+/// Each statistic has its own column named after its field; any that are missing from an existing table (e.g. one created by an older fastleng) are added first, and the older rows are left `NULL` in them.
+/// The row also records the input filename(s) and the time of the insert, in seconds since the Unix epoch.
+/// # Arguments
+/// * `db_filename` - the SQLite database file
+/// * `input_label` - the input file(s) that the stats describe
+/// * `length_stats` - the stats to insert
+pub fn insert_length_stats(db_filename: &str, input_label: &str, length_stats: &LengthStats) -> Result<(), Box<dyn std::error::Error>> {
+    let connection: Connection = Connection::open(db_filename)?;
+    let timestamp: u64 = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    insert_length_stats_row(&connection, input_label, timestamp, length_stats)?;
+    Ok(())
+}
+
+/// Inserts a single row into an open database, see `insert_length_stats(...)`
+fn insert_length_stats_row(connection: &Connection, input_label: &str, timestamp: u64, length_stats: &LengthStats) -> Result<(), Box<dyn std::error::Error>> {
+    //the serialized fields keep the integer statistics exact, unlike `LengthStats::iter_fields()`
+    let fields: Vec<(String, Value)> = match serde_json::to_value(length_stats)? {
+        serde_json::Value::Object(stats_map) => stats_map.into_iter()
+            .map(|(name, value)| {
+                let column_value: Value = match (value.as_i64(), value.as_f64()) {
+                    (Some(int_value), _) => Value::Integer(int_value),
+                    (None, Some(float_value)) => Value::Real(float_value),
+                    (None, None) => Value::Null
+                };
+                (name, column_value)
+            })
+            .collect(),
+        _ => return Err("LengthStats did not serialize to a JSON object".into())
+    };
+
+    connection.execute(&format!("CREATE TABLE IF NOT EXISTS {SQLITE_TABLE} (filename TEXT NOT NULL, timestamp INTEGER NOT NULL)"), [])?;
+    let existing_columns: Vec<String> = {
+        let mut statement = connection.prepare(&format!("PRAGMA table_info({SQLITE_TABLE})"))?;
+        let column_names = statement.query_map([], |row| row.get::<_, String>(1))?;
+        column_names.collect::<Result<Vec<String>, rusqlite::Error>>()?
+    };
+    for (name, value) in fields.iter().filter(|(name, _value)| !existing_columns.contains(name)) {
+        let column_type: &str = if matches!(value, Value::Integer(_)) { "INTEGER" } else { "REAL" };
+        connection.execute(&format!("ALTER TABLE {SQLITE_TABLE} ADD COLUMN {name} {column_type}"), [])?;
+    }
+
+    let column_names: Vec<&str> = fields.iter().map(|(name, _value)| name.as_str()).collect();
+    let placeholders: Vec<String> = (0..fields.len() + 2).map(|index| format!("?{}", index + 1)).collect();
+    let mut values: Vec<Value> = vec![Value::Text(input_label.to_string()), Value::Integer(timestamp as i64)];
+    values.extend(fields.into_iter().map(|(_name, value)| value));
+    connection.execute(
+        &format!("INSERT INTO {SQLITE_TABLE} (filename, timestamp, {}) VALUES ({})", column_names.join(", "), placeholders.join(", ")),
+        params_from_iter(values)
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::length_stats::compute_length_stats;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_insert_length_stats_row() {
+        let length_counts: BTreeMap<usize, u64> = [(5, 10), (10, 3)].iter().cloned().collect();
+        let length_stats: LengthStats = compute_length_stats(&length_counts);
+        let connection: Connection = Connection::open_in_memory().unwrap();
+
+        //the first insert creates the table, the second only adds a row
+        insert_length_stats_row(&connection, "first.fa", 1, &length_stats).unwrap();
+        insert_length_stats_row(&connection, "second.fa", 2, &length_stats).unwrap();
+        let row_count: i64 = connection.query_row(&format!("SELECT COUNT(*) FROM {SQLITE_TABLE}"), [], |row| row.get(0)).unwrap();
+        assert_eq!(row_count, 2);
+
+        let (total_bases, n50, mean_length): (i64, i64, f64) = connection.query_row(
+            &format!("SELECT total_bases, n50, mean_length FROM {SQLITE_TABLE} WHERE filename = 'second.fa'"), [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        ).unwrap();
+        assert_eq!((total_bases, n50), (80, 5));
+        assert_eq!(mean_length, length_stats.mean_length);
+    }
+
+    #[test]
+    fn test_insert_length_stats_adds_columns() {
+        //a table from an older version that only had a couple of the statistics
+        let connection: Connection = Connection::open_in_memory().unwrap();
+        connection.execute(&format!("CREATE TABLE {SQLITE_TABLE} (filename TEXT NOT NULL, timestamp INTEGER NOT NULL, total_bases INTEGER, n50 INTEGER)"), []).unwrap();
+        connection.execute(&format!("INSERT INTO {SQLITE_TABLE} VALUES ('old.fa', 0, 100, 10)"), []).unwrap();
+
+        let length_counts: BTreeMap<usize, u64> = [(4, 2)].iter().cloned().collect();
+        insert_length_stats_row(&connection, "new.fa", 1, &compute_length_stats(&length_counts)).unwrap();
+
+        //the new columns are filled in for the new row and left empty for the old one
+        let gini_values: Vec<Option<f64>> = connection.prepare(&format!("SELECT gini_coefficient FROM {SQLITE_TABLE} ORDER BY timestamp")).unwrap()
+            .query_map([], |row| row.get(0)).unwrap()
+            .collect::<Result<Vec<Option<f64>>, rusqlite::Error>>().unwrap();
+        assert_eq!(gini_values, vec![None, Some(0.0)]);
+    }
+}
@@ -0,0 +1,272 @@
+
+use flate2::read::MultiGzDecoder;
+use log::{info, warn};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Cursor, Read};
+
+use crate::fastx_loader::stream_fastx_reader_lengths;
+use crate::format::format_from_extension;
+use crate::loader::{KeyedLengthCounts, LoaderConfig};
+
+/// The size of a tar header and the alignment of each member's data
+const TAR_BLOCK_SIZE: usize = 512;
+
+/// A single member of a tar archive, as described by its header
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TarMember {
+    /// The path of the member inside the archive
+    pub name: String,
+    /// The size of the member data in bytes
+    pub size: u64,
+    /// True if this is a normal file (as opposed to a directory, link, etc.)
+    pub is_file: bool
+}
+
+/// This will iterate through the FASTX members of a tar archive (optionally gzip compressed) and gather the lengths of each member into its own BTreeMap.
+/// Members are identified as FASTX by their extension (e.g. `.fa` or `.fq.gz`), and any other members are skipped with a warning.
+/// The `head`, `max_records`, `min_mean_qual`, and length filters of `config` are applied to each member, but sampling is not.
+/// # Arguments
+/// * `filename` - the tar archive to read
+/// * `config` - the loading options and filters to apply
+/// # Examples
+/// ```
+/// use fastleng::tar_loader::gather_tar_stats;
+/// use fastleng::loader::LoaderConfig;
+/// let member_counts = gather_tar_stats("./test_data/fastx_archive.tar.gz", &LoaderConfig::new()).unwrap();
+/// assert_eq!(member_counts["five_strings.fa"].values().sum::<u64>(), 5);
+/// ```
+pub fn gather_tar_stats(filename: &str, config: &LoaderConfig) -> Result<KeyedLengthCounts<String>, Box<dyn std::error::Error>> {
+    let mut member_counts: KeyedLengthCounts<String> = BTreeMap::new();
+    let mut distinct_lengths: usize = 0;
+    let mut file = File::open(filename)?;
+
+    //same approach as format detection: peek for the gzip magic before deciding how to read it
+    let mut magic: Vec<u8> = vec![];
+    (&mut file).take(2).read_to_end(&mut magic)?;
+    let is_gzip: bool = magic == [0x1f, 0x8b];
+    let full_reader = Cursor::new(magic).chain(file);
+    let mut reader: Box<dyn Read + Send> = if is_gzip {
+        Box::new(MultiGzDecoder::new(full_reader))
+    } else {
+        Box::new(full_reader)
+    };
+
+    info!("Loading archive \"{}\"...", filename);
+    while let Some(member) = read_tar_member(&mut reader).map_err(|e| format!("{e}: {filename:?}"))? {
+        let mut member_reader = (&mut reader).take(member.size);
+        if !member.is_file {
+            //directories and links have no data we care about
+        } else if format_from_extension(&member.name, false).is_none() {
+            warn!("Skipping non-FASTX archive member: {:?}", member.name);
+        } else if member.size == 0 {
+            warn!("Skipping empty archive member: {:?}", member.name);
+        } else {
+            let hash_stats: &mut BTreeMap<usize, u64> = member_counts.entry(member.name.clone()).or_default();
+            stream_fastx_reader_lengths(&mut member_reader, &member.name, config, |_seq_id, seq_len| {
+                if config.passes_length_filters(seq_len) {
+                    config.count_keyed_length(hash_stats, seq_len, &mut distinct_lengths);
+                }
+                Ok(())
+            })?;
+        }
+
+        //skip anything left unread (e.g. with head) along with the padding to the next block
+        std::io::copy(&mut member_reader, &mut std::io::sink())?;
+        let padding: u64 = (TAR_BLOCK_SIZE as u64 - member.size % TAR_BLOCK_SIZE as u64) % TAR_BLOCK_SIZE as u64;
+        let skipped: u64 = std::io::copy(&mut (&mut reader).take(padding), &mut std::io::sink())?;
+        if skipped != padding {
+            return Err(format!("Truncated tar archive after member: {:?}", member.name).into());
+        }
+    }
+    info!("Finished loading archive with {} FASTX members.", member_counts.len());
+    Ok(member_counts)
+}
+
+/// Reads the header(s) of the next tar member, leaving `reader` at the start of the member data.
+/// Returns `None` at the end of the archive; GNU long names and PAX `path` records are applied to the member they describe.
+/// # Arguments
+/// * `reader` - the uncompressed tar stream, positioned at a header block
+fn read_tar_member<R: Read>(reader: &mut R) -> Result<Option<TarMember>, Box<dyn std::error::Error>> {
+    let mut long_name: Option<String> = None;
+    loop {
+        let mut header = [0_u8; TAR_BLOCK_SIZE];
+        let filled: usize = read_full_block(reader, &mut header)?;
+        if filled == 0 || header.iter().all(|&b| b == 0) {
+            //a missing or zero block marks the end of the archive
+            return Ok(None);
+        }
+        if filled < TAR_BLOCK_SIZE {
+            return Err("Not a tar archive (or a truncated one), the last header is incomplete".into());
+        }
+        if !header_checksum_matches(&header) {
+            return Err("Not a tar archive, a header checksum does not match".into());
+        }
+        let size: u64 = parse_tar_size(&header[124..136])?;
+        let type_flag: u8 = header[156];
+        match type_flag {
+            b'L' | b'x' => {
+                //these describe the next header, so read the data and keep going
+                let mut extended_data: Vec<u8> = vec![];
+                (&mut *reader).take(size).read_to_end(&mut extended_data)?;
+                let padding: u64 = (TAR_BLOCK_SIZE as u64 - size % TAR_BLOCK_SIZE as u64) % TAR_BLOCK_SIZE as u64;
+                std::io::copy(&mut (&mut *reader).take(padding), &mut std::io::sink())?;
+                if type_flag == b'L' {
+                    long_name = Some(String::from_utf8_lossy(trim_nul(&extended_data)).to_string());
+                } else if let Some(pax_path) = parse_pax_path(&extended_data) {
+                    long_name = Some(pax_path);
+                }
+            },
+            b'g' => {
+                //global PAX headers do not apply to a single member
+                std::io::copy(&mut (&mut *reader).take(size.div_ceil(TAR_BLOCK_SIZE as u64) * TAR_BLOCK_SIZE as u64), &mut std::io::sink())?;
+            },
+            _ => {
+                let name: String = long_name.unwrap_or_else(|| {
+                    let base_name: &[u8] = trim_nul(&header[0..100]);
+                    let prefix: &[u8] = if &header[257..262] == b"ustar" { trim_nul(&header[345..500]) } else { b"" };
+                    if prefix.is_empty() {
+                        String::from_utf8_lossy(base_name).to_string()
+                    } else {
+                        format!("{}/{}", String::from_utf8_lossy(prefix), String::from_utf8_lossy(base_name))
+                    }
+                });
+                return Ok(Some(TarMember {
+                    name,
+                    size,
+                    is_file: type_flag == b'0' || type_flag == 0 || type_flag == b'7'
+                }));
+            }
+        };
+    }
+}
+
+/// Fills `block` from `reader`, returning the number of bytes read, which is less than the block size only if the stream ended first
+fn read_full_block<R: Read>(reader: &mut R, block: &mut [u8]) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut filled: usize = 0;
+    while filled < block.len() {
+        let read_count: usize = reader.read(&mut block[filled..])?;
+        if read_count == 0 {
+            break;
+        }
+        filled += read_count;
+    }
+    Ok(filled)
+}
+
+/// Returns true if the checksum field of a tar header matches the header, i.e. the sum of its bytes with the checksum field itself counted as spaces.
+/// Both the standard unsigned sum and the signed sum written by some old tar implementations are accepted.
+/// # Arguments
+/// * `header` - a complete header block
+fn header_checksum_matches(header: &[u8; TAR_BLOCK_SIZE]) -> bool {
+    let stored_checksum: u64 = match std::str::from_utf8(trim_nul(&header[148..156])).ok().and_then(|octal| u64::from_str_radix(octal.trim(), 8).ok()) {
+        Some(checksum) => checksum,
+        None => return false
+    };
+    let (unsigned_sum, signed_sum) = header.iter().enumerate().fold((0_u64, 0_i64), |(unsigned_sum, signed_sum), (index, &b)| {
+        let b: u8 = if (148..156).contains(&index) { b' ' } else { b };
+        (unsigned_sum + b as u64, signed_sum + b as i8 as i64)
+    });
+    stored_checksum == unsigned_sum || stored_checksum as i64 == signed_sum
+}
+
+/// Returns the bytes up to the first NUL
+fn trim_nul(field: &[u8]) -> &[u8] {
+    field.split(|&b| b == 0).next().unwrap_or(b"")
+}
+
+/// This will parse the size field of a tar header, which is either NUL/space terminated octal or (for large files) big-endian base-256.
+/// # Arguments
+/// * `field` - the 12 byte size field of the header
+fn parse_tar_size(field: &[u8]) -> Result<u64, Box<dyn std::error::Error>> {
+    if field[0] & 0x80 != 0 {
+        //base-256, the remaining bits of the first byte are the most significant
+        let size: u64 = field[1..].iter().fold((field[0] & 0x7f) as u64, |acc, &b| (acc << 8) | b as u64);
+        return Ok(size);
+    }
+    let octal: &str = std::str::from_utf8(trim_nul(field))?.trim();
+    if octal.is_empty() {
+        return Ok(0);
+    }
+    Ok(u64::from_str_radix(octal, 8)?)
+}
+
+/// This will find the `path` record in PAX extended header data, where each record is `<length> <key>=<value>\n`.
+/// # Arguments
+/// * `pax_data` - the data of a PAX extended header member
+fn parse_pax_path(pax_data: &[u8]) -> Option<String> {
+    let pax_text: String = String::from_utf8_lossy(pax_data).to_string();
+    pax_text.lines()
+        .filter_map(|record| record.split_once(' ').map(|(_length, key_value)| key_value))
+        .filter_map(|key_value| key_value.split_once('='))
+        .find(|(key, _value)| *key == "path")
+        .map(|(_key, value)| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fastx_loader::gather_fastx_stats;
+
+    #[test]
+    fn test_gather_tar_stats() {
+        //the same members should load identically from a plain or gzipped archive
+        for filename in ["./test_data/fastx_archive.tar", "./test_data/fastx_archive.tar.gz"] {
+            let member_counts = gather_tar_stats(filename, &LoaderConfig::new()).unwrap();
+            let member_names: Vec<&String> = member_counts.keys().collect();
+            assert_eq!(member_names.len(), 5);
+            assert_eq!(member_counts["five_strings.fa"], gather_fastx_stats("./test_data/five_strings.fa").unwrap());
+            assert_eq!(member_counts["reads/five_strings.fq"], gather_fastx_stats("./test_data/five_strings.fq").unwrap());
+            assert_eq!(member_counts["reads/long_strings.fa.gz"], gather_fastx_stats("./test_data/long_strings.fa").unwrap());
+
+            //these have names too long for the basic header, stored with the ustar prefix and a PAX header respectively
+            let prefix_name: String = format!("{}/small_strings.fa", "nested_directory_name".repeat(6));
+            assert_eq!(member_counts[&prefix_name], gather_fastx_stats("./test_data/small_strings.fa").unwrap());
+            let pax_name: String = format!("reads/{}single_string.fa", "very_long_member_name_".repeat(6));
+            assert_eq!(member_counts[&pax_name], gather_fastx_stats("./test_data/single_string.fa").unwrap());
+        }
+    }
+
+    #[test]
+    fn test_tar_options() {
+        let config = LoaderConfig::new().head(2).min_length(2);
+        let member_counts = gather_tar_stats("./test_data/fastx_archive.tar", &config).unwrap();
+        let expected: BTreeMap<usize, u64> = [(2, 1)].iter().cloned().collect();
+        assert_eq!(member_counts["five_strings.fa"], expected);
+
+        //a missing file
+        assert!(gather_tar_stats("./test_data/does_not_exist.tar", &config).is_err());
+    }
+
+    #[test]
+    fn test_not_a_tar_archive() {
+        //FASTX files, whether larger or smaller than a header block, and plain or gzipped
+        for filename in ["./test_data/long_strings.fa", "./test_data/single_string.fa", "./test_data/long_strings.fa.gz"] {
+            let error: String = gather_tar_stats(filename, &LoaderConfig::new()).unwrap_err().to_string();
+            assert!(error.starts_with("Not a tar archive"), "{filename}: {error}");
+        }
+
+        //a corrupted header in an otherwise valid archive
+        let mut archive: Vec<u8> = std::fs::read("./test_data/fastx_archive.tar").unwrap();
+        assert!(header_checksum_matches(archive[..TAR_BLOCK_SIZE].try_into().unwrap()));
+        archive[0] ^= 0x01;
+        assert!(!header_checksum_matches(archive[..TAR_BLOCK_SIZE].try_into().unwrap()));
+        assert!(read_tar_member(&mut &archive[..]).is_err());
+    }
+
+    #[test]
+    fn test_parse_tar_size() {
+        assert_eq!(parse_tar_size(b"00000001750\0").unwrap(), 1000);
+        assert_eq!(parse_tar_size(b"     1750 \0\0").unwrap(), 1000);
+        assert_eq!(parse_tar_size(b"\0\0\0\0\0\0\0\0\0\0\0\0").unwrap(), 0);
+        assert_eq!(parse_tar_size(&[0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x03, 0xe8]).unwrap(), 1000);
+        assert!(parse_tar_size(b"0000000999\0\0").is_err());
+    }
+
+    #[test]
+    fn test_parse_pax_path() {
+        assert_eq!(parse_pax_path(b"30 mtime=1234567890.123456789\n19 path=a/b/reads.fq\n"), Some("a/b/reads.fq".to_string()));
+        assert_eq!(parse_pax_path(b"30 mtime=1234567890.123456789\n"), None);
+    }
+}
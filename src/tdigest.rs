@@ -0,0 +1,278 @@
+
+/// The default compression of a `TDigest`; larger values are more accurate but keep more centroids
+pub const DEFAULT_COMPRESSION: f64 = 100.0;
+
+/// A cluster of nearby values, summarized by their mean and total weight
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Centroid {
+    /// The mean of the values in the cluster
+    mean: f64,
+    /// The number of values in the cluster
+    weight: f64
+}
+
+/// A merging t-digest, a small sketch of a stream of values that can estimate any quantile in a single pass.
+/// The memory used depends only on the compression (at most about `compression` centroids plus a buffer of `5 * compression` values), not on the number of values added.
+/// Estimates are most accurate near the extremes (e.g. the 1st or 99th percentile) and within about a percent of the true rank near the median.
+/// For details, see Dunning and Ertl, "Computing Extremely Accurate Quantiles Using t-Digests".
+/// # Examples
+/// ```
+/// use fastleng::tdigest::TDigest;
+/// let mut digest = TDigest::new(100.0);
+/// for value in 1..=1000 {
+///     digest.add(value as f64, 1);
+/// }
+/// let median: f64 = digest.quantile(0.5);
+/// assert!((median - 500.5).abs() < 10.0);
+/// assert_eq!(digest.count(), 1000);
+/// ```
+#[derive(Clone, Debug)]
+pub struct TDigest {
+    /// Controls the accuracy and size of the digest
+    compression: f64,
+    /// The merged centroids, sorted by mean
+    centroids: Vec<Centroid>,
+    /// Values that have been added but not merged yet
+    buffer: Vec<Centroid>,
+    /// The total weight of everything added
+    total_weight: u64,
+    /// The smallest value added
+    min: f64,
+    /// The largest value added
+    max: f64
+}
+
+impl TDigest {
+    /// Creates an empty digest with the given compression, e.g. `DEFAULT_COMPRESSION`
+    pub fn new(compression: f64) -> TDigest {
+        assert!(compression >= 1.0);
+        TDigest {
+            compression,
+            centroids: vec![],
+            buffer: vec![],
+            total_weight: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY
+        }
+    }
+
+    /// Adds `value` to the digest `weight` times
+    pub fn add(&mut self, value: f64, weight: u64) {
+        if weight == 0 {
+            return;
+        }
+        self.buffer.push(Centroid { mean: value, weight: weight as f64 });
+        self.total_weight += weight;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        if self.buffer.len() as f64 >= 5.0 * self.compression {
+            self.compress();
+        }
+    }
+
+    /// Returns the total weight of all values added
+    pub fn count(&self) -> u64 {
+        self.total_weight
+    }
+
+    /// Returns the smallest value added, or `None` if the digest is empty
+    pub fn min(&self) -> Option<f64> {
+        if self.total_weight == 0 { None } else { Some(self.min) }
+    }
+
+    /// Returns the largest value added, or `None` if the digest is empty
+    pub fn max(&self) -> Option<f64> {
+        if self.total_weight == 0 { None } else { Some(self.max) }
+    }
+
+    /// This will estimate the value at quantile `q` of everything added, returning 0.0 for an empty digest.
+    /// # Arguments
+    /// * `q` - the quantile to estimate, in the range 0.0-1.0 (e.g. 0.5 for the median)
+    pub fn quantile(&mut self, q: f64) -> f64 {
+        assert!((0.0..=1.0).contains(&q));
+        self.compress();
+        if self.centroids.is_empty() {
+            return 0.0;
+        }
+        if self.centroids.len() == 1 {
+            return self.centroids[0].mean;
+        }
+
+        //each centroid is treated as centered on its cumulative weight; interpolate between the two centers around the target
+        let target: f64 = q * self.total_weight as f64;
+        let first: &Centroid = &self.centroids[0];
+        if target < first.weight / 2.0 {
+            return self.min + (first.mean - self.min) * target / (first.weight / 2.0);
+        }
+        let mut cumulative: f64 = 0.0;
+        for pair in self.centroids.windows(2) {
+            let (left, right) = (&pair[0], &pair[1]);
+            let left_center: f64 = cumulative + left.weight / 2.0;
+            let right_center: f64 = cumulative + left.weight + right.weight / 2.0;
+            if target <= right_center {
+                let fraction: f64 = (target - left_center) / (right_center - left_center);
+                return left.mean + (right.mean - left.mean) * fraction;
+            }
+            cumulative += left.weight;
+        }
+
+        //past the last center, interpolate towards the maximum
+        let last: &Centroid = &self.centroids[self.centroids.len() - 1];
+        let last_center: f64 = self.total_weight as f64 - last.weight / 2.0;
+        let remaining: f64 = self.total_weight as f64 - last_center;
+        last.mean + (self.max - last.mean) * ((target - last_center) / remaining).min(1.0)
+    }
+
+    /// Merges any buffered values into the centroids, keeping each centroid within the size limit for its quantile
+    fn compress(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut all_centroids: Vec<Centroid> = std::mem::take(&mut self.centroids);
+        all_centroids.append(&mut self.buffer);
+        all_centroids.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+
+        //each cluster may span one unit of the arcsine scale, so they are small at the tails and large in the middle
+        let total_weight: f64 = self.total_weight as f64;
+        let mut merged: Vec<Centroid> = Vec::with_capacity(all_centroids.len());
+        let mut weight_so_far: f64 = 0.0;
+        let mut q_limit: f64 = self.next_quantile_limit(0.0);
+        let mut current: Centroid = all_centroids[0];
+        for next in all_centroids.into_iter().skip(1) {
+            let proposed_weight: f64 = current.weight + next.weight;
+            if (weight_so_far + proposed_weight) / total_weight <= q_limit {
+                current.mean += (next.mean - current.mean) * next.weight / proposed_weight;
+                current.weight = proposed_weight;
+            } else {
+                weight_so_far += current.weight;
+                q_limit = self.next_quantile_limit(weight_so_far / total_weight);
+                merged.push(current);
+                current = next;
+            }
+        }
+        merged.push(current);
+        self.centroids = merged;
+    }
+
+    /// Returns the largest quantile that a cluster starting at quantile `q` may reach, using the scale `k(q) = compression / (2 * pi) * asin(2q - 1)`
+    fn next_quantile_limit(&self, q: f64) -> f64 {
+        let scale: f64 = self.compression / (2.0 * std::f64::consts::PI);
+        let next_k: f64 = scale * (2.0 * q - 1.0).asin() + 1.0;
+        if next_k >= self.compression / 4.0 {
+            1.0
+        } else {
+            ((next_k / scale).sin() + 1.0) / 2.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampling::SeededRng;
+
+    /// Returns how far quantile `q` is from the range of ranks that `estimate` has in `sorted_values`, as a fraction of all of the values
+    fn rank_error(sorted_values: &[f64], q: f64, estimate: f64) -> f64 {
+        let below: usize = sorted_values.partition_point(|&value| value < estimate);
+        let at_or_below: usize = sorted_values.partition_point(|&value| value <= estimate);
+        let total: f64 = sorted_values.len() as f64;
+        if q < below as f64 / total {
+            below as f64 / total - q
+        } else {
+            (q - at_or_below as f64 / total).max(0.0)
+        }
+    }
+
+    /// Adds every length to a new digest and checks the rank error of each quantile against the exact lengths
+    fn check_rank_errors(mut lengths: Vec<f64>, tolerances: &[(f64, f64)]) {
+        let mut digest = TDigest::new(DEFAULT_COMPRESSION);
+        lengths.iter().for_each(|&seq_len| digest.add(seq_len, 1));
+        lengths.sort_by(|a, b| a.total_cmp(b));
+        for &(q, tolerance) in tolerances.iter() {
+            let estimate: f64 = digest.quantile(q);
+            let error: f64 = rank_error(&lengths, q, estimate);
+            assert!(error <= tolerance, "quantile {q}: estimate {estimate}, rank error {error}");
+        }
+        assert!(digest.centroids.len() <= DEFAULT_COMPRESSION as usize);
+    }
+
+    #[test]
+    fn test_tdigest_uniform() {
+        let mut digest = TDigest::new(DEFAULT_COMPRESSION);
+        for value in 1..=100000 {
+            digest.add(value as f64, 1);
+        }
+        assert_eq!(digest.count(), 100000);
+        assert_eq!(digest.min(), Some(1.0));
+        assert_eq!(digest.max(), Some(100000.0));
+
+        //within half a percent of the true rank everywhere, and much closer at the tails
+        for (q, tolerance) in [(0.5, 500.0), (0.25, 500.0), (0.9, 500.0), (0.01, 50.0), (0.999, 50.0)] {
+            let expected: f64 = q * 100000.0;
+            assert!((digest.quantile(q) - expected).abs() < tolerance, "{q}: {}", digest.quantile(q));
+        }
+        assert_eq!(digest.quantile(0.0), 1.0);
+        assert_eq!(digest.quantile(1.0), 100000.0);
+
+        //the size is bounded no matter how much is added
+        assert!(digest.centroids.len() <= DEFAULT_COMPRESSION as usize);
+    }
+
+    #[test]
+    fn test_tdigest_skewed_lengths() {
+        let mut rng = SeededRng::new(0);
+        //within about a tenth of a percent of the true rank at the tails and half a percent in the middle
+        let tolerances: [(f64, f64); 7] = [(0.001, 0.001), (0.01, 0.0015), (0.25, 0.005), (0.5, 0.005), (0.75, 0.005), (0.99, 0.0015), (0.999, 0.001)];
+
+        //log-normal long reads (median 10kb, most of them within 3-30kb), from Box-Muller normals
+        let log_normal: Vec<f64> = (0..100000).map(|_| {
+            let normal: f64 = (-2.0 * (1.0 - rng.next_f64()).ln()).sqrt() * (2.0 * std::f64::consts::PI * rng.next_f64()).cos();
+            (10000.0 * normal.exp()).round()
+        }).collect();
+        check_rank_errors(log_normal, &tolerances);
+
+        //a Pareto tail where a few reads are orders of magnitude longer than the rest
+        let pareto: Vec<f64> = (0..100000).map(|_| (1000.0 / (1.0 - rng.next_f64()).powf(1.0 / 1.2)).round()).collect();
+        check_rank_errors(pareto, &tolerances);
+
+        //mostly identical short reads with a long tail of long ones, where the median must stay on the short length
+        let short_with_long_tail: Vec<f64> = (0..100000).map(|_| {
+            if rng.next_f64() < 0.9 { 150.0 } else { (1000.0 + 99000.0 * rng.next_f64()).round() }
+        }).collect();
+        check_rank_errors(short_with_long_tail, &tolerances);
+    }
+
+    #[test]
+    fn test_tdigest_weighted() {
+        //lengths of 5 (x10) and 10 (x3) have a median of 5; small inputs are kept exactly
+        let mut digest = TDigest::new(DEFAULT_COMPRESSION);
+        (0..10).for_each(|_| digest.add(5.0, 1));
+        (0..3).for_each(|_| digest.add(10.0, 1));
+        assert_eq!(digest.count(), 13);
+        assert_eq!(digest.quantile(0.5), 5.0);
+        assert_eq!(digest.quantile(1.0), 10.0);
+
+        //a single weighted value is one centroid
+        let mut weighted_digest = TDigest::new(DEFAULT_COMPRESSION);
+        weighted_digest.add(7.0, 4);
+        assert_eq!(weighted_digest.count(), 4);
+        assert_eq!(weighted_digest.quantile(0.3), 7.0);
+
+        //heavy repeats of a discrete value stay exact after compression
+        let mut same_digest = TDigest::new(DEFAULT_COMPRESSION);
+        for _ in 0..10000 {
+            same_digest.add(42.0, 1);
+        }
+        assert_eq!(same_digest.quantile(0.3), 42.0);
+        assert!(same_digest.centroids.len() <= DEFAULT_COMPRESSION as usize);
+    }
+
+    #[test]
+    fn test_tdigest_empty() {
+        let mut digest = TDigest::new(DEFAULT_COMPRESSION);
+        digest.add(1.0, 0);
+        assert_eq!(digest.count(), 0);
+        assert_eq!(digest.min(), None);
+        assert_eq!(digest.quantile(0.5), 0.0);
+    }
+}